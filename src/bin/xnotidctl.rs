@@ -0,0 +1,36 @@
+use std::io::Write;
+use std::process::ExitCode;
+use xnotid::ctl::{self, CtlError};
+use zbus::Connection;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match ctl::parse(&args) {
+        Ok(command) => {
+            let mut out = std::io::stdout().lock();
+            let result = if command.needs_bus() {
+                match Connection::session().await {
+                    Ok(conn) => ctl::run(command, Some(&conn), &mut out).await,
+                    Err(e) => {
+                        log::debug!("No session bus: {}", e);
+                        Err(CtlError::NotRunning)
+                    }
+                }
+            } else {
+                ctl::run(command, None, &mut out).await
+            };
+            let _ = out.flush();
+            result
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(code) => ExitCode::from(code as u8),
+        Err(e) => {
+            eprintln!("xnotidctl: {}", e);
+            ExitCode::from(e.exit_code() as u8)
+        }
+    }
+}
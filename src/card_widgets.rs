@@ -0,0 +1,435 @@
+use crate::notification::DatetimeMode;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use gtk4::prelude::*;
+use gtk4::{
+    Align, Box as GtkBox, Button, Calendar, Entry, Label, Orientation, Overlay, ProgressBar,
+    Scale, ScrolledWindow, SpinButton, TextView, WrapMode,
+};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Sends a card's JSON reply (the `ActionInvoked` action key)
+pub type Submit = Rc<dyn Fn(Value)>;
+
+/// Selected rows of a multiple-choice card, kept in sync with the rows'
+/// "selected" class and check mark. In single mode selecting a row clears
+/// the others. `on_change` runs after every change.
+#[derive(Clone)]
+pub struct ChoiceSelection {
+    selected: Rc<RefCell<HashSet<String>>>,
+    /// (choice id, row, check mark) in display order
+    rows: Rc<RefCell<Vec<(String, GtkBox, Label)>>>,
+    single: bool,
+    on_change: Rc<dyn Fn(&HashSet<String>)>,
+}
+
+impl ChoiceSelection {
+    pub fn new(single: bool, on_change: impl Fn(&HashSet<String>) + 'static) -> Self {
+        Self {
+            selected: Rc::default(),
+            rows: Rc::default(),
+            single,
+            on_change: Rc::new(on_change),
+        }
+    }
+
+    pub fn add_row(&self, id: &str, row: &GtkBox, check: &Label) {
+        self.rows
+            .borrow_mut()
+            .push((id.to_string(), row.clone(), check.clone()));
+    }
+
+    /// Id of the row at `index`, for the number hotkeys
+    pub fn id_at(&self, index: usize) -> Option<String> {
+        self.rows.borrow().get(index).map(|(id, _, _)| id.clone())
+    }
+
+    pub fn is_selected(&self, id: &str) -> bool {
+        self.selected.borrow().contains(id)
+    }
+
+    /// Selected ids in display order
+    pub fn selected(&self) -> Vec<String> {
+        let selected = self.selected.borrow();
+        self.rows
+            .borrow()
+            .iter()
+            .filter(|(id, _, _)| selected.contains(id))
+            .map(|(id, _, _)| id.clone())
+            .collect()
+    }
+
+    pub fn toggle(&self, id: &str) {
+        self.set(id, !self.is_selected(id));
+    }
+
+    pub fn set(&self, id: &str, on: bool) {
+        {
+            let mut selected = self.selected.borrow_mut();
+            for (row_id, row, check) in self.rows.borrow().iter() {
+                let now = if row_id == id {
+                    on
+                } else {
+                    selected.contains(row_id) && !(on && self.single)
+                };
+                if now {
+                    selected.insert(row_id.clone());
+                    row.add_css_class("selected");
+                    check.set_text("✓");
+                } else {
+                    selected.remove(row_id);
+                    row.remove_css_class("selected");
+                    check.set_text("");
+                }
+            }
+        }
+        self.changed();
+    }
+
+    /// Run `on_change`, e.g. after something it reads besides the selection
+    pub fn changed(&self) {
+        (self.on_change)(&self.selected.borrow());
+    }
+}
+
+/// A bar draining towards a card's deadline, with the seconds left. The
+/// store answers the card when it runs out.
+pub fn countdown(created_at: DateTime<Utc>, seconds: u64) -> ProgressBar {
+    let bar = ProgressBar::new();
+    bar.set_widget_name("notification-card-countdown");
+    bar.set_show_text(true);
+
+    let update = move |bar: &ProgressBar| {
+        let elapsed = (Utc::now() - created_at).num_seconds().max(0) as u64;
+        let left = seconds.saturating_sub(elapsed);
+        bar.set_fraction(left as f64 / seconds.max(1) as f64);
+        bar.set_text(Some(&format!("{}s left", left)));
+        left > 0
+    };
+    update(&bar);
+
+    let weak = bar.downgrade();
+    glib2::timeout_add_seconds_local(1, move || match weak.upgrade() {
+        Some(bar) if update(&bar) => glib2::ControlFlow::Continue,
+        _ => glib2::ControlFlow::Break,
+    });
+    bar
+}
+
+/// Right-aligned row holding the card's buttons
+fn button_row() -> GtkBox {
+    let row = GtkBox::new(Orientation::Horizontal, 4);
+    row.set_widget_name("notification-actions");
+    row.set_halign(Align::End);
+    row
+}
+
+fn card_button(label: &str, class: &str) -> Button {
+    let btn = Button::with_label(label);
+    btn.set_css_classes(&["notification-action", class]);
+    btn
+}
+
+/// Deny, allow and (optionally) allow-always buttons, with an optional
+/// reason entry. Replies `{"type":"permission","decision":..,"reason":..}`.
+pub fn permission(
+    allow_label: &str,
+    deny_label: &str,
+    allow_always_label: Option<&str>,
+    ask_reason: bool,
+    submit: Submit,
+) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 4);
+
+    let reason = ask_reason.then(|| {
+        let entry = Entry::new();
+        entry.set_widget_name("notification-card-reason");
+        entry.set_placeholder_text(Some("Reason (optional)"));
+        entry.set_hexpand(true);
+        container.append(&entry);
+        entry
+    });
+
+    let row = button_row();
+    let mut buttons = vec![(deny_label, "deny", "notification-deny")];
+    buttons.push((allow_label, "allow", "notification-submit"));
+    if let Some(label) = allow_always_label {
+        buttons.push((label, "allow_always", "notification-submit"));
+    }
+    for (label, decision, class) in buttons {
+        let btn = card_button(label, class);
+        let submit = submit.clone();
+        let reason = reason.clone();
+        btn.connect_clicked(move |_| {
+            let reason = reason
+                .as_ref()
+                .map(|entry| entry.text().trim().to_string())
+                .filter(|text| !text.is_empty());
+            submit(json!({ "type": "permission", "decision": decision, "reason": reason }));
+        });
+        row.append(&btn);
+    }
+    container.append(&row);
+    container
+}
+
+/// A text entry (or text view when `multiline`) with a Submit button that
+/// stays disabled until the answer is non-empty and matches `pattern`.
+/// Replies `{"type":"text-input","text":..}`.
+pub fn text_input(
+    placeholder: Option<&str>,
+    multiline: bool,
+    pattern: Option<&str>,
+    pattern_error: Option<&str>,
+    submit: Submit,
+) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 4);
+    // Anchored so the whole answer has to match
+    let pattern = pattern.and_then(|p| Regex::new(&format!("^(?:{})$", p)).ok());
+    let error_text = pattern_error
+        .unwrap_or("The answer doesn't have the expected format")
+        .to_string();
+
+    let error = Label::new(None);
+    error.set_widget_name("notification-card-error");
+    error.set_css_classes(&["notification-card-error"]);
+    error.set_halign(Align::Start);
+    error.set_wrap(true);
+    error.set_visible(false);
+
+    let submit_btn = card_button("Submit", "notification-submit");
+    submit_btn.set_sensitive(false);
+
+    let entry = (!multiline).then(Entry::new);
+    let view = multiline.then(TextView::new);
+    let read: Rc<dyn Fn() -> String> = {
+        let entry = entry.clone();
+        let view = view.clone();
+        Rc::new(move || match (&entry, &view) {
+            (Some(entry), _) => entry.text().to_string(),
+            (None, Some(view)) => {
+                let buffer = view.buffer();
+                let (start, end) = buffer.bounds();
+                buffer.text(&start, &end, false).to_string()
+            }
+            (None, None) => String::new(),
+        })
+    };
+
+    // Enable Submit and show the error as the answer changes
+    let check = {
+        let read = read.clone();
+        let btn = submit_btn.clone();
+        let error = error.clone();
+        Rc::new(move || {
+            let text = read();
+            let matches = pattern.as_ref().is_none_or(|re| re.is_match(&text));
+            btn.set_sensitive(!text.trim().is_empty() && matches);
+            error.set_text(&error_text);
+            error.set_visible(!text.is_empty() && !matches);
+        })
+    };
+
+    if let Some(entry) = entry {
+        entry.set_widget_name("notification-card-text");
+        entry.set_hexpand(true);
+        if let Some(placeholder) = placeholder {
+            entry.set_placeholder_text(Some(placeholder));
+        }
+        let check = check.clone();
+        entry.connect_changed(move |_| check());
+        let btn = submit_btn.clone();
+        entry.connect_activate(move |_| {
+            if btn.is_sensitive() {
+                btn.emit_clicked();
+            }
+        });
+        container.append(&entry);
+    }
+    if let Some(view) = view {
+        view.set_widget_name("notification-card-text");
+        view.set_wrap_mode(WrapMode::WordChar);
+        view.set_accepts_tab(false);
+        let scroll = ScrolledWindow::new();
+        scroll.set_min_content_height(60);
+        scroll.set_child(Some(&view));
+
+        // TextView has no placeholder of its own: lay a dimmed label over
+        // it while the buffer is empty. Clicks pass through to the view.
+        let overlay = Overlay::new();
+        overlay.set_child(Some(&scroll));
+        let hint = placeholder.map(|placeholder| {
+            let hint = Label::new(Some(placeholder));
+            hint.set_widget_name("notification-card-placeholder");
+            hint.set_css_classes(&["dim-label"]);
+            hint.set_halign(Align::Start);
+            hint.set_valign(Align::Start);
+            hint.set_can_target(false);
+            overlay.add_overlay(&hint);
+            hint
+        });
+        view.buffer().connect_changed(move |buffer| {
+            if let Some(ref hint) = hint {
+                hint.set_visible(buffer.char_count() == 0);
+            }
+            check();
+        });
+        container.append(&overlay);
+    }
+    container.append(&error);
+
+    submit_btn.connect_clicked(move |_| {
+        submit(json!({ "type": "text-input", "text": read() }));
+    });
+    let row = button_row();
+    row.append(&submit_btn);
+    container.append(&row);
+    container
+}
+
+/// Two buttons. Replies `{"type":"confirm","confirmed":..,"label":..}`.
+pub fn confirm(confirm_label: &str, cancel_label: &str, submit: Submit) -> GtkBox {
+    let row = button_row();
+    for (label, confirmed, class) in [
+        (cancel_label, false, "notification-deny"),
+        (confirm_label, true, "notification-submit"),
+    ] {
+        let btn = card_button(label, class);
+        let submit = submit.clone();
+        let label = label.to_string();
+        btn.connect_clicked(move |_| {
+            submit(json!({ "type": "confirm", "confirmed": confirmed, "label": label }));
+        });
+        row.append(&btn);
+    }
+    row
+}
+
+/// `max` stars; clicking one answers. Replies
+/// `{"type":"rating","value":..,"max":..}`.
+pub fn rating(max: u8, submit: Submit) -> GtkBox {
+    let row = GtkBox::new(Orientation::Horizontal, 2);
+    row.set_widget_name("notification-card-rating");
+    let stars: Vec<Button> = (1..=max)
+        .map(|value| {
+            let star = Button::with_label("☆");
+            star.set_css_classes(&["notification-card-star"]);
+            star.set_tooltip_text(Some(&format!("{} of {}", value, max)));
+            row.append(&star);
+            star
+        })
+        .collect();
+
+    let stars = Rc::new(stars);
+    for (idx, star) in stars.iter().enumerate() {
+        // Fill the stars up to the hovered one
+        let hover = gtk4::EventControllerMotion::new();
+        let lit = stars.clone();
+        hover.connect_enter(move |_, _, _| {
+            for (other, star) in lit.iter().enumerate() {
+                star.set_label(if other <= idx { "★" } else { "☆" });
+            }
+        });
+        let unlit = stars.clone();
+        hover.connect_leave(move |_| {
+            for star in unlit.iter() {
+                star.set_label("☆");
+            }
+        });
+        star.add_controller(hover);
+
+        let submit = submit.clone();
+        let value = idx + 1;
+        star.connect_clicked(move |_| {
+            submit(json!({ "type": "rating", "value": value, "max": max }));
+        });
+    }
+    row
+}
+
+/// A slider with a Submit button. Replies `{"type":"slider","value":..}`.
+pub fn slider(min: f64, max: f64, step: f64, value: Option<f64>, submit: Submit) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 4);
+    let scale = Scale::with_range(Orientation::Horizontal, min, max, step);
+    scale.set_widget_name("notification-card-slider");
+    scale.set_draw_value(true);
+    scale.set_hexpand(true);
+    scale.set_value(value.unwrap_or(min));
+    container.append(&scale);
+
+    let btn = card_button("Submit", "notification-submit");
+    btn.connect_clicked(move |_| {
+        submit(json!({ "type": "slider", "value": scale.value() }));
+    });
+    let row = button_row();
+    row.append(&btn);
+    container.append(&row);
+    container
+}
+
+/// A calendar and/or hour and minute spinners with a Submit button.
+/// Replies `{"type":"datetime","value":..}` formatted per `mode`.
+pub fn datetime(mode: DatetimeMode, value: Option<&str>, submit: Submit) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 4);
+    let initial = value
+        .and_then(|v| mode.parse(v))
+        .unwrap_or_else(|| Local::now().naive_local());
+
+    let calendar = (mode != DatetimeMode::Time).then(|| {
+        let calendar = Calendar::new();
+        calendar.set_widget_name("notification-card-calendar");
+        let date = glib2::DateTime::from_local(
+            initial.year(),
+            initial.month() as i32,
+            initial.day() as i32,
+            0,
+            0,
+            0.0,
+        );
+        if let Ok(date) = date {
+            calendar.select_day(&date);
+        }
+        container.append(&calendar);
+        calendar
+    });
+
+    let clock = (mode != DatetimeMode::Date).then(|| {
+        let row = GtkBox::new(Orientation::Horizontal, 4);
+        row.set_widget_name("notification-card-time");
+        let hour = SpinButton::with_range(0.0, 23.0, 1.0);
+        hour.set_value(initial.hour().into());
+        let minute = SpinButton::with_range(0.0, 59.0, 1.0);
+        minute.set_value(initial.minute().into());
+        for spin in [&hour, &minute] {
+            spin.set_wrap(true);
+            spin.set_width_chars(2);
+        }
+        row.append(&hour);
+        row.append(&Label::new(Some(":")));
+        row.append(&minute);
+        container.append(&row);
+        (hour, minute)
+    });
+
+    let btn = card_button("Submit", "notification-submit");
+    btn.connect_clicked(move |_| {
+        let date = calendar.as_ref().map_or(initial.date(), |calendar| {
+            let day = calendar.date();
+            let (month, day_of_month) = (day.month() as u32, day.day_of_month() as u32);
+            NaiveDate::from_ymd_opt(day.year(), month, day_of_month).unwrap_or(initial.date())
+        });
+        let time = clock.as_ref().map_or(initial.time(), |(hour, minute)| {
+            let (hour, minute) = (hour.value_as_int() as u32, minute.value_as_int() as u32);
+            NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or(initial.time())
+        });
+        let value = NaiveDateTime::new(date, time).format(mode.format()).to_string();
+        submit(json!({ "type": "datetime", "value": value }));
+    });
+    let row = button_row();
+    row.append(&btn);
+    container.append(&row);
+    container
+}
@@ -0,0 +1,108 @@
+use crate::config::{self, Config, ConfigError, Overrides};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: xnotid [OPTIONS]
+
+Options:
+  -c, --config PATH       Read config.yaml from PATH            [XNOTID_CONFIG]
+  -s, --style PATH        Read style.css from PATH              [XNOTID_STYLE]
+      --log-path PATH     Write the notification log to PATH    [XNOTID_LOG_PATH]
+  -p, --profile NAME      Use the config set in profiles/NAME   [XNOTID_PROFILE]
+      --no-tray           Don't show the tray icon
+      --replace           Take over from a running notification daemon
+      --check-config [PATH]
+                          Validate config.yaml (or PATH) and exit
+  -h, --help              Show this help
+  -V, --version           Show the version
+
+Options take precedence over the environment variables in brackets.
+";
+
+/// What the `xnotid` command line asked for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Run the notification daemon
+    #[default]
+    Run,
+    /// Validate a config file and exit; None = the usual config.yaml
+    CheckConfig(Option<PathBuf>),
+    Help,
+    Version,
+}
+
+/// Everything given on the daemon's command line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub mode: Mode,
+    /// Only what the command line set; see `Overrides::or` for the
+    /// environment fallback
+    pub overrides: Overrides,
+    pub no_tray: bool,
+    pub replace: bool,
+}
+
+/// Parse the daemon's arguments (without the program name)
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter().peekable();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let takes_value = matches!(
+            flag,
+            "--config" | "--style" | "--log-path" | "--profile" | "--check-config"
+        );
+        if inline.is_some() && !takes_value {
+            return Err(format!("{} doesn't take a value", flag));
+        }
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+
+        match flag {
+            "-c" | "--config" => options.overrides.config = Some(value(flag)?.into()),
+            "-s" | "--style" => options.overrides.style = Some(value(flag)?.into()),
+            "--log-path" => options.overrides.log_path = Some(value(flag)?),
+            "-p" | "--profile" => {
+                let name = value(flag)?;
+                if !config::valid_profile(&name) {
+                    return Err(format!("invalid profile name '{}'", name));
+                }
+                options.overrides.profile = Some(name);
+            }
+            "--no-tray" => options.no_tray = true,
+            "--replace" => options.replace = true,
+            "--check-config" => {
+                let path = inline.clone().or_else(|| {
+                    args.next_if(|next| !next.starts_with('-')).cloned()
+                });
+                options.mode = Mode::CheckConfig(path.map(PathBuf::from));
+            }
+            "-h" | "--help" => options.mode = Mode::Help,
+            "-V" | "--version" => options.mode = Mode::Version,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Validate the config at `path` (or config.yaml), returning the verdict to
+/// print: a one-line confirmation, or every problem found.
+pub fn check_config(path: Option<PathBuf>) -> Result<String, String> {
+    let explicit = path.is_some();
+    let path = path.unwrap_or_else(Config::config_path);
+    if !explicit && !path.exists() {
+        return Ok(format!("{}: not found, defaults apply", path.display()));
+    }
+    match Config::check_file(&path) {
+        Ok(_) => Ok(format!("{}: OK", path.display())),
+        Err(errors) => Err(ConfigError::report(&path, &errors)),
+    }
+}
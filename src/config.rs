@@ -1,17 +1,24 @@
+use crate::dnd::DndWindow;
+use crate::rules::Rule;
 use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default = "default_monitor")]
     pub monitor: i32,
 
-    #[serde(default = "default_position_x")]
-    pub position_x: String, // "right", "left", "center"
+    #[serde(default)]
+    pub position_x: PositionX,
 
-    #[serde(default = "default_position_y")]
-    pub position_y: String, // "top", "bottom"
+    #[serde(default)]
+    pub position_y: PositionY,
 
     #[serde(default = "default_popup_width")]
     pub popup_width: i32,
@@ -23,10 +30,10 @@ pub struct Config {
     pub spacing: i32,
 
     #[serde(default = "default_margin")]
-    pub margin_top: i32,
+    pub margin_top: i32, // gap to the anchored top/bottom edge
 
     #[serde(default = "default_margin")]
-    pub margin_right: i32,
+    pub margin_right: i32, // gap to the anchored right/left edge
 
     #[serde(default = "default_max_visible")]
     pub max_visible: i32,
@@ -67,16 +74,96 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub dnd_enabled: bool, // whether DND feature is available
 
+    #[serde(default)]
+    pub dnd_schedule: Vec<DndWindow>, // recurring DND windows, local time
+
+    #[serde(default)]
+    pub dnd_allow_apps: Vec<String>, // app names whose popups bypass DND
+
+    #[serde(default)]
+    pub dnd_block_apps: Vec<String>, // app names held back by DND even when critical
+
+    #[serde(default)]
+    pub dnd_fullscreen: bool, // DND while a fullscreen window is focused (X11)
+
+    #[serde(default = "default_true")]
+    pub sound_enabled: bool,
+
+    #[serde(default = "default_sound_command")]
+    pub sound_command: Vec<String>, // player; the sound file is appended
+
+    #[serde(default = "default_sound_theme")]
+    pub sound_theme: String, // freedesktop sound theme for `sound-name`
+
+    #[serde(default)]
+    pub sound_low: Option<String>, // default sounds by urgency: a path or theme name
+
+    #[serde(default)]
+    pub sound_normal: Option<String>,
+
+    #[serde(default)]
+    pub sound_critical: Option<String>,
+
+    #[serde(default)]
+    pub sound_apps: HashMap<String, String>, // app name -> sound, "" = silent
+
+    #[serde(default = "default_sound_min_interval_ms")]
+    pub sound_min_interval_ms: u32, // at most one sound per interval
+
     #[serde(default = "default_true")]
     pub log_enabled: bool,
 
     #[serde(default = "default_log_path")]
     pub log_path: String,
+
+    #[serde(default = "default_log_rotate_size_kb")]
+    pub log_rotate_size_kb: u64, // rotate once the log reaches this size, 0 = never
+
+    #[serde(default)]
+    pub log_rotate_days: u32, // rotate once the log is this many days old, 0 = never
+
+    #[serde(default = "default_log_keep")]
+    pub log_keep: usize, // rotated segments to keep (log_path.1 is the newest)
+
+    #[serde(default)]
+    pub log_compress: bool, // gzip rotated segments
+
+    #[serde(default)]
+    pub log_max_age_days: u32, // purge records older than this at startup, 0 = keep
+
+    #[serde(default)]
+    pub log_redact_apps: Vec<String>, // app names whose body text is not logged
+
+    #[serde(default = "default_true")]
+    pub persist_history: bool, // restore center contents after a restart
+
+    #[serde(default = "default_state_path")]
+    pub state_path: String,
+
+    #[serde(default)]
+    pub rules: Vec<Rule>, // per-app / per-pattern overrides, see README
+}
+
+/// Which vertical screen edge (or the middle) popups are anchored to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionX {
+    Left,
+    Center,
+    #[default]
+    Right,
+}
+
+/// Which horizontal screen edge popups are anchored to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionY {
+    #[default]
+    Top,
+    Bottom,
 }
 
 fn default_monitor() -> i32 { 0 }
-fn default_position_x() -> String { "right".into() }
-fn default_position_y() -> String { "top".into() }
 fn default_popup_width() -> i32 { 400 }
 fn default_slot_height() -> i32 { 75 }
 fn default_spacing() -> i32 { 8 }
@@ -91,20 +178,30 @@ fn default_scroll_speed() -> f64 { 3.0 }
 fn default_max_popup_height() -> i32 { 600 }
 fn default_max_center_height() -> i32 { 600 }
 fn default_true() -> bool { true }
+fn default_log_rotate_size_kb() -> u64 { 10 * 1024 }
+fn default_log_keep() -> usize { 5 }
+fn default_sound_command() -> Vec<String> { vec!["paplay".to_string()] }
+fn default_sound_theme() -> String { "freedesktop".to_string() }
+fn default_sound_min_interval_ms() -> u32 { 500 }
 
 fn default_log_path() -> String {
-    let mut p = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
-    p.push("xnotid");
+    let mut p = Overrides::current().data_dir();
     p.push("notifications.jsonl");
     p.to_string_lossy().into_owned()
 }
 
+fn default_state_path() -> String {
+    let mut p = Overrides::current().data_dir();
+    p.push("state.json");
+    p.to_string_lossy().into_owned()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             monitor: default_monitor(),
-            position_x: default_position_x(),
-            position_y: default_position_y(),
+            position_x: PositionX::default(),
+            position_y: PositionY::default(),
             popup_width: default_popup_width(),
             slot_height: default_slot_height(),
             spacing: default_spacing(),
@@ -123,43 +220,176 @@ impl Default for Config {
             max_popup_height: default_max_popup_height(),
             max_center_height: default_max_center_height(),
             dnd_enabled: true,
+            dnd_schedule: Vec::new(),
+            dnd_allow_apps: Vec::new(),
+            dnd_block_apps: Vec::new(),
+            dnd_fullscreen: false,
+            sound_enabled: true,
+            sound_command: default_sound_command(),
+            sound_theme: default_sound_theme(),
+            sound_low: None,
+            sound_normal: None,
+            sound_critical: None,
+            sound_apps: HashMap::new(),
+            sound_min_interval_ms: default_sound_min_interval_ms(),
             log_enabled: true,
             log_path: default_log_path(),
+            log_rotate_size_kb: default_log_rotate_size_kb(),
+            log_rotate_days: 0,
+            log_keep: default_log_keep(),
+            log_compress: false,
+            log_max_age_days: 0,
+            log_redact_apps: Vec::new(),
+            persist_history: true,
+            state_path: default_state_path(),
+            rules: Vec::new(),
         }
     }
 }
 
 impl Config {
     pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| {
+            log::warn!("Failed to parse config: {e}, using defaults");
+            Config::default()
+        })
+    }
+
+    /// Like `load`, but a config file that can't be read, parsed or
+    /// validated is an error instead of falling back to defaults.
+    pub fn try_load() -> Result<Self, String> {
         let config_path = Self::config_path();
-        if config_path.exists() {
-            let contents = fs::read_to_string(&config_path).unwrap_or_default();
-            serde_yaml::from_str(&contents).unwrap_or_else(|e| {
-                log::warn!("Failed to parse config: {e}, using defaults");
-                Config::default()
-            })
+        let mut config = if config_path.exists() {
+            Self::check_file(&config_path)
+                .map_err(|errors| ConfigError::report(&config_path, &errors))?
         } else {
             log::info!("No config file found at {:?}, using defaults", config_path);
             Config::default()
+        };
+        if let Some(log_path) = &Overrides::current().log_path {
+            config.log_path = log_path.clone();
         }
+        Ok(config)
+    }
+
+    /// Read, parse and validate the config file at `path`
+    pub fn check_file(path: &Path) -> Result<Self, Vec<ConfigError>> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| vec![ConfigError::new(e.to_string())])?;
+        Self::parse(&contents)
+    }
+
+    /// Parse and validate config.yaml contents. Unknown keys, malformed
+    /// values and out-of-range settings are all errors; a file holding only
+    /// comments gives the defaults.
+    pub fn parse(text: &str) -> Result<Self, Vec<ConfigError>> {
+        let blank = text.lines().all(|l| {
+            let l = l.trim();
+            l.is_empty() || l.starts_with('#')
+        });
+        if blank {
+            return Ok(Config::default());
+        }
+
+        let config: Config =
+            serde_yaml::from_str(text).map_err(|e| vec![ConfigError::from_yaml(&e)])?;
+        let errors = config.validate(text);
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Range and consistency checks serde can't express. `text` is the
+    /// source the config came from, used to point at the offending key.
+    pub fn validate(&self, text: &str) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, key: &str, message: String| {
+            if !ok {
+                errors.push(ConfigError::at_key(text, key, None, format!("`{}` {}", key, message)));
+            }
+        };
+
+        let positive = |value: i32| format!("must be greater than 0, got {}", value);
+        check(self.popup_width > 0, "popup_width", positive(self.popup_width));
+        check(self.slot_height > 0, "slot_height", positive(self.slot_height));
+        let at_least_one = format!("must be at least 1, got {}", self.max_visible);
+        check(self.max_visible >= 1, "max_visible", at_least_one);
+        for (key, value) in [
+            ("monitor", self.monitor),
+            ("spacing", self.spacing),
+            ("margin_top", self.margin_top),
+            ("margin_right", self.margin_right),
+            ("max_popup_height", self.max_popup_height),
+            ("max_center_height", self.max_center_height),
+        ] {
+            check(value >= 0, key, format!("must not be negative, got {}", value));
+        }
+        for (key, value) in [
+            ("font_size_pct", self.font_size_pct),
+            ("scroll_speed", self.scroll_speed),
+        ] {
+            let valid = value.is_finite() && value > 0.0;
+            check(valid, key, format!("must be greater than 0, got {}", value));
+        }
+        check(
+            !self.log_enabled || !self.log_path.trim().is_empty(),
+            "log_path",
+            "must not be empty while `log_enabled` is on".into(),
+        );
+        check(
+            !self.sound_enabled || self.sound_command.first().is_some_and(|c| !c.trim().is_empty()),
+            "sound_command",
+            "must name a program while `sound_enabled` is on".into(),
+        );
+        check(
+            !self.persist_history || !self.state_path.trim().is_empty(),
+            "state_path",
+            "must not be empty while `persist_history` is on".into(),
+        );
+
+        for (idx, window) in self.dnd_schedule.iter().enumerate() {
+            let at = |message: String| {
+                let message = format!("`dnd_schedule[{}]` {}", idx, message);
+                ConfigError::at_key(text, "dnd_schedule", Some(idx), message)
+            };
+            if window.times().is_none() {
+                errors.push(at(format!(
+                    "needs `start` and `end` as HH:MM, got \"{}\" and \"{}\"",
+                    window.start, window.end
+                )));
+            }
+            if window.weekdays().is_none() {
+                let days = format!("has an unknown day in {:?}, expected mon..sun", window.days);
+                errors.push(at(days));
+            }
+        }
+
+        for (idx, rule) in self.rules.iter().enumerate() {
+            if let Err(e) = rule.validate() {
+                errors.push(ConfigError::at_key(
+                    text,
+                    "rules",
+                    Some(idx),
+                    format!("`rules[{}]` has an invalid pattern: {}", idx, e),
+                ));
+            }
+        }
+
+        errors
     }
 
     pub fn config_dir() -> PathBuf {
-        let mut p = dirs::config_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
-        p.push("xnotid");
-        p
+        Overrides::current().config_dir()
     }
 
     pub fn config_path() -> PathBuf {
-        let mut p = Self::config_dir();
-        p.push("config.yaml");
-        p
+        Overrides::current().config_path()
     }
 
     pub fn css_path() -> PathBuf {
-        let mut p = Self::config_dir();
-        p.push("style.css");
-        p
+        Overrides::current().css_path()
     }
 
     pub fn timeout_for_urgency(&self, urgency: u8) -> u32 {
@@ -170,3 +400,229 @@ impl Config {
         }
     }
 }
+
+/// Where this instance keeps its files when it isn't the usual daemon, set
+/// from the command line or the `XNOTID_CONFIG`, `XNOTID_STYLE`,
+/// `XNOTID_LOG_PATH` and `XNOTID_PROFILE` environment variables. A profile
+/// gets its own directory under both `~/.config/xnotid/profiles/` and
+/// `~/.local/share/xnotid/profiles/`, so it shares nothing with the default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    pub config: Option<PathBuf>,
+    pub style: Option<PathBuf>,
+    pub log_path: Option<String>,
+    pub profile: Option<String>,
+}
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+impl Overrides {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let profile = var("XNOTID_PROFILE").filter(|name| {
+            let valid = valid_profile(name);
+            if !valid {
+                log::warn!("Ignoring XNOTID_PROFILE={:?}: not a valid profile name", name);
+            }
+            valid
+        });
+        Self {
+            config: var("XNOTID_CONFIG").map(PathBuf::from),
+            style: var("XNOTID_STYLE").map(PathBuf::from),
+            log_path: var("XNOTID_LOG_PATH"),
+            profile,
+        }
+    }
+
+    /// `self`, with anything it leaves unset taken from `fallback`
+    pub fn or(self, fallback: Overrides) -> Self {
+        Self {
+            config: self.config.or(fallback.config),
+            style: self.style.or(fallback.style),
+            log_path: self.log_path.or(fallback.log_path),
+            profile: self.profile.or(fallback.profile),
+        }
+    }
+
+    /// Use these for the rest of the process. Only works before the first
+    /// path lookup; returns false if the overrides were already fixed.
+    pub fn install(self) -> bool {
+        OVERRIDES.set(self).is_ok()
+    }
+
+    /// The installed overrides, or those from the environment
+    pub fn current() -> &'static Overrides {
+        OVERRIDES.get_or_init(Self::from_env)
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        let mut p = dirs::config_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        p.push("xnotid");
+        if let Some(profile) = &self.profile {
+            p.push("profiles");
+            p.push(profile);
+        }
+        p
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.config.clone().unwrap_or_else(|| self.config_dir().join("config.yaml"))
+    }
+
+    pub fn css_path(&self) -> PathBuf {
+        self.style.clone().unwrap_or_else(|| self.config_dir().join("style.css"))
+    }
+
+    /// Directory for the default log and state files
+    pub fn data_dir(&self) -> PathBuf {
+        let mut p = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        p.push("xnotid");
+        if let Some(profile) = &self.profile {
+            p.push("profiles");
+            p.push(profile);
+        }
+        p
+    }
+}
+
+/// Profile names become a directory name, so no separators or dot-names
+pub fn valid_profile(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+/// One problem found in config.yaml, with its 1-based position when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { line: None, column: None, message: message.into() }
+    }
+
+    /// Translate a serde_yaml error, moving its position out of the message
+    /// and suggesting the closest name for a misspelt key or value.
+    pub fn from_yaml(err: &serde_yaml::Error) -> Self {
+        let mut message = err.to_string();
+        let location = err.location();
+        if let Some(loc) = &location {
+            let suffix = format!(" at line {} column {}", loc.line(), loc.column());
+            message = message.replacen(&suffix, "", 1);
+        }
+        Self {
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+            message: suggest_fix(&message).unwrap_or(message),
+        }
+    }
+
+    /// An error about the top-level `key`, or the `item`th entry of the list
+    /// under it, positioned at that line of `text` if it can be found there.
+    fn at_key(text: &str, key: &str, item: Option<usize>, message: String) -> Self {
+        let position = find_key(text, key).map(|line| match item {
+            Some(idx) => find_item(text, line, idx).unwrap_or((line, 1)),
+            None => (line, 1),
+        });
+        Self {
+            line: position.map(|p| p.0),
+            column: position.map(|p| p.1),
+            message,
+        }
+    }
+
+    /// All errors, one per line, prefixed with the file they came from
+    pub fn report(path: &Path, errors: &[ConfigError]) -> String {
+        errors
+            .iter()
+            .map(|e| match e.line {
+                Some(_) => format!("{}:{}", path.display(), e),
+                None => format!("{}: {}", path.display(), e),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}: {}", line, column, self.message),
+            (Some(line), None) => write!(f, "{}: {}", line, self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// 1-based line of a top-level `key:` in `text`
+fn find_key(text: &str, key: &str) -> Option<usize> {
+    text.lines().position(|l| {
+        l.strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with(':'))
+    })
+    .map(|idx| idx + 1)
+}
+
+/// 1-based line and column of the `idx`th `- ` entry of the block list that
+/// starts after line `key_line`
+fn find_item(text: &str, key_line: usize, idx: usize) -> Option<(usize, usize)> {
+    let mut indent = None;
+    let mut seen = 0;
+    for (n, line) in text.lines().enumerate().skip(key_line) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let col = line.len() - trimmed.len();
+        if col == 0 && !trimmed.starts_with('-') {
+            break;
+        }
+        if trimmed.starts_with('-') && *indent.get_or_insert(col) == col {
+            if seen == idx {
+                return Some((n + 1, col + 1));
+            }
+            seen += 1;
+        }
+    }
+    None
+}
+
+/// For serde's "unknown field/variant `x`, expected ..." messages, replace
+/// the list of every valid name with the one closest to `x`, if any is close.
+fn suggest_fix(message: &str) -> Option<String> {
+    let re = Regex::new(r"unknown (?:field|variant) `([^`]*)`, (?:expected|there are no)").ok()?;
+    let caps = re.captures(message)?;
+    let word = &caps[1];
+    let candidates = &message[caps.get(0)?.end()..];
+    let best = Regex::new(r"`([^`]*)`")
+        .ok()?
+        .captures_iter(candidates)
+        .map(|c| c.get(1).map_or("", |m| m.as_str()).to_string())
+        .map(|name| (edit_distance(word, &name), name))
+        .min()?;
+    if best.0 > (word.len() / 3).max(2) {
+        return None;
+    }
+    let head = &message[..caps.get(1)?.end() + 1];
+    Some(format!("{}, did you mean `{}`?", head, best.1))
+}
+
+/// Levenshtein distance between two names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let next = (prev + usize::from(ca != cb)).min(row[j] + 1).min(row[j + 1] + 1);
+            prev = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
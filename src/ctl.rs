@@ -0,0 +1,1014 @@
+use crate::config::Config;
+use crate::dbus_server::NotificationRow;
+use crate::history::{self, HistoryQuery};
+use crate::notification::{CARD_HINT, CARD_VERSION, CloseReason, LogEntry, NotificationCard};
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
+use zbus::export::futures_core::Stream;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+use zbus::zvariant::Value;
+use zbus::{proxy, Connection};
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_RUNNING: i32 = 3;
+/// `ask`: the prompt was dismissed without an answer
+pub const EXIT_DENIED: i32 = 4;
+/// `ask`: no answer within `--timeout`
+pub const EXIT_TIMEOUT: i32 = 5;
+
+pub const USAGE: &str = "\
+Usage: xnotidctl <command> [options]
+
+Commands:
+  toggle                          toggle the notification center
+  dnd on|off|toggle|status        Do Not Disturb
+  list [--json]                   notifications in the center
+  dismiss <id> | --all            dismiss one or all notifications
+  action <id> <key>               invoke an action of a notification
+  history [--grep TEXT] [--app APP] [--limit N] [--json] [--log PATH]
+                                  search the notification log
+  reload                          re-read config.yaml
+  watch                           print the status as a JSON line now and
+                                  on every change (for bar widgets)
+  send [options] <summary> [body] send a notification (see `send --help`)
+  ask [options] <summary> [question]
+                                  send a card and wait for the answer
+                                  (see `ask --help`)
+
+Exit status: 0 ok, 1 failure, 2 usage error, 3 xnotid not running,
+4 prompt denied, 5 prompt timed out.
+";
+
+pub const SEND_USAGE: &str = "\
+Usage: xnotidctl send [options] <summary> [body]
+
+Options (notify-send compatible):
+  -a, --app-name NAME             sending application (default xnotidctl)
+  -i, --icon ICON                 icon name or path
+  -u, --urgency low|normal|critical
+  -t, --expire-time MS            popup timeout, 0 = never expire
+  -c, --category CATEGORY
+  -e, --transient                 don't keep it in the center
+  -r, --replace-id ID             replace an existing notification
+  -A, --action [KEY=]LABEL        add an action button (repeatable)
+  -h, --hint TYPE:NAME:VALUE      TYPE is int, double, string, byte or boolean
+  -p, --print-id                  print the notification id
+
+xnotid extensions:
+      --group NAME                x-group hint
+      --css-class CLASS           x-css-class hint
+      --acknowledge               x-acknowledge hint: stay until dismissed
+      --card JSON                 send a card in the x-xnotid-card hint; the body
+                                  (default: its question) is the fallback text
+";
+
+pub const ASK_USAGE: &str = "\
+Usage: xnotidctl ask [options] <summary> [question]
+
+Sends a card and waits until it is answered or dismissed. Without --card
+a permission card asking <question> (default: the summary) is sent, or a
+multiple-choice card when --choice is given. Takes all `send` options.
+
+Options:
+      --choice [ID=]LABEL         add a choice (repeatable)
+      --allow-other               add a free-text answer to the choices
+      --allow-label LABEL         label of the permission card's allow button
+      --deny-label LABEL          label of its deny button
+      --allow-always              add an \"allow always\" button
+      --timeout SECS              give up (and close the card) after SECS
+      --json                      print the answer as JSON
+
+Output: the decision (\"allow\", \"allow_always\" or \"deny\") and reason, the
+chosen ids and free-text answer one per line, the text, rating, slider or
+date value, \"confirm\" or \"cancel\", the invoked action key, \"deny\" or
+\"timeout\".
+Exit status: 0 answered, 4 denied, cancelled or dismissed, 5 timed out
+(1-3 as for send).
+";
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.xnotid.Control",
+    default_service = "org.xnotid.Control",
+    default_path = "/org/xnotid/Control"
+)]
+pub trait Control {
+    fn toggle_center(&self) -> zbus::Result<()>;
+    fn set_dnd(&self, on: bool) -> zbus::Result<()>;
+    fn get_dnd(&self) -> zbus::Result<bool>;
+    fn dismiss_all(&self) -> zbus::Result<()>;
+    fn dismiss(&self, id: u32) -> zbus::Result<()>;
+    fn invoke_action(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+    fn list_notifications(&self) -> zbus::Result<Vec<NotificationRow>>;
+    fn reload_config(&self) -> zbus::Result<()>;
+    fn get_status(&self) -> zbus::Result<String>;
+
+    #[zbus(signal)]
+    fn status_changed(&self, status: &str) -> zbus::Result<()>;
+}
+
+#[derive(Debug)]
+pub enum CtlError {
+    Usage(String),
+    NotRunning,
+    Bus(zbus::Error),
+    Io(io::Error),
+}
+
+impl CtlError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CtlError::Usage(_) => EXIT_USAGE,
+            CtlError::NotRunning => EXIT_NOT_RUNNING,
+            CtlError::Bus(_) | CtlError::Io(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for CtlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CtlError::Usage(msg) => write!(f, "{}", msg),
+            CtlError::NotRunning => write!(f, "xnotid is not running"),
+            // Errors returned by the daemon carry a readable message
+            CtlError::Bus(zbus::Error::MethodError(_, Some(msg), _)) => write!(f, "{}", msg),
+            CtlError::Bus(e) => write!(f, "{}", e),
+            CtlError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<zbus::Error> for CtlError {
+    fn from(e: zbus::Error) -> Self {
+        CtlError::Bus(e)
+    }
+}
+
+impl From<zbus::fdo::Error> for CtlError {
+    fn from(e: zbus::fdo::Error) -> Self {
+        CtlError::Bus(e.into())
+    }
+}
+
+impl From<io::Error> for CtlError {
+    fn from(e: io::Error) -> Self {
+        CtlError::Io(e)
+    }
+}
+
+fn usage(msg: impl Into<String>) -> CtlError {
+    CtlError::Usage(msg.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DndOp {
+    On,
+    Off,
+    Toggle,
+    Status,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HintValue {
+    Int(i32),
+    Double(f64),
+    Str(String),
+    Byte(u8),
+    Bool(bool),
+}
+
+impl HintValue {
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            HintValue::Int(v) => Value::from(*v),
+            HintValue::Double(v) => Value::from(*v),
+            HintValue::Str(v) => Value::from(v.as_str()),
+            HintValue::Byte(v) => Value::from(*v),
+            HintValue::Bool(v) => Value::from(*v),
+        }
+    }
+}
+
+/// Arguments of `xnotidctl send`, mapped onto a Notify call
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendArgs {
+    pub app_name: String,
+    pub icon: String,
+    pub summary: String,
+    pub body: String,
+    pub expire_timeout: i32,
+    pub replaces_id: u32,
+    /// (key, label) pairs
+    pub actions: Vec<(String, String)>,
+    pub hints: Vec<(String, HintValue)>,
+    pub print_id: bool,
+}
+
+impl SendArgs {
+    /// Attach a card envelope as the `x-xnotid-card` hint, with its question
+    /// as the body unless one was given
+    fn add_card(&mut self, (envelope, question): (String, String)) {
+        self.hints.push((CARD_HINT.to_string(), HintValue::Str(envelope)));
+        if self.body.is_empty() {
+            self.body = question;
+        }
+    }
+}
+
+impl Default for SendArgs {
+    fn default() -> Self {
+        Self {
+            app_name: "xnotidctl".to_string(),
+            icon: String::new(),
+            summary: String::new(),
+            body: String::new(),
+            expire_timeout: -1,
+            replaces_id: 0,
+            actions: Vec::new(),
+            hints: Vec::new(),
+            print_id: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryArgs {
+    pub query: HistoryQuery,
+    pub limit: usize,
+    pub json: bool,
+    /// Log to read; None = `log_path` from config.yaml
+    pub log: Option<PathBuf>,
+}
+
+/// Arguments of `xnotidctl ask`: a card to send and how to wait for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AskArgs {
+    pub send: SendArgs,
+    /// None = wait until the card is answered or dismissed
+    pub timeout: Option<Duration>,
+    pub json: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Help(&'static str),
+    Toggle,
+    Dnd(DndOp),
+    List { json: bool },
+    /// None = all
+    Dismiss(Option<u32>),
+    Action { id: u32, key: String },
+    History(HistoryArgs),
+    Reload,
+    Watch,
+    Send(SendArgs),
+    Ask(AskArgs),
+}
+
+impl Command {
+    /// Whether the command talks to the daemon (help and history don't)
+    pub fn needs_bus(&self) -> bool {
+        !matches!(self, Command::Help(_) | Command::History(_))
+    }
+}
+
+/// Split `--flag=value` into its parts; short flags and plain words are kept whole.
+fn split_flag(arg: &str) -> (&str, Option<&str>) {
+    if arg.starts_with("--")
+        && let Some((flag, value)) = arg.split_once('=')
+    {
+        return (flag, Some(value));
+    }
+    (arg, None)
+}
+
+/// Walks the arguments of one subcommand
+struct Args<'a> {
+    iter: std::slice::Iter<'a, String>,
+}
+
+impl<'a> Args<'a> {
+    fn new(args: &'a [String]) -> Self {
+        Self { iter: args.iter() }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.iter.next().map(String::as_str)
+    }
+
+    /// Value of `flag`, given inline (`--flag=value`) or as the next argument
+    fn value(&mut self, flag: &str, inline: Option<&'a str>) -> Result<&'a str, CtlError> {
+        inline
+            .or_else(|| self.next())
+            .ok_or_else(|| usage(format!("{} needs a value", flag)))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(what: &str, value: &str) -> Result<T, CtlError> {
+    value
+        .parse()
+        .map_err(|_| usage(format!("invalid {}: {:?}", what, value)))
+}
+
+/// Parse the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Command, CtlError> {
+    let Some((name, rest)) = args.split_first() else {
+        return Err(usage(USAGE));
+    };
+    let mut rest_args = Args::new(rest);
+
+    let command = match name.as_str() {
+        "help" | "-h" | "--help" => return Ok(Command::Help(USAGE)),
+        "toggle" => Command::Toggle,
+        "dnd" => {
+            let op = match rest_args.next() {
+                Some("on") => DndOp::On,
+                Some("off") => DndOp::Off,
+                Some("toggle") => DndOp::Toggle,
+                Some("status") | None => DndOp::Status,
+                Some(other) => return Err(usage(format!("unknown dnd mode {:?}", other))),
+            };
+            Command::Dnd(op)
+        }
+        "list" => {
+            let mut json = false;
+            while let Some(arg) = rest_args.next() {
+                match arg {
+                    "--json" => json = true,
+                    other => return Err(usage(format!("unexpected argument {:?}", other))),
+                }
+            }
+            Command::List { json }
+        }
+        "dismiss" => match rest_args.next() {
+            Some("--all") => Command::Dismiss(None),
+            Some(id) => Command::Dismiss(Some(parse_number("id", id)?)),
+            None => return Err(usage("dismiss needs an id or --all")),
+        },
+        "action" => match (rest_args.next(), rest_args.next()) {
+            (Some(id), Some(key)) => Command::Action {
+                id: parse_number("id", id)?,
+                key: key.to_string(),
+            },
+            _ => return Err(usage("action needs an id and an action key")),
+        },
+        "history" => Command::History(parse_history(&mut rest_args)?),
+        "reload" => Command::Reload,
+        "watch" => Command::Watch,
+        "send" => return parse_send(&mut rest_args, false),
+        "ask" => return parse_send(&mut rest_args, true),
+        other => return Err(usage(format!("unknown command {:?}\n\n{}", other, USAGE))),
+    };
+
+    if let Some(extra) = rest_args.next() {
+        return Err(usage(format!("unexpected argument {:?}", extra)));
+    }
+    Ok(command)
+}
+
+fn parse_history(args: &mut Args<'_>) -> Result<HistoryArgs, CtlError> {
+    let mut history = HistoryArgs {
+        query: HistoryQuery::default(),
+        limit: 20,
+        json: false,
+        log: None,
+    };
+    while let Some(arg) = args.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--grep" | "-g" => history.query.text = Some(args.value(flag, inline)?.to_string()),
+            "--app" => history.query.app_name = Some(args.value(flag, inline)?.to_string()),
+            "--limit" | "-n" => history.limit = parse_number("limit", args.value(flag, inline)?)?,
+            "--log" => history.log = Some(PathBuf::from(args.value(flag, inline)?)),
+            "--json" => history.json = true,
+            other => return Err(usage(format!("unexpected argument {:?}", other))),
+        }
+    }
+    Ok(history)
+}
+
+/// Parse `send`, or `ask` which takes the same options plus its own.
+fn parse_send(args: &mut Args<'_>, ask: bool) -> Result<Command, CtlError> {
+    let help = if ask { ASK_USAGE } else { SEND_USAGE };
+    let mut send = SendArgs::default();
+    let mut positional = Vec::new();
+    let mut card = None;
+    let mut choices = Vec::new();
+    let mut allow_other = false;
+    let mut allow_label = None;
+    let mut deny_label = None;
+    let mut allow_always = false;
+    let mut timeout = None;
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = split_flag(arg);
+        match flag {
+            "--help" => return Ok(Command::Help(help)),
+            "-a" | "--app-name" => send.app_name = args.value(flag, inline)?.to_string(),
+            "-i" | "--icon" => send.icon = args.value(flag, inline)?.to_string(),
+            "-u" | "--urgency" => {
+                let level: u8 = match args.value(flag, inline)? {
+                    "low" | "0" => 0,
+                    "normal" | "1" => 1,
+                    "critical" | "2" => 2,
+                    other => return Err(usage(format!("unknown urgency {:?}", other))),
+                };
+                send.hints.push(("urgency".to_string(), HintValue::Byte(level)));
+            }
+            "-t" | "--expire-time" => {
+                send.expire_timeout = parse_number("expire time", args.value(flag, inline)?)?
+            }
+            "-c" | "--category" => {
+                let category = args.value(flag, inline)?.to_string();
+                send.hints.push(("category".to_string(), HintValue::Str(category)));
+            }
+            "-e" | "--transient" => send.hints.push(("transient".to_string(), HintValue::Bool(true))),
+            "-r" | "--replace-id" => send.replaces_id = parse_number("id", args.value(flag, inline)?)?,
+            "-A" | "--action" => {
+                let action = args.value(flag, inline)?;
+                // Like notify-send, an action without a key is keyed by its index
+                let (key, label) = match action.split_once('=') {
+                    Some((key, label)) => (key.to_string(), label.to_string()),
+                    None => (send.actions.len().to_string(), action.to_string()),
+                };
+                send.actions.push((key, label));
+            }
+            "-h" | "--hint" => send.hints.push(parse_hint(args.value(flag, inline)?)?),
+            "-p" | "--print-id" => send.print_id = true,
+            "--group" => {
+                let group = args.value(flag, inline)?.to_string();
+                send.hints.push(("x-group".to_string(), HintValue::Str(group)));
+            }
+            "--css-class" => {
+                let class = args.value(flag, inline)?.to_string();
+                send.hints.push(("x-css-class".to_string(), HintValue::Str(class)));
+            }
+            "--acknowledge" => send.hints.push(("x-acknowledge".to_string(), HintValue::Bool(true))),
+            "--card" => card = Some(card_hint(args.value(flag, inline)?)?),
+            "--choice" if ask => {
+                let choice = args.value(flag, inline)?;
+                let (id, label) = match choice.split_once('=') {
+                    Some((id, label)) => (id.to_string(), label.to_string()),
+                    None => (choice.to_string(), choice.to_string()),
+                };
+                choices.push(serde_json::json!({ "id": id, "label": label }));
+            }
+            "--allow-other" if ask => allow_other = true,
+            "--allow-label" if ask => allow_label = Some(args.value(flag, inline)?.to_string()),
+            "--deny-label" if ask => deny_label = Some(args.value(flag, inline)?.to_string()),
+            "--allow-always" if ask => allow_always = true,
+            "--timeout" if ask => {
+                let secs: u64 = parse_number("timeout", args.value(flag, inline)?)?;
+                timeout = (secs > 0).then(|| Duration::from_secs(secs));
+            }
+            "--json" if ask => json = true,
+            "--" => positional.extend(args.iter.by_ref().cloned()),
+            other if other.starts_with('-') && other.len() > 1 => {
+                return Err(usage(format!("unknown option {:?}", other)));
+            }
+            other => positional.push(other.to_string()),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    send.summary = positional
+        .next()
+        .ok_or_else(|| usage(format!("a summary is required\n\n{}", help)))?;
+    if let Some(body) = positional.next() {
+        send.body = body;
+    }
+    if let Some(extra) = positional.next() {
+        return Err(usage(format!("unexpected argument {:?}", extra)));
+    }
+    if !ask {
+        if let Some(card) = card {
+            send.add_card(card);
+        }
+        return Ok(Command::Send(send));
+    }
+
+    let card = match card {
+        Some(card) => card,
+        None => {
+            let question = if send.body.is_empty() { &send.summary } else { &send.body };
+            let card = if choices.is_empty() {
+                let mut card = serde_json::json!({ "type": "permission", "question": question });
+                if let Some(label) = allow_label {
+                    card["allow_label"] = label.into();
+                }
+                if let Some(label) = deny_label {
+                    card["deny_label"] = label.into();
+                }
+                if allow_always {
+                    card["allow_always"] = true.into();
+                }
+                card
+            } else {
+                serde_json::json!({
+                    "type": "multiple-choice",
+                    "question": question,
+                    "choices": choices,
+                    "allow_other": allow_other,
+                })
+            };
+            card_hint(&card.to_string())?
+        }
+    };
+    send.add_card(card);
+    Ok(Command::Ask(AskArgs { send, timeout, json }))
+}
+
+/// Parse a notify-send style `TYPE:NAME:VALUE` hint
+pub fn parse_hint(spec: &str) -> Result<(String, HintValue), CtlError> {
+    let mut parts = spec.splitn(3, ':');
+    let (Some(kind), Some(name), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(usage(format!("hint {:?} is not TYPE:NAME:VALUE", spec)));
+    };
+    let value = match kind {
+        "int" => HintValue::Int(parse_number("int hint", value)?),
+        "double" => HintValue::Double(parse_number("double hint", value)?),
+        "string" => HintValue::Str(value.to_string()),
+        "byte" => HintValue::Byte(parse_number("byte hint", value)?),
+        "boolean" => HintValue::Bool(parse_number("boolean hint", value)?),
+        other => return Err(usage(format!("unknown hint type {:?}", other))),
+    };
+    Ok((name.to_string(), value))
+}
+
+/// Check a card given on the command line and wrap it in the current
+/// envelope. Returns the envelope and the card's question.
+fn card_hint(json: &str) -> Result<(String, String), CtlError> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| usage(format!("invalid card JSON: {}", e)))?;
+    let card = NotificationCard::from_envelope(value.clone())
+        .map_err(|e| usage(format!("invalid card: {}", e)))?;
+    // Already an envelope: send it as given
+    let envelope = if value.get("xnotid_card").is_some() {
+        value
+    } else {
+        serde_json::json!({ "xnotid_card": format!("v{}", CARD_VERSION), "card": value })
+    };
+    Ok((envelope.to_string(), card.question().to_string()))
+}
+
+/// Fail with `NotRunning` unless `name` is owned on the bus.
+async fn ensure_running(conn: &Connection, name: &str) -> Result<(), CtlError> {
+    let name = BusName::try_from(name).map_err(zbus::Error::from)?;
+    if DBusProxy::new(conn).await?.name_has_owner(name).await? {
+        Ok(())
+    } else {
+        Err(CtlError::NotRunning)
+    }
+}
+
+async fn control(conn: &Connection) -> Result<ControlProxy<'_>, CtlError> {
+    ensure_running(conn, "org.xnotid.Control").await?;
+    Ok(ControlProxy::new(conn).await?)
+}
+
+/// Run `command`, writing its output to `out`, and return the exit status.
+/// `conn` is only used by commands that need the daemon.
+pub async fn run(command: Command, conn: Option<&Connection>, out: &mut impl Write) -> Result<i32, CtlError> {
+    let conn = match command {
+        Command::Help(text) => {
+            write!(out, "{}", text)?;
+            return Ok(0);
+        }
+        Command::History(ref history) => {
+            print_history(history, out)?;
+            return Ok(0);
+        }
+        _ => conn.ok_or(CtlError::NotRunning)?,
+    };
+
+    match command {
+        Command::Toggle => control(conn).await?.toggle_center().await?,
+        Command::Dnd(op) => {
+            let control = control(conn).await?;
+            match op {
+                DndOp::On => control.set_dnd(true).await?,
+                DndOp::Off => control.set_dnd(false).await?,
+                DndOp::Toggle => {
+                    let on = !control.get_dnd().await?;
+                    control.set_dnd(on).await?;
+                    writeln!(out, "{}", on_off(on))?;
+                }
+                DndOp::Status => writeln!(out, "{}", on_off(control.get_dnd().await?))?,
+            }
+        }
+        Command::List { json } => {
+            let rows = control(conn).await?.list_notifications().await?;
+            print_list(&rows, json, out)?;
+        }
+        Command::Dismiss(Some(id)) => control(conn).await?.dismiss(id).await?,
+        Command::Dismiss(None) => control(conn).await?.dismiss_all().await?,
+        Command::Action { id, ref key } => control(conn).await?.invoke_action(id, key).await?,
+        Command::Reload => control(conn).await?.reload_config().await?,
+        Command::Send(ref send) => {
+            let id = send_notification(conn, send).await?;
+            if send.print_id {
+                writeln!(out, "{}", id)?;
+            }
+        }
+        Command::Watch => return watch(conn, out).await,
+        Command::Ask(ref ask) => return run_ask(conn, ask, out).await,
+        Command::Help(_) | Command::History(_) => unreachable!(),
+    }
+    Ok(0)
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+/// Send `send` through org.freedesktop.Notifications and return the id.
+pub async fn send_notification(conn: &Connection, send: &SendArgs) -> Result<u32, CtlError> {
+    ensure_running(conn, "org.freedesktop.Notifications").await?;
+    notify(&NotificationsProxy::new(conn).await?, send).await
+}
+
+async fn notify(proxy: &NotificationsProxy<'_>, send: &SendArgs) -> Result<u32, CtlError> {
+    let actions: Vec<&str> = send
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect();
+    let hints: HashMap<&str, Value<'_>> = send
+        .hints
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.to_value()))
+        .collect();
+    let id = proxy
+        .notify(
+            &send.app_name,
+            send.replaces_id,
+            &send.icon,
+            &send.summary,
+            &send.body,
+            &actions,
+            hints,
+            send.expire_timeout,
+        )
+        .await?;
+    Ok(id)
+}
+
+/// How a prompt sent by `ask` ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// The plain "allow" action key of older permission cards
+    Allow,
+    /// A permission card's decision: "allow", "allow_always" or "deny"
+    Permission {
+        decision: String,
+        reason: Option<String>,
+    },
+    /// A multiple-choice submission: chosen (id, label) pairs and free text
+    Choice {
+        selected: Vec<(String, String)>,
+        other: Option<String>,
+    },
+    /// The reply of any other card type, e.g. "text-input" or "rating"
+    Card {
+        kind: String,
+        payload: serde_json::Value,
+    },
+    /// Any other action key
+    Action(String),
+    /// Dismissed or closed without an answer
+    Deny,
+    /// Expired, or no answer within `--timeout`
+    Timeout,
+}
+
+impl Answer {
+    /// Interpret the key of an `ActionInvoked` signal
+    pub fn from_action_key(key: &str) -> Self {
+        if key == "allow" {
+            return Answer::Allow;
+        }
+        let Ok(payload) = serde_json::from_str::<serde_json::Value>(key) else {
+            return Answer::Action(key.to_string());
+        };
+        let Some(kind) = payload["type"].as_str() else {
+            return Answer::Action(key.to_string());
+        };
+        match kind {
+            "multiple-choice" => {
+                let selected = payload["selected"]
+                    .as_array()
+                    .map(|items| {
+                        items
+                            .iter()
+                            .filter_map(|item| {
+                                let id = item["id"].as_str()?;
+                                let label = item["label"].as_str().unwrap_or(id);
+                                Some((id.to_string(), label.to_string()))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Answer::Choice {
+                    selected,
+                    other: payload["other"].as_str().map(str::to_string),
+                }
+            }
+            "permission" => Answer::Permission {
+                decision: payload["decision"].as_str().unwrap_or("deny").to_string(),
+                reason: payload["reason"].as_str().map(str::to_string),
+            },
+            _ => Answer::Card {
+                kind: kind.to_string(),
+                payload,
+            },
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Answer::Permission { decision, .. } if decision == "deny" => EXIT_DENIED,
+            Answer::Card { kind, payload } if kind == "confirm" && payload["confirmed"] != true => {
+                EXIT_DENIED
+            }
+            Answer::Allow
+            | Answer::Permission { .. }
+            | Answer::Choice { .. }
+            | Answer::Card { .. }
+            | Answer::Action(_) => 0,
+            Answer::Deny => EXIT_DENIED,
+            Answer::Timeout => EXIT_TIMEOUT,
+        }
+    }
+
+    fn to_json(&self, id: u32) -> serde_json::Value {
+        match self {
+            Answer::Allow => serde_json::json!({ "id": id, "result": "allow" }),
+            Answer::Permission { decision, reason } => {
+                serde_json::json!({ "id": id, "result": decision, "reason": reason })
+            }
+            Answer::Choice { selected, other } => serde_json::json!({
+                "id": id,
+                "result": "choice",
+                "selected": selected
+                    .iter()
+                    .map(|(id, label)| serde_json::json!({ "id": id, "label": label }))
+                    .collect::<Vec<_>>(),
+                "other": other,
+            }),
+            Answer::Card { kind, payload } => {
+                // The card's reply, with "type" moved to "result"
+                let mut json = serde_json::json!({ "id": id, "result": kind });
+                if let (Some(json), Some(fields)) = (json.as_object_mut(), payload.as_object()) {
+                    for (key, value) in fields.iter().filter(|(key, _)| *key != "type") {
+                        json.insert(key.clone(), value.clone());
+                    }
+                }
+                json
+            }
+            Answer::Action(key) => serde_json::json!({ "id": id, "result": "action", "action_key": key }),
+            Answer::Deny => serde_json::json!({ "id": id, "result": "deny" }),
+            Answer::Timeout => serde_json::json!({ "id": id, "result": "timeout" }),
+        }
+    }
+
+    fn print(&self, id: u32, json: bool, out: &mut impl Write) -> io::Result<()> {
+        if json {
+            return writeln!(out, "{}", self.to_json(id));
+        }
+        match self {
+            Answer::Allow => writeln!(out, "allow"),
+            Answer::Permission { decision, reason } => {
+                writeln!(out, "{}", decision)?;
+                match reason {
+                    Some(reason) => writeln!(out, "{}", reason),
+                    None => Ok(()),
+                }
+            }
+            Answer::Choice { selected, other } => {
+                for (id, _) in selected {
+                    writeln!(out, "{}", id)?;
+                }
+                match other {
+                    Some(text) => writeln!(out, "{}", text),
+                    None => Ok(()),
+                }
+            }
+            Answer::Card { kind, payload } => match kind.as_str() {
+                "text-input" => writeln!(out, "{}", payload["text"].as_str().unwrap_or("")),
+                "confirm" if payload["confirmed"] == true => writeln!(out, "confirm"),
+                "confirm" => writeln!(out, "cancel"),
+                _ => match &payload["value"] {
+                    serde_json::Value::String(value) => writeln!(out, "{}", value),
+                    value => writeln!(out, "{}", value),
+                },
+            },
+            Answer::Action(key) => writeln!(out, "{}", key),
+            Answer::Deny => writeln!(out, "deny"),
+            Answer::Timeout => writeln!(out, "timeout"),
+        }
+    }
+}
+
+/// Next item of a signal stream; None once the connection is gone
+async fn next_signal<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// Print the status, then again on every `StatusChanged`, until xnotid exits.
+async fn watch(conn: &Connection, out: &mut impl Write) -> Result<i32, CtlError> {
+    let control = control(conn).await?;
+    let mut changes = control.receive_status_changed().await?;
+    let mut owner = DBusProxy::new(conn)
+        .await?
+        .receive_name_owner_changed_with_args(&[(0, "org.xnotid.Control")])
+        .await?;
+
+    writeln!(out, "{}", control.get_status().await?)?;
+    out.flush()?;
+    loop {
+        tokio::select! {
+            biased;
+            Some(signal) = next_signal(&mut changes) => {
+                writeln!(out, "{}", signal.args()?.status)?;
+                out.flush()?;
+            }
+            Some(signal) = next_signal(&mut owner) => {
+                if signal.args()?.new_owner.is_none() {
+                    return Err(CtlError::NotRunning);
+                }
+            }
+            else => return Err(CtlError::NotRunning),
+        }
+    }
+}
+
+/// Send the card and block until it is answered, dismissed or timed out.
+/// Exits as not running if the daemon goes away or is replaced meanwhile.
+async fn run_ask(conn: &Connection, ask: &AskArgs, out: &mut impl Write) -> Result<i32, CtlError> {
+    ensure_running(conn, "org.freedesktop.Notifications").await?;
+    let proxy = NotificationsProxy::new(conn).await?;
+    // Subscribe before sending so a quick answer can't be missed
+    let mut actions = proxy.receive_action_invoked().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+    let mut owner = DBusProxy::new(conn)
+        .await?
+        .receive_name_owner_changed_with_args(&[(0, "org.freedesktop.Notifications")])
+        .await?;
+    let id = notify(&proxy, &ask.send).await?;
+
+    let wait = async {
+        loop {
+            // An answer is followed by NotificationClosed; check actions first
+            tokio::select! {
+                biased;
+                Some(signal) = next_signal(&mut actions) => {
+                    let args = signal.args()?;
+                    if args.id == id {
+                        return Ok(Answer::from_action_key(args.action_key));
+                    }
+                }
+                Some(signal) = next_signal(&mut closed) => {
+                    let args = signal.args()?;
+                    if args.id == id {
+                        return Ok(if args.reason == CloseReason::Expired as u32 {
+                            Answer::Timeout
+                        } else {
+                            Answer::Deny
+                        });
+                    }
+                }
+                // The name was owned when we sent, so any change means the
+                // daemon holding our card exited or was replaced
+                Some(_) = next_signal(&mut owner) => return Err(CtlError::NotRunning),
+                else => return Err(CtlError::NotRunning),
+            }
+        }
+    };
+
+    let answer = match ask.timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, wait).await {
+            Ok(answer) => answer?,
+            Err(_) => {
+                let _ = proxy.close_notification(id).await;
+                Answer::Timeout
+            }
+        },
+        None => wait.await?,
+    };
+    answer.print(id, ask.json, out)?;
+    Ok(answer.exit_code())
+}
+
+fn print_list(rows: &[NotificationRow], json: bool, out: &mut impl Write) -> io::Result<()> {
+    if json {
+        let rows: Vec<_> = rows
+            .iter()
+            .map(|(id, app, summary, urgency, age)| {
+                serde_json::json!({
+                    "id": id,
+                    "app_name": app,
+                    "summary": summary,
+                    "urgency": urgency,
+                    "age": age,
+                })
+            })
+            .collect();
+        return writeln!(out, "{}", serde_json::Value::from(rows));
+    }
+
+    for (id, app, summary, urgency, age) in rows {
+        writeln!(
+            out,
+            "{:<6} {:>4}  {:<8}  {:<16}  {}",
+            id,
+            format_age(*age),
+            urgency,
+            app,
+            summary
+        )?;
+    }
+    Ok(())
+}
+
+/// Compact age like "42s", "5m", "3h" or "2d"
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+/// Print the newest matching log records, oldest of them first.
+fn print_history(history: &HistoryArgs, out: &mut impl Write) -> Result<(), CtlError> {
+    let path = match history.log {
+        Some(ref path) => path.clone(),
+        None => PathBuf::from(Config::load().log_path),
+    };
+    let mut entries = history::read_page(&path, &history.query, None, history.limit)?.entries;
+    entries.reverse();
+
+    if history.json {
+        let json = serde_json::to_string(&entries).map_err(io::Error::other)?;
+        writeln!(out, "{}", json)?;
+        return Ok(());
+    }
+    for entry in &entries {
+        writeln!(out, "{}", history_line(entry))?;
+    }
+    Ok(())
+}
+
+fn history_line(entry: &LogEntry) -> String {
+    let time = DateTime::parse_from_rfc3339(&entry.timestamp)
+        .map(|ts| ts.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| entry.timestamp.clone());
+    let mut line = format!(
+        "{}  {:<9}  {:<16}  {}",
+        time,
+        entry.event,
+        entry.app_name.as_deref().unwrap_or(""),
+        entry.summary.as_deref().unwrap_or("")
+    );
+    if let Some(ref key) = entry.action_key {
+        line.push_str(&format!(" [{}]", key));
+    }
+    line
+}
+
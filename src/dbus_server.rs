@@ -1,22 +1,62 @@
-use crate::notification::{CloseReason, Notification};
+use crate::config::Config;
+use crate::notification::{CloseReason, Notification, Urgency};
 use crate::store::SharedStore;
+use chrono::Utc;
 use std::collections::HashMap;
-use std::sync::mpsc::{Receiver, Sender};
+use std::pin::Pin;
+use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedReceiver;
+use zbus::export::futures_core::Stream;
+use zbus::fdo::{self, RequestNameFlags, RequestNameReply};
+use zbus::names::BusName;
 use zbus::object_server::SignalEmitter;
-use zbus::zvariant::OwnedValue;
+use zbus::zvariant::{OwnedValue, Value};
 use zbus::{interface, Connection};
-use gtk4::glib as glib2;
+
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+const CONTROL_PATH: &str = "/org/xnotid/Control";
+
+/// One ListNotifications entry: (id, app_name, summary, urgency, age in seconds)
+pub type NotificationRow = (u32, String, String, String, u64);
 
 /// Commands that can be sent from D-Bus to the UI thread
 #[derive(Debug)]
 pub enum UiCommand {
     ToggleCenter,
+    ShowCenter,
+    HideCenter,
+    /// The store has a reloaded config; re-read geometry and CSS
+    ApplyConfig,
+    /// Leave the main loop so the daemon can shut down cleanly
+    Quit,
 }
 
+/// What `GetCapabilities` advertises: only what the UI actually honours
+pub const CAPABILITIES: &[&str] = &[
+    "actions",
+    "action-icons",
+    "body",
+    "body-markup",
+    "icon-static",
+    "inline-reply",
+    "persistence",
+    "sound",
+    // Cards (up to notification::CARD_VERSION) in the x-xnotid-card hint
+    "x-xnotid-card-version",
+];
+
 /// Signals that should be emitted on D-Bus (sent from UI thread)
 #[derive(Debug)]
 pub enum DbusSignal {
     ActionInvoked { id: u32, action_key: String },
+    NotificationReplied { id: u32, text: String },
+    NotificationClosed { id: u32, reason: CloseReason },
+    DndChanged(bool),
+    CountChanged(u32),
+    PausedChanged(bool),
+    CenterVisibleChanged(bool),
+    /// Store status as JSON, see `store::Status`
+    StatusChanged(String),
 }
 
 /// The D-Bus notification server implementing org.freedesktop.Notifications
@@ -34,14 +74,7 @@ impl NotificationServer {
 impl NotificationServer {
     /// Returns the capabilities of this notification server.
     fn get_capabilities(&self) -> Vec<String> {
-        vec![
-            "body".into(),
-            "body-markup".into(),
-            "body-images".into(),
-            "actions".into(),
-            "persistence".into(),
-            "icon-static".into(),
-        ]
+        CAPABILITIES.iter().map(|cap| cap.to_string()).collect()
     }
 
     /// Sends a notification. Returns the notification ID.
@@ -76,14 +109,7 @@ impl NotificationServer {
 
         let mut store = self.store.lock().unwrap();
         let id = store.add(noti, replaces_id);
-        drop(store);
-
-        // Trigger UI update via glib main context
-        let store_clone = self.store.clone();
-        glib2::idle_add_once(move || {
-            let s = store_clone.lock().unwrap();
-            s.notify_change();
-        });
+        store.notify_change();
 
         id
     }
@@ -124,91 +150,449 @@ impl NotificationServer {
         id: u32,
         action_key: &str,
     ) -> zbus::Result<()>;
+
+    /// Signal: NotificationReplied(id, text), for the `inline-reply` action
+    #[zbus(signal)]
+    async fn notification_replied(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        text: &str,
+    ) -> zbus::Result<()>;
 }
 
 /// Control interface for xnotid-specific commands
 pub struct ControlServer {
+    store: SharedStore,
     cmd_tx: Sender<UiCommand>,
 }
 
 impl ControlServer {
-    pub fn new(cmd_tx: Sender<UiCommand>) -> Self {
-        Self { cmd_tx }
+    pub fn new(store: SharedStore, cmd_tx: Sender<UiCommand>) -> Self {
+        Self { store, cmd_tx }
+    }
+
+    fn send(&self, cmd: UiCommand) {
+        let _ = self.cmd_tx.send(cmd);
     }
 }
 
+pub fn urgency_name(urgency: Urgency) -> &'static str {
+    match urgency {
+        Urgency::Low => "low",
+        Urgency::Normal => "normal",
+        Urgency::Critical => "critical",
+    }
+}
+
+fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+    value
+        .into()
+        .try_to_owned()
+        .expect("plain values carry no file descriptors")
+}
+
+fn unknown_id(id: u32) -> fdo::Error {
+    fdo::Error::InvalidArgs(format!("No notification with id {}", id))
+}
+
 #[interface(name = "org.xnotid.Control")]
 impl ControlServer {
     /// Toggle the notification center visibility
     fn toggle_center(&self) {
         log::info!("ToggleCenter requested via D-Bus");
-        let _ = self.cmd_tx.send(UiCommand::ToggleCenter);
+        self.send(UiCommand::ToggleCenter);
+    }
+
+    fn show_center(&self) {
+        self.send(UiCommand::ShowCenter);
+    }
+
+    fn hide_center(&self) {
+        self.send(UiCommand::HideCenter);
+    }
+
+    /// Turn Do Not Disturb on or off (the same switch as the header button)
+    fn set_dnd(&self, on: bool) {
+        let mut store = self.store.lock().unwrap();
+        store.set_dnd(on);
+        store.notify_change();
+    }
+
+    /// Whether DND is in effect, including scheduled and fullscreen DND
+    fn get_dnd(&self) -> bool {
+        self.store.lock().unwrap().dnd_active()
+    }
+
+    fn dismiss_all(&self) {
+        let mut store = self.store.lock().unwrap();
+        store.clear_all();
+        store.notify_change();
+    }
+
+    fn dismiss(&self, id: u32) -> fdo::Result<()> {
+        let mut store = self.store.lock().unwrap();
+        store.close(id, CloseReason::Dismissed).ok_or_else(|| unknown_id(id))?;
+        store.notify_change();
+        Ok(())
     }
+
+    /// Invoke an action as if its button was clicked
+    fn invoke_action(&self, id: u32, action_key: &str) -> fdo::Result<()> {
+        let mut store = self.store.lock().unwrap();
+        let noti = store.notifications.get(&id).ok_or_else(|| unknown_id(id))?;
+        if !noti.actions.iter().any(|a| a.key == action_key) {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Notification {} has no action {:?}",
+                id, action_key
+            )));
+        }
+        store.invoke_action(id, action_key);
+        store.notify_change();
+        Ok(())
+    }
+
+    /// Send `text` as the reply to a notification with an `inline-reply`
+    /// action, as if typed into its reply entry
+    fn reply(&self, id: u32, text: &str) -> fdo::Result<()> {
+        let mut store = self.store.lock().unwrap();
+        if !store.notifications.contains_key(&id) {
+            return Err(unknown_id(id));
+        }
+        if !store.reply(id, text) {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Notification {} doesn't take replies",
+                id
+            )));
+        }
+        store.notify_change();
+        Ok(())
+    }
+
+    /// Live notifications, newest first
+    fn list_notifications(&self) -> Vec<NotificationRow> {
+        let store = self.store.lock().unwrap();
+        let now = Utc::now();
+        store
+            .order
+            .iter()
+            .filter_map(|id| store.notifications.get(id))
+            .map(|n| {
+                (
+                    n.id,
+                    n.app_name.clone(),
+                    n.summary.clone(),
+                    urgency_name(n.urgency).to_string(),
+                    (now - n.created_at).num_seconds().max(0) as u64,
+                )
+            })
+            .collect()
+    }
+
+    /// All fields of one notification as a{sv}
+    fn get_notification(&self, id: u32) -> fdo::Result<HashMap<String, OwnedValue>> {
+        let store = self.store.lock().unwrap();
+        let n = store.notifications.get(&id).ok_or_else(|| unknown_id(id))?;
+        let age = (Utc::now() - n.created_at).num_seconds().max(0) as u64;
+        let actions: Vec<String> = n
+            .actions
+            .iter()
+            .flat_map(|a| [a.key.clone(), a.label.clone()])
+            .collect();
+
+        let mut fields = HashMap::from([
+            ("id".to_string(), owned(n.id)),
+            ("app_name".to_string(), owned(n.app_name.as_str())),
+            ("app_icon".to_string(), owned(n.app_icon.as_str())),
+            ("summary".to_string(), owned(n.summary.as_str())),
+            ("body".to_string(), owned(n.body.as_str())),
+            ("urgency".to_string(), owned(urgency_name(n.urgency))),
+            ("age".to_string(), owned(age)),
+            ("created_at".to_string(), owned(n.created_at.to_rfc3339())),
+            ("timeout".to_string(), owned(n.timeout)),
+            ("actions".to_string(), owned(actions)),
+            ("transient".to_string(), owned(n.transient)),
+            ("acknowledge".to_string(), owned(n.acknowledge_to_dismiss)),
+        ]);
+        if let Some(ref group) = n.group {
+            fields.insert("group".to_string(), owned(group.as_str()));
+        }
+        if let Some(ref entry) = n.desktop_entry {
+            fields.insert("desktop_entry".to_string(), owned(entry.as_str()));
+        }
+        if let Some(progress) = n.progress {
+            fields.insert("progress".to_string(), owned(progress));
+        }
+        if let Some(ref category) = n.category {
+            fields.insert("category".to_string(), owned(category.as_str()));
+        }
+        if let Some(pid) = n.sender_pid {
+            fields.insert("sender_pid".to_string(), owned(pid));
+        }
+        if let Some(ref error) = n.card_error {
+            fields.insert("card_error".to_string(), owned(error.as_str()));
+        }
+        if n.resident {
+            fields.insert("resident".to_string(), owned(true));
+        }
+        Ok(fields)
+    }
+
+    /// Re-read config.yaml. A config that fails to parse is rejected and the
+    /// running one kept.
+    fn reload_config(&self) -> fdo::Result<()> {
+        let config = Config::try_load().map_err(fdo::Error::Failed)?;
+        let mut store = self.store.lock().unwrap();
+        store.set_config(config);
+        store.notify_change();
+        self.send(UiCommand::ApplyConfig);
+        Ok(())
+    }
+
+    /// Status snapshot as JSON: counts by urgency, DND, pause, center
+    /// visibility and the newest notification
+    fn get_status(&self) -> fdo::Result<String> {
+        let status = self.store.lock().unwrap().status();
+        serde_json::to_string(&status).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Hold new popups back (they still reach the center) until Resume
+    fn pause(&self) {
+        let mut store = self.store.lock().unwrap();
+        store.set_paused(true);
+        store.notify_change();
+    }
+
+    fn resume(&self) {
+        let mut store = self.store.lock().unwrap();
+        store.set_paused(false);
+        store.notify_change();
+    }
+
+    #[zbus(property)]
+    fn dnd(&self) -> bool {
+        self.store.lock().unwrap().dnd_active()
+    }
+
+    /// Notifications in the center
+    #[zbus(property)]
+    fn count(&self) -> u32 {
+        self.store.lock().unwrap().all_notifications().len() as u32
+    }
+
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        self.store.lock().unwrap().paused
+    }
+
+    #[zbus(property)]
+    fn center_visible(&self) -> bool {
+        self.store.lock().unwrap().center_visible
+    }
+
+    #[zbus(signal, name = "DndChanged")]
+    async fn signal_dnd_changed(emitter: &SignalEmitter<'_>, dnd: bool) -> zbus::Result<()>;
+
+    #[zbus(signal, name = "CountChanged")]
+    async fn signal_count_changed(emitter: &SignalEmitter<'_>, count: u32) -> zbus::Result<()>;
+
+    #[zbus(signal, name = "PausedChanged")]
+    async fn signal_paused_changed(emitter: &SignalEmitter<'_>, paused: bool) -> zbus::Result<()>;
+
+    #[zbus(signal, name = "CenterVisibleChanged")]
+    async fn signal_center_visible_changed(
+        emitter: &SignalEmitter<'_>,
+        visible: bool,
+    ) -> zbus::Result<()>;
+
+    /// Sent with the GetStatus JSON whenever any part of it changes
+    #[zbus(signal)]
+    async fn status_changed(emitter: &SignalEmitter<'_>, status: &str) -> zbus::Result<()>;
 }
 
+/// Well-known names owned by the daemon
+pub const BUS_NAMES: [&str; 2] = ["org.freedesktop.Notifications", "org.xnotid.Control"];
+
 /// Starts the D-Bus server and acquires the notification bus name.
+/// With `replace`, a daemon that already owns the name is asked to give it up.
 pub async fn start_dbus_server(
     store: SharedStore,
     cmd_tx: Sender<UiCommand>,
-    signal_rx: Receiver<DbusSignal>,
+    signal_rx: UnboundedReceiver<DbusSignal>,
+    replace: bool,
 ) -> zbus::Result<Connection> {
-    let server = NotificationServer::new(store);
-    let control = ControlServer::new(cmd_tx);
-
     let connection = Connection::session().await?;
+    serve_on(connection, store, cmd_tx, signal_rx, replace).await
+}
+
+/// Serves both interfaces on an existing connection (e.g. a private test bus).
+pub async fn serve_on(
+    connection: Connection,
+    store: SharedStore,
+    cmd_tx: Sender<UiCommand>,
+    mut signal_rx: UnboundedReceiver<DbusSignal>,
+    replace: bool,
+) -> zbus::Result<Connection> {
+    let server = NotificationServer::new(store.clone());
+    let control = ControlServer::new(store, cmd_tx.clone());
 
     connection
         .object_server()
-        .at("/org/freedesktop/Notifications", server)
+        .at(NOTIFICATIONS_PATH, server)
         .await?;
 
     connection
         .object_server()
-        .at("/org/xnotid/Control", control)
+        .at(CONTROL_PATH, control)
         .await?;
 
-    connection
-        .request_name("org.freedesktop.Notifications")
-        .await?;
+    watch_name_lost(&connection, cmd_tx).await?;
 
-    connection
-        .request_name("org.xnotid.Control")
-        .await?;
+    // Always let a later `xnotid --replace` take over. With `replace`, take
+    // over ourselves, or wait in line if the owner doesn't allow that.
+    let flags = if replace {
+        RequestNameFlags::AllowReplacement | RequestNameFlags::ReplaceExisting
+    } else {
+        RequestNameFlags::AllowReplacement | RequestNameFlags::DoNotQueue
+    };
+    for name in BUS_NAMES {
+        match connection.request_name_with_flags(name, flags).await {
+            Ok(RequestNameReply::InQueue) => log::warn!(
+                "{} is owned by {}, which doesn't allow replacement; waiting for it to exit",
+                name,
+                describe_owner(&connection, name).await
+            ),
+            Ok(_) => {}
+            Err(zbus::Error::NameTaken) => {
+                return Err(zbus::Error::Failure(format!(
+                    "{} is already owned by {}; stop it or start xnotid with --replace",
+                    name,
+                    describe_owner(&connection, name).await
+                )));
+            }
+            Err(e) => return Err(e),
+        }
+    }
 
     log::info!("D-Bus server started: org.freedesktop.Notifications + org.xnotid.Control");
 
-    // Spawn task to handle signal emissions from UI thread
+    // Spawn task to emit signals sent from the UI thread as they arrive
     let conn_clone = connection.clone();
     tokio::spawn(async move {
-        loop {
-            // Check for signals to emit (non-blocking poll)
-            match signal_rx.try_recv() {
-                Ok(DbusSignal::ActionInvoked { id, action_key }) => {
-                    log::info!("Emitting ActionInvoked signal: id={}, key={}", id, action_key);
-                    let iface_ref = conn_clone
-                        .object_server()
-                        .interface::<_, NotificationServer>("/org/freedesktop/Notifications")
-                        .await;
-                    if let Ok(iface) = iface_ref {
-                        let _ = NotificationServer::action_invoked(
-                            iface.signal_emitter(),
-                            id,
-                            &action_key,
-                        )
-                        .await;
-                    }
-                }
-                Err(std::sync::mpsc::TryRecvError::Empty) => {
-                    // No signals pending, sleep briefly
-                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                }
-                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                    log::warn!("Signal channel disconnected");
-                    break;
-                }
+        while let Some(signal) = signal_rx.recv().await {
+            if let Err(e) = emit_signal(&conn_clone, signal).await {
+                log::warn!("Failed to emit D-Bus signal: {}", e);
             }
         }
+        log::warn!("Signal channel disconnected");
     });
 
     Ok(connection)
 }
+
+/// Ask the UI to quit once another daemon takes org.freedesktop.Notifications
+/// away from us (e.g. `xnotid --replace`).
+async fn watch_name_lost(connection: &Connection, cmd_tx: Sender<UiCommand>) -> zbus::Result<()> {
+    let mut lost = fdo::DBusProxy::new(connection).await?.receive_name_lost().await?;
+    tokio::spawn(async move {
+        loop {
+            let next = std::future::poll_fn(|cx| Pin::new(&mut lost).poll_next(cx));
+            let Some(signal) = next.await else { break };
+            if signal.args().is_ok_and(|args| args.name == BUS_NAMES[0]) {
+                log::warn!("Lost {} to another daemon, shutting down", BUS_NAMES[0]);
+                let _ = cmd_tx.send(UiCommand::Quit);
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Give up the bus names so a queued daemon takes over right away.
+pub async fn release_names(connection: &Connection) {
+    for name in BUS_NAMES {
+        if let Err(e) = connection.release_name(name).await {
+            log::warn!("Failed to release {}: {}", name, e);
+        }
+    }
+}
+
+/// Who owns `name`, for diagnostics: "dunst (pid 1234)" where the process
+/// can be found, else its unique bus name.
+pub async fn describe_owner(connection: &Connection, name: &str) -> String {
+    let Ok(dbus) = fdo::DBusProxy::new(connection).await else {
+        return "another process".into();
+    };
+    let owner = match BusName::try_from(name) {
+        Ok(bus_name) => dbus.get_name_owner(bus_name).await.ok(),
+        Err(_) => None,
+    };
+    let Some(owner) = owner else {
+        return "another process".into();
+    };
+    let Ok(pid) = dbus
+        .get_connection_unix_process_id(BusName::from(&owner))
+        .await
+    else {
+        return owner.to_string();
+    };
+    match std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        Ok(comm) => format!("{} (pid {})", comm.trim(), pid),
+        Err(_) => format!("pid {} ({})", pid, owner),
+    }
+}
+
+async fn emit_signal(conn: &Connection, signal: DbusSignal) -> zbus::Result<()> {
+    let object_server = conn.object_server();
+    match signal {
+        DbusSignal::ActionInvoked { id, action_key } => {
+            log::info!("Emitting ActionInvoked signal: id={}, key={}", id, action_key);
+            let iface = object_server
+                .interface::<_, NotificationServer>(NOTIFICATIONS_PATH)
+                .await?;
+            NotificationServer::action_invoked(iface.signal_emitter(), id, &action_key).await
+        }
+        DbusSignal::NotificationReplied { id, text } => {
+            log::info!("Emitting NotificationReplied signal: id={}", id);
+            let iface = object_server
+                .interface::<_, NotificationServer>(NOTIFICATIONS_PATH)
+                .await?;
+            NotificationServer::notification_replied(iface.signal_emitter(), id, &text).await
+        }
+        DbusSignal::NotificationClosed { id, reason } => {
+            log::info!("Emitting NotificationClosed signal: id={}, reason={:?}", id, reason);
+            let iface = object_server
+                .interface::<_, NotificationServer>(NOTIFICATIONS_PATH)
+                .await?;
+            NotificationServer::notification_closed(iface.signal_emitter(), id, reason as u32).await
+        }
+        // Status changes go out both as a plain signal and as PropertiesChanged
+        DbusSignal::DndChanged(dnd) => {
+            let iface = object_server.interface::<_, ControlServer>(CONTROL_PATH).await?;
+            let emitter = iface.signal_emitter();
+            ControlServer::signal_dnd_changed(emitter, dnd).await?;
+            iface.get().await.dnd_changed(emitter).await
+        }
+        DbusSignal::CountChanged(count) => {
+            let iface = object_server.interface::<_, ControlServer>(CONTROL_PATH).await?;
+            let emitter = iface.signal_emitter();
+            ControlServer::signal_count_changed(emitter, count).await?;
+            iface.get().await.count_changed(emitter).await
+        }
+        DbusSignal::PausedChanged(paused) => {
+            let iface = object_server.interface::<_, ControlServer>(CONTROL_PATH).await?;
+            let emitter = iface.signal_emitter();
+            ControlServer::signal_paused_changed(emitter, paused).await?;
+            iface.get().await.paused_changed(emitter).await
+        }
+        DbusSignal::CenterVisibleChanged(visible) => {
+            let iface = object_server.interface::<_, ControlServer>(CONTROL_PATH).await?;
+            let emitter = iface.signal_emitter();
+            ControlServer::signal_center_visible_changed(emitter, visible).await?;
+            iface.get().await.center_visible_changed(emitter).await
+        }
+        DbusSignal::StatusChanged(status) => {
+            let iface = object_server.interface::<_, ControlServer>(CONTROL_PATH).await?;
+            ControlServer::status_changed(iface.signal_emitter(), &status).await
+        }
+    }
+}
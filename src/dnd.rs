@@ -0,0 +1,79 @@
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// A recurring Do Not Disturb window from `dnd_schedule` in config.yaml.
+/// A window whose `end` is before its `start` runs past midnight and belongs
+/// to the day it starts on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DndWindow {
+    /// "mon".."sun" (or full names); empty = every day
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// "HH:MM", local time
+    pub start: String,
+    /// "HH:MM", local time; equal to `start` = the whole day
+    pub end: String,
+}
+
+impl DndWindow {
+    /// Parsed start/end times, or None if either is malformed
+    pub fn times(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let parse = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+        Some((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    /// Parsed weekdays, or None if one of them is malformed
+    pub fn weekdays(&self) -> Option<Vec<Weekday>> {
+        self.days.iter().map(|d| d.trim().parse().ok()).collect()
+    }
+
+    fn on_day(&self, days: &[Weekday], day: Weekday) -> bool {
+        days.is_empty() || days.contains(&day)
+    }
+
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let (Some((start, end)), Some(days)) = (self.times(), self.weekdays()) else {
+            return false;
+        };
+        let time = now.time();
+        let today = now.weekday();
+
+        if start == end {
+            self.on_day(&days, today)
+        } else if start < end {
+            self.on_day(&days, today) && time >= start && time < end
+        } else {
+            let yesterday = (now - Duration::days(1)).weekday();
+            (self.on_day(&days, today) && time >= start)
+                || (self.on_day(&days, yesterday) && time < end)
+        }
+    }
+}
+
+/// Whether any window of the schedule covers `now`
+pub fn scheduled(windows: &[DndWindow], now: NaiveDateTime) -> bool {
+    windows.iter().any(|w| w.contains(now))
+}
+
+/// Popup text listing what DND held back, busiest app first
+pub fn suppressed_summary(counts: &[(String, u32)]) -> (String, String) {
+    let total: u32 = counts.iter().map(|(_, n)| n).sum();
+    let mut sorted = counts.to_vec();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let summary = if total == 1 {
+        "1 notification while Do Not Disturb was on".to_string()
+    } else {
+        format!("{} notifications while Do Not Disturb was on", total)
+    };
+    let body = sorted
+        .iter()
+        .map(|(app, n)| {
+            let app = if app.is_empty() { "Unknown app" } else { app };
+            format!("{} ({})", app, n)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    (summary, body)
+}
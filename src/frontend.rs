@@ -0,0 +1,44 @@
+use crate::dbus_server::UiCommand;
+use crate::store::SharedStore;
+use std::sync::mpsc::{self, Receiver};
+
+/// Consumer of store changes and UI commands. The GTK `Ui` implements this in
+/// the daemon; tests plug in a recording stand-in.
+pub trait Frontend {
+    /// Re-sync widgets with the store (called after store changes)
+    fn refresh(&self);
+    /// Toggle the notification center visibility
+    fn toggle_center(&self);
+    fn show_center(&self);
+    fn hide_center(&self);
+    /// Apply the store's (reloaded) config and re-read the stylesheet
+    fn apply_config(&self);
+    /// Stop the main loop; the daemon flushes its state and exits
+    fn quit(&self);
+}
+
+/// Route the store's change callback into a channel that `pump` drains.
+pub fn connect_store(store: &SharedStore) -> Receiver<()> {
+    let (tx, rx) = mpsc::channel::<()>();
+    let mut s = store.lock().unwrap();
+    s.on_change = Some(Box::new(move || {
+        let _ = tx.send(());
+    }));
+    rx
+}
+
+/// Deliver all pending store changes and commands to the frontend.
+pub fn pump(frontend: &dyn Frontend, changes: &Receiver<()>, commands: &Receiver<UiCommand>) {
+    while let Ok(()) = changes.try_recv() {
+        frontend.refresh();
+    }
+    while let Ok(cmd) = commands.try_recv() {
+        match cmd {
+            UiCommand::ToggleCenter => frontend.toggle_center(),
+            UiCommand::ShowCenter => frontend.show_center(),
+            UiCommand::HideCenter => frontend.hide_center(),
+            UiCommand::ApplyConfig => frontend.apply_config(),
+            UiCommand::Quit => frontend.quit(),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::logfile::segment_path;
+use crate::notification::LogEntry;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Bytes read per step when walking the log backwards
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Decompressed `.gz` segments kept around, enough for a page that spans two
+const GZ_CACHE_SIZE: usize = 2;
+
+/// A `.gz` segment as path, size and mtime; rotated segments don't change
+/// once written, so a match means the cached copy is still good
+type GzKey = (PathBuf, u64, Option<SystemTime>);
+
+/// Recently decompressed segments, least recently used first
+static GZ_CACHE: Mutex<Vec<(GzKey, Arc<Vec<u8>>)>> = Mutex::new(Vec::new());
+
+/// Filters for browsing the JSONL log. All set fields must match.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    /// Case-insensitive substring of summary or body
+    pub text: Option<String>,
+    /// Case-insensitive substring of app_name
+    pub app_name: Option<String>,
+    /// "Low", "Normal" or "Critical" (only "received" records carry urgency)
+    pub urgency: Option<String>,
+    /// "received", "dismissed", "action", "expired" or "closed"
+    pub event: Option<String>,
+    /// Inclusive lower bound on the record timestamp
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on the record timestamp
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl HistoryQuery {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.event.as_ref().is_some_and(|event| entry.event != *event) {
+            return false;
+        }
+
+        if self
+            .urgency
+            .as_ref()
+            .is_some_and(|urgency| entry.urgency.as_deref() != Some(urgency.as_str()))
+        {
+            return false;
+        }
+
+        if let Some(ref app) = self.app_name {
+            let app = app.to_lowercase();
+            if !entry
+                .app_name
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase().contains(&app))
+            {
+                return false;
+            }
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let Ok(ts) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+                return false;
+            };
+            let ts = ts.with_timezone(&Utc);
+            if self.since.is_some_and(|since| ts < since) || self.until.is_some_and(|until| ts >= until) {
+                return false;
+            }
+        }
+
+        if let Some(ref text) = self.text {
+            let text = text.to_lowercase();
+            let hit = |field: &Option<String>| {
+                field
+                    .as_deref()
+                    .is_some_and(|value| value.to_lowercase().contains(&text))
+            };
+            if !hit(&entry.summary) && !hit(&entry.body) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Where a page left off: a log segment (0 = the active log, N = `.N` or
+/// `.N.gz`) and the byte offset in it to continue before (None = its end)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistoryCursor {
+    pub segment: usize,
+    pub before: Option<u64>,
+}
+
+/// One page of matching records, newest first
+#[derive(Debug, Default)]
+pub struct HistoryPage {
+    pub entries: Vec<LogEntry>,
+    /// Cursor to pass as `from` for the next page (None = oldest segment reached)
+    pub next: Option<HistoryCursor>,
+}
+
+/// Read up to `limit` records matching `query`, walking the log backwards
+/// from `from` (None = end of the active log) and on into the rotated
+/// segments, oldest last. Only the chunks needed to fill the page are read
+/// from plain segments, so large logs page in lazily; `.gz` segments are
+/// decompressed whole once and cached for the following pages and
+/// searches. Unparseable lines are skipped.
+/// A rotation between two pages shifts the segments under the cursor.
+pub fn read_page(
+    path: &Path,
+    query: &HistoryQuery,
+    from: Option<HistoryCursor>,
+    limit: usize,
+) -> io::Result<HistoryPage> {
+    let from = from.unwrap_or_default();
+    let mut page = HistoryPage::default();
+
+    for segment in from.segment.. {
+        let before = if segment == from.segment { from.before } else { None };
+        let stopped_at = match open_segment(path, segment)? {
+            Some(Segment::Plain(mut file)) => scan(&mut file, before, query, limit, &mut page.entries)?,
+            Some(Segment::Gzip(data)) => {
+                scan(&mut Cursor::new(data.as_slice()), before, query, limit, &mut page.entries)?
+            }
+            // The active log may not exist yet while rotated segments do
+            None if segment == 0 => None,
+            None => break,
+        };
+        if let Some(offset) = stopped_at {
+            page.next = if offset > 0 {
+                Some(HistoryCursor { segment, before: Some(offset) })
+            } else {
+                segment_exists(path, segment + 1).then_some(HistoryCursor {
+                    segment: segment + 1,
+                    before: None,
+                })
+            };
+            break;
+        }
+    }
+    Ok(page)
+}
+
+enum Segment {
+    Plain(File),
+    Gzip(Arc<Vec<u8>>),
+}
+
+fn open_segment(path: &Path, n: usize) -> io::Result<Option<Segment>> {
+    let plain = if n == 0 { path.to_path_buf() } else { segment_path(path, n, false) };
+    match File::open(&plain) {
+        Ok(file) => return Ok(Some(Segment::Plain(file))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    if n == 0 {
+        return Ok(None);
+    }
+
+    let gz = segment_path(path, n, true);
+    if !gz.exists() {
+        return Ok(None);
+    }
+    Ok(Some(Segment::Gzip(decompressed(&gz)?)))
+}
+
+/// Contents of the `.gz` segment at `gz`, from the cache if it's unchanged
+fn decompressed(gz: &Path) -> io::Result<Arc<Vec<u8>>> {
+    let meta = fs::metadata(gz)?;
+    let key = (gz.to_path_buf(), meta.len(), meta.modified().ok());
+    let mut cache = GZ_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pos) = cache.iter().position(|(cached, _)| *cached == key) {
+        let hit = cache.remove(pos);
+        let data = hit.1.clone();
+        cache.push(hit);
+        return Ok(data);
+    }
+
+    let mut data = Vec::new();
+    GzDecoder::new(File::open(gz)?).read_to_end(&mut data)?;
+    let data = Arc::new(data);
+    if cache.len() >= GZ_CACHE_SIZE {
+        cache.remove(0);
+    }
+    cache.push((key, data.clone()));
+    Ok(data)
+}
+
+fn segment_exists(path: &Path, n: usize) -> bool {
+    segment_path(path, n, false).exists() || segment_path(path, n, true).exists()
+}
+
+/// Collect matches from one segment until `entries` holds `limit`; returns
+/// the offset of the line that filled it, or None if the segment ran out.
+fn scan<R: Read + Seek>(
+    reader: &mut R,
+    before: Option<u64>,
+    query: &HistoryQuery,
+    limit: usize,
+    entries: &mut Vec<LogEntry>,
+) -> io::Result<Option<u64>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut lines = ReverseLines {
+        pos: before.unwrap_or(len).min(len),
+        buf: Vec::new(),
+    };
+
+    while let Some((offset, line)) = lines.next_line(reader)? {
+        let Ok(entry) = serde_json::from_slice::<LogEntry>(&line) else {
+            continue;
+        };
+        if query.matches(&entry) {
+            entries.push(entry);
+            if entries.len() >= limit {
+                return Ok(Some(offset));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Yields lines from the end of a file towards its start
+struct ReverseLines {
+    /// File offset of the first byte held in `buf`
+    pos: u64,
+    /// Unconsumed bytes `[pos, pos + buf.len())`
+    buf: Vec<u8>,
+}
+
+impl ReverseLines {
+    /// Next non-empty line and the offset it starts at
+    fn next_line<R: Read + Seek>(&mut self, file: &mut R) -> io::Result<Option<(u64, Vec<u8>)>> {
+        loop {
+            if let Some(nl) = self.buf.iter().rposition(|&b| b == b'\n') {
+                let line = self.buf.split_off(nl + 1);
+                self.buf.truncate(nl);
+                if !line.is_empty() {
+                    return Ok(Some((self.pos + nl as u64 + 1, line)));
+                }
+                continue;
+            }
+
+            if self.pos == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                return Ok(Some((0, std::mem::take(&mut self.buf))));
+            }
+
+            let start = self.pos.saturating_sub(CHUNK_SIZE);
+            let mut chunk = vec![0; (self.pos - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.append(&mut self.buf);
+            self.buf = chunk;
+            self.pos = start;
+        }
+    }
+}
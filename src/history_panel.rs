@@ -0,0 +1,288 @@
+use crate::history::{self, HistoryCursor, HistoryQuery};
+use crate::notification::LogEntry;
+use crate::store::SharedStore;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use gtk4::prelude::*;
+use gtk4::{
+    Align, Box as GtkBox, Button, DropDown, Entry, Label, Orientation, PositionType,
+    ScrolledWindow, SearchEntry,
+};
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Matching records loaded per page
+const PAGE_SIZE: usize = 50;
+const URGENCIES: [&str; 4] = ["Any urgency", "Low", "Normal", "Critical"];
+const EVENTS: [&str; 6] = ["Any event", "received", "dismissed", "action", "expired", "closed"];
+
+/// "History" tab of the notification center: searches the JSONL log and
+/// pages through it newest first as the list is scrolled.
+pub struct HistoryPanel {
+    store: SharedStore,
+    root: GtkBox,
+    search: SearchEntry,
+    app_entry: Entry,
+    urgency: DropDown,
+    event: DropDown,
+    since: Entry,
+    until: Entry,
+    list: GtkBox,
+    status: Label,
+    more_btn: Button,
+    /// Where the next page continues from (None = end of the active log)
+    before: Cell<Option<HistoryCursor>>,
+    /// Start of the oldest segment reached for the current query
+    exhausted: Cell<bool>,
+}
+
+impl HistoryPanel {
+    pub fn new(store: SharedStore, max_height: i32) -> Rc<Self> {
+        let root = GtkBox::new(Orientation::Vertical, 6);
+        root.set_widget_name("history-panel");
+        root.set_margin_start(8);
+        root.set_margin_end(8);
+        root.set_margin_top(4);
+        root.set_margin_bottom(4);
+
+        // Filters
+        let filters = GtkBox::new(Orientation::Vertical, 4);
+        filters.set_widget_name("history-filters");
+
+        let search = SearchEntry::new();
+        search.set_placeholder_text(Some("Search summary and body"));
+        filters.append(&search);
+
+        let row = GtkBox::new(Orientation::Horizontal, 4);
+        let app_entry = Entry::new();
+        app_entry.set_placeholder_text(Some("App"));
+        app_entry.set_hexpand(true);
+        row.append(&app_entry);
+        let urgency = DropDown::from_strings(&URGENCIES);
+        row.append(&urgency);
+        let event = DropDown::from_strings(&EVENTS);
+        row.append(&event);
+        filters.append(&row);
+
+        let row = GtkBox::new(Orientation::Horizontal, 4);
+        let since = Entry::new();
+        since.set_placeholder_text(Some("From (YYYY-MM-DD)"));
+        since.set_hexpand(true);
+        row.append(&since);
+        let until = Entry::new();
+        until.set_placeholder_text(Some("To (YYYY-MM-DD)"));
+        until.set_hexpand(true);
+        row.append(&until);
+        filters.append(&row);
+
+        root.append(&filters);
+
+        // Results
+        let scrolled = ScrolledWindow::new();
+        scrolled.set_vexpand(true);
+        scrolled.set_widget_name("history-scroll");
+        scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+        scrolled.set_vscrollbar_policy(gtk4::PolicyType::Automatic);
+        scrolled.set_max_content_height(max_height);
+        scrolled.set_propagate_natural_height(true);
+
+        let content = GtkBox::new(Orientation::Vertical, 4);
+        let list = GtkBox::new(Orientation::Vertical, 4);
+        list.set_widget_name("history-list");
+        content.append(&list);
+
+        let status = Label::new(None);
+        status.set_widget_name("history-status");
+        status.set_css_classes(&["dim-label"]);
+        content.append(&status);
+
+        let more_btn = Button::with_label("Load more");
+        more_btn.set_widget_name("history-more");
+        more_btn.set_halign(Align::Center);
+        content.append(&more_btn);
+
+        scrolled.set_child(Some(&content));
+        root.append(&scrolled);
+
+        let panel = Rc::new(Self {
+            store,
+            root,
+            search,
+            app_entry,
+            urgency,
+            event,
+            since,
+            until,
+            list,
+            status,
+            more_btn,
+            before: Cell::new(None),
+            exhausted: Cell::new(false),
+        });
+
+        // Search is debounced by SearchEntry; text filters apply on Enter
+        let p = panel.clone();
+        panel.search.connect_search_changed(move |_| p.reload());
+        for entry in [&panel.app_entry, &panel.since, &panel.until] {
+            let p = panel.clone();
+            entry.connect_activate(move |_| p.reload());
+        }
+        for dropdown in [&panel.urgency, &panel.event] {
+            let p = panel.clone();
+            dropdown.connect_selected_notify(move |_| p.reload());
+        }
+
+        let p = panel.clone();
+        panel.more_btn.connect_clicked(move |_| p.load_more());
+        let p = panel.clone();
+        scrolled.connect_edge_reached(move |_, pos| {
+            if pos == PositionType::Bottom {
+                p.load_more();
+            }
+        });
+
+        panel
+    }
+
+    pub fn widget(&self) -> &GtkBox {
+        &self.root
+    }
+
+    /// Drop loaded rows and read the newest page for the current filters
+    pub fn reload(&self) {
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+        self.before.set(None);
+        self.exhausted.set(false);
+        self.load_more();
+    }
+
+    fn load_more(&self) {
+        if self.exhausted.get() {
+            return;
+        }
+
+        let query = self.query();
+        // Looked up every time so a reloaded config's log_path applies
+        let log_path = PathBuf::from(&self.store.lock().unwrap().config.log_path);
+        let page = match history::read_page(&log_path, &query, self.before.get(), PAGE_SIZE) {
+            Ok(page) => page,
+            Err(e) => {
+                log::warn!("Failed to read history from {:?}: {}", log_path, e);
+                self.status.set_text("Could not read the notification log");
+                self.more_btn.set_visible(false);
+                self.exhausted.set(true);
+                return;
+            }
+        };
+
+        for entry in page.entries {
+            self.list.append(&self.build_row(entry));
+        }
+
+        self.before.set(page.next);
+        self.exhausted.set(page.next.is_none());
+        self.more_btn.set_visible(page.next.is_some());
+        if self.list.first_child().is_none() {
+            self.status.set_text("No matching history");
+            self.status.set_visible(true);
+        } else {
+            self.status.set_visible(false);
+        }
+    }
+
+    fn query(&self) -> HistoryQuery {
+        let text = |s: String| {
+            let s = s.trim().to_string();
+            (!s.is_empty()).then_some(s)
+        };
+        let choice = |dropdown: &DropDown, options: &[&str]| match dropdown.selected() {
+            0 | gtk4::INVALID_LIST_POSITION => None,
+            idx => options.get(idx as usize).map(|s| s.to_string()),
+        };
+
+        HistoryQuery {
+            text: text(self.search.text().to_string()),
+            app_name: text(self.app_entry.text().to_string()),
+            urgency: choice(&self.urgency, &URGENCIES),
+            event: choice(&self.event, &EVENTS),
+            since: parse_day(&self.since.text(), false),
+            until: parse_day(&self.until.text(), true),
+        }
+    }
+
+    fn build_row(&self, entry: LogEntry) -> GtkBox {
+        let row = GtkBox::new(Orientation::Vertical, 2);
+        row.add_css_class("history-row");
+        if let Some(ref urgency) = entry.urgency {
+            row.add_css_class(&urgency.to_lowercase());
+        }
+
+        let meta = GtkBox::new(Orientation::Horizontal, 6);
+        let when = DateTime::parse_from_rfc3339(&entry.timestamp)
+            .map(|ts| ts.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| entry.timestamp.clone());
+        let time_label = Label::new(Some(&when));
+        time_label.add_css_class("history-time");
+        meta.append(&time_label);
+
+        let app_label = Label::new(entry.app_name.as_deref());
+        app_label.add_css_class("history-app");
+        app_label.set_hexpand(true);
+        app_label.set_halign(Align::Start);
+        app_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        meta.append(&app_label);
+
+        let event_label = Label::new(Some(&entry.event));
+        event_label.add_css_class("history-event");
+        meta.append(&event_label);
+        row.append(&meta);
+
+        let summary = Label::new(entry.summary.as_deref());
+        summary.add_css_class("summary");
+        summary.set_halign(Align::Start);
+        summary.set_wrap(true);
+        summary.set_xalign(0.0);
+        row.append(&summary);
+
+        if let Some(body) = entry.body.as_deref().filter(|b| !b.is_empty()) {
+            let body_label = Label::new(Some(body));
+            body_label.add_css_class("body");
+            body_label.set_halign(Align::Start);
+            body_label.set_xalign(0.0);
+            body_label.set_wrap(true);
+            body_label.set_lines(2);
+            body_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+            row.append(&body_label);
+        }
+
+        // Only "received" records carry enough to rebuild the notification
+        if entry.event == "received" {
+            let reshow = Button::with_label("Re-show");
+            reshow.add_css_class("notification-action");
+            reshow.set_halign(Align::End);
+            let store = self.store.clone();
+            reshow.connect_clicked(move |_| {
+                let mut s = store.lock().unwrap();
+                let id = s.add(entry.to_notification(), 0);
+                log::info!("Re-showed history entry {} as notification {}", entry.uuid, id);
+                s.notify_change();
+            });
+            row.append(&reshow);
+        }
+
+        row
+    }
+}
+
+/// Parse a YYYY-MM-DD filter as local midnight; `end_of_day` gives the
+/// following midnight so the "To" date is inclusive.
+fn parse_day(text: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()?;
+    let date = if end_of_day { date.succ_opt()? } else { date };
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
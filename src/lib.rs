@@ -0,0 +1,18 @@
+pub mod card_widgets;
+pub mod cli;
+pub mod config;
+pub mod ctl;
+pub mod dbus_server;
+pub mod dnd;
+pub mod frontend;
+pub mod history;
+pub mod history_panel;
+pub mod logfile;
+pub mod notification;
+pub mod placement;
+pub mod rules;
+pub mod sound;
+pub mod statefile;
+pub mod store;
+pub mod tray;
+pub mod ui;
@@ -0,0 +1,269 @@
+use crate::config::Config;
+use crate::notification::LogEntry;
+use chrono::{DateTime, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, SystemTime};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The JSONL notification log. Keeps one buffered handle open and rotates
+/// `log_path` to `log_path.1` (`.1.gz` when compressed), `.2`, ... by size or
+/// age, keeping at most `log_keep` rotated segments. Settings are read from
+/// the config passed to each call, so a reloaded config applies immediately.
+pub struct LogFile {
+    writer: Option<BufWriter<File>>,
+    /// Path the writer was opened for
+    path: PathBuf,
+    /// Bytes in the active segment
+    size: u64,
+    /// Time of the first record in the active segment
+    started_at: SystemTime,
+    /// `gzip` still compressing the last rotated segment
+    compressing: Option<Child>,
+}
+
+impl Default for LogFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogFile {
+    pub fn new() -> Self {
+        Self {
+            writer: None,
+            path: PathBuf::new(),
+            size: 0,
+            started_at: SystemTime::now(),
+            compressing: None,
+        }
+    }
+
+    /// Append one record, rotating first if it would exceed the limits.
+    pub fn append(&mut self, config: &Config, line: &str) -> io::Result<()> {
+        self.reap_compression(false);
+        let path = PathBuf::from(&config.log_path);
+        if self.writer.is_none() || self.path != path {
+            self.open(&path)?;
+        }
+
+        let len = line.len() as u64 + 1;
+        if self.needs_rotation(config, len) {
+            self.rotate(config)?;
+            self.open(&path)?;
+        }
+
+        if let Some(ref mut writer) = self.writer {
+            writeln!(writer, "{}", line)?;
+            self.size += len;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.writer {
+            Some(ref mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Collect a finished background `gzip`, or with `wait` block until it
+    /// is done (before segments are shifted or deleted).
+    fn reap_compression(&mut self, wait: bool) {
+        let Some(ref mut child) = self.compressing else {
+            return;
+        };
+        let status = if wait { child.wait().map(Some) } else { child.try_wait() };
+        match status {
+            Ok(None) => return,
+            Ok(Some(status)) if !status.success() => log::warn!("gzip exited with {}", status),
+            Ok(Some(_)) => {}
+            Err(e) => log::warn!("Failed to wait for gzip: {}", e),
+        }
+        self.compressing = None;
+    }
+
+    fn open(&mut self, path: &Path) -> io::Result<()> {
+        self.flush()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.size = file.metadata()?.len();
+        self.started_at = first_record_time(path).unwrap_or_else(SystemTime::now);
+        self.writer = Some(BufWriter::new(file));
+        self.path = path.to_path_buf();
+        Ok(())
+    }
+
+    fn needs_rotation(&self, config: &Config, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let max_bytes = config.log_rotate_size_kb * 1024;
+        let too_big = max_bytes > 0 && self.size + incoming > max_bytes;
+        let too_old = config.log_rotate_days > 0
+            && self
+                .started_at
+                .elapsed()
+                .is_ok_and(|age| age >= DAY * config.log_rotate_days);
+        too_big || too_old
+    }
+
+    /// Shift `.N` segments up by one, move the active log to `.1` and drop
+    /// whatever falls beyond `log_keep`.
+    fn rotate(&mut self, config: &Config) -> io::Result<()> {
+        // Segments can't shift under a running gzip
+        self.reap_compression(true);
+        self.flush()?;
+        self.writer = None;
+        let path = self.path.clone();
+
+        for n in (1..=config.log_keep).rev() {
+            for gz in [false, true] {
+                let from = segment_path(&path, n, gz);
+                if !from.exists() {
+                    continue;
+                }
+                if n == config.log_keep {
+                    fs::remove_file(&from)?;
+                } else {
+                    fs::rename(&from, segment_path(&path, n + 1, gz))?;
+                }
+            }
+        }
+
+        if config.log_keep == 0 {
+            fs::remove_file(&path)?;
+        } else {
+            let first = segment_path(&path, 1, false);
+            fs::rename(&path, &first)?;
+            if config.log_compress {
+                self.compressing = compress(&first);
+            }
+        }
+        prune_segments(&path, config.log_keep);
+
+        log::info!("Rotated notification log {:?}", path);
+        Ok(())
+    }
+
+    /// Startup purge: delete rotated segments last written before the cutoff
+    /// and drop older records from the active log.
+    pub fn purge_expired(&mut self, config: &Config) -> io::Result<()> {
+        if config.log_max_age_days == 0 {
+            return Ok(());
+        }
+        self.reap_compression(true);
+        self.flush()?;
+        self.writer = None;
+
+        let path = PathBuf::from(&config.log_path);
+        let cutoff = SystemTime::now() - DAY * config.log_max_age_days;
+
+        for (_, segment) in segments(&path) {
+            let expired = fs::metadata(&segment)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified < cutoff);
+            if expired {
+                fs::remove_file(&segment)?;
+                log::info!("Purged expired log segment {:?}", segment);
+            }
+        }
+
+        let file = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let cutoff: DateTime<Utc> = cutoff.into();
+        let mut kept = Vec::new();
+        let mut dropped = 0;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            match record_time(&line) {
+                Some(ts) if ts < cutoff => dropped += 1,
+                _ => kept.push(line),
+            }
+        }
+        if dropped > 0 {
+            let tmp = path.with_extension("jsonl.tmp");
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            for line in &kept {
+                writeln!(out, "{}", line)?;
+            }
+            out.flush()?;
+            fs::rename(&tmp, &path)?;
+            log::info!("Purged {} expired records from {:?}", dropped, path);
+        }
+        Ok(())
+    }
+}
+
+/// Path of rotated segment `n`
+pub fn segment_path(path: &Path, n: usize, gz: bool) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", n));
+    if gz {
+        name.push(".gz");
+    }
+    PathBuf::from(name)
+}
+
+/// Rotated segments of `path` with their index, in no particular order
+fn segments(path: &Path) -> Vec<(usize, PathBuf)> {
+    let (Some(dir), Some(base)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", base);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let rest = name.strip_prefix(&prefix)?;
+            let n = rest.strip_suffix(".gz").unwrap_or(rest).parse().ok()?;
+            Some((n, entry.path()))
+        })
+        .collect()
+}
+
+/// Remove segments left over beyond `keep`, e.g. after lowering `log_keep`
+fn prune_segments(path: &Path, keep: usize) {
+    for (n, segment) in segments(path) {
+        if n > keep {
+            let _ = fs::remove_file(segment);
+        }
+    }
+}
+
+/// Start gzipping a freshly rotated segment; `gzip` replaces it with
+/// `.gz`. It runs in the background so the store isn't held up; until it
+/// finishes `.1` and `.1.gz` can both exist, and readers prefer `.1`.
+fn compress(segment: &Path) -> Option<Child> {
+    match Command::new("gzip").arg("-f").arg(segment).spawn() {
+        Ok(child) => Some(child),
+        Err(e) => {
+            log::warn!("Failed to run gzip on {:?}: {}", segment, e);
+            None
+        }
+    }
+}
+
+fn record_time(line: &str) -> Option<DateTime<Utc>> {
+    let entry: LogEntry = serde_json::from_str(line).ok()?;
+    DateTime::parse_from_rfc3339(&entry.timestamp)
+        .ok()
+        .map(|ts| ts.with_timezone(&Utc))
+}
+
+fn first_record_time(path: &Path) -> Option<SystemTime> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?).read_line(&mut line).ok()?;
+    record_time(line.trim_end()).map(SystemTime::from)
+}
@@ -1,23 +1,60 @@
-mod config;
-mod dbus_server;
-mod notification;
-mod store;
-mod tray;
-mod ui;
-
-use config::Config;
-use dbus_server::{DbusSignal, UiCommand};
 use std::rc::Rc;
-use store::Store;
+use xnotid::config::{Config, Overrides};
+use xnotid::dbus_server::{self, DbusSignal, UiCommand};
+use xnotid::sound::CommandPlayer;
+use xnotid::store::Store;
+use xnotid::cli::{self, Mode};
+use xnotid::{frontend, tray, ui};
 
 fn main() {
+    // First, so warnings about the command line and environment are shown
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("xnotid: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    // Before anything looks up a config path
+    options.overrides.clone().or(Overrides::from_env()).install();
+
+    match options.mode {
+        Mode::Run => {}
+        Mode::Help => {
+            print!("{}", cli::USAGE);
+            return;
+        }
+        Mode::Version => {
+            println!("xnotid {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Mode::CheckConfig(path) => match cli::check_config(path) {
+            Ok(verdict) => {
+                println!("{}", verdict);
+                return;
+            }
+            Err(errors) => {
+                eprintln!("{}", errors);
+                std::process::exit(1);
+            }
+        },
+    }
+
     log::info!("xnotid starting");
+    if let Some(profile) = &Overrides::current().profile {
+        log::info!("Using profile {:?}", profile);
+    }
 
     gtk4::init().expect("Failed to initialize GTK4");
 
-    let config = Config::load();
+    // A broken config.yaml still starts with defaults, but the user is told
+    let (config, config_error) = match Config::try_load() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e)),
+    };
     log::info!(
         "Config loaded: popup_width={}, max_visible={}",
         config.popup_width,
@@ -26,70 +63,118 @@ fn main() {
 
     let store = Store::new_shared(config);
 
-    // Signal channel for UI -> D-Bus (e.g. ActionInvoked)
-    let (signal_tx, signal_rx) = std::sync::mpsc::channel::<DbusSignal>();
+    // Bring back the center from before the last restart, drop expired log records
+    {
+        let mut s = store.lock().unwrap();
+        s.restore_state();
+        s.purge_log();
+    }
+
+    // Signal channel for UI -> D-Bus (e.g. ActionInvoked, NotificationClosed)
+    let (signal_tx, signal_rx) = tokio::sync::mpsc::unbounded_channel::<DbusSignal>();
 
     // Create UI (no Application — we manage our own main loop)
-    let ui = Rc::new(ui::Ui::new(store.clone(), signal_tx));
+    let ui = Rc::new(ui::Ui::new(store.clone()));
 
     // Wire up store -> UI refresh callback via channel
-    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let changes = frontend::connect_store(&store);
     {
         let mut s = store.lock().unwrap();
-        s.on_change = Some(Box::new(move || {
-            let _ = tx.send(());
-        }));
+        s.signal_tx = Some(signal_tx);
+        s.set_sound_player(Box::new(CommandPlayer));
+        s.refresh_dnd();
+        if let Some(ref e) = config_error {
+            s.report_config_error(e);
+        }
+        // Render whatever restore_state brought back
+        s.notify_change();
     }
 
-    // Poll the channel from the GTK main loop
+    // Command channel for D-Bus -> UI (e.g. toggle center)
+    let (cmd_tx, cmd_rx) = std::sync::mpsc::channel::<UiCommand>();
+
+    // Poll both channels from the GTK main loop
     let ui_poll = ui.clone();
     glib2::timeout_add_local(std::time::Duration::from_millis(50), move || {
-        while let Ok(()) = rx.try_recv() {
-            ui_poll.refresh();
-        }
+        frontend::pump(&*ui_poll, &changes, &cmd_rx);
         glib2::ControlFlow::Continue
     });
 
-    // Command channel for D-Bus -> UI (e.g. toggle center)
-    let (cmd_tx, cmd_rx) = std::sync::mpsc::channel::<UiCommand>();
-    let ui_cmd = ui.clone();
-    glib2::timeout_add_local(std::time::Duration::from_millis(50), move || {
-        while let Ok(cmd) = cmd_rx.try_recv() {
-            match cmd {
-                UiCommand::ToggleCenter => ui_cmd.toggle_center(),
-            }
+    if !options.no_tray {
+        tray::start_tray_service(cmd_tx.clone());
+    }
+
+    // Anchor popup + center windows (popups hidden until first notification)
+    ui.position_windows();
+
+    // Scheduled and fullscreen Do Not Disturb
+    let store_dnd = store.clone();
+    glib2::timeout_add_seconds_local(30, move || {
+        let mut s = store_dnd.lock().unwrap();
+        if s.refresh_dnd() {
+            s.notify_change();
+        }
+        glib2::ControlFlow::Continue
+    });
+    ui.watch_fullscreen();
+
+    // Answer cards whose deadline passed with their default
+    let store_cards = store.clone();
+    glib2::timeout_add_seconds_local(1, move || {
+        let mut s = store_cards.lock().unwrap();
+        if s.expire_cards() {
+            s.notify_change();
         }
         glib2::ControlFlow::Continue
     });
 
-    tray::start_tray_service(cmd_tx.clone());
-
-    // Position popup window (hidden until first notification)
-    ui.position_popup();
+    // Pick up edits to config.yaml and style.css
+    ui.watch_config();
 
-    // Start D-Bus server in a background thread
+    // Start D-Bus server in a background thread. It also turns SIGTERM and
+    // SIGINT into a clean shutdown, and releases the bus names at the end.
     let store_dbus = store.clone();
-    std::thread::spawn(move || {
+    let replace = options.replace;
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let dbus_thread = std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
         rt.block_on(async {
-            match dbus_server::start_dbus_server(store_dbus, cmd_tx, signal_rx).await {
-                Ok(_conn) => {
-                    log::info!("D-Bus connection established");
-                    loop {
-                        tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
-                    }
-                }
+            let quit_tx = cmd_tx.clone();
+            let conn = match dbus_server::start_dbus_server(store_dbus, cmd_tx, signal_rx, replace)
+                .await
+            {
+                Ok(conn) => conn,
                 Err(e) => {
                     log::error!("Failed to start D-Bus server: {}", e);
                     std::process::exit(1);
                 }
-            }
+            };
+            log::info!("D-Bus connection established");
+
+            tokio::spawn(async move {
+                use tokio::signal::unix::{signal, SignalKind};
+                let mut term = signal(SignalKind::terminate()).expect("SIGTERM handler");
+                let mut int = signal(SignalKind::interrupt()).expect("SIGINT handler");
+                let name = tokio::select! {
+                    _ = term.recv() => "SIGTERM",
+                    _ = int.recv() => "SIGINT",
+                };
+                log::info!("Received {}, shutting down", name);
+                let _ = quit_tx.send(UiCommand::Quit);
+            });
+
+            let _ = stop_rx.await;
+            dbus_server::release_names(&conn).await;
         });
     });
 
     log::info!("xnotid ready, entering main loop");
 
-    // Run the GLib main loop — runs forever (daemon)
-    let main_loop = glib2::MainLoop::new(None, false);
-    main_loop.run();
+    // Runs until a signal or another daemon taking over asks the UI to quit
+    ui.run();
+
+    log::info!("xnotid shutting down");
+    store.lock().unwrap().flush();
+    let _ = stop_tx.send(());
+    let _ = dbus_thread.join();
 }
@@ -1,15 +1,29 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 use zbus::zvariant::{OwnedValue, Value};
 
+/// Action key asking for a reply entry instead of a button (the KDE and
+/// GNOME convention); the typed text comes back as `NotificationReplied`
+pub const INLINE_REPLY: &str = "inline-reply";
+
+/// Newest card envelope version understood; older ones are still accepted
+pub const CARD_VERSION: u32 = 2;
+
+/// Hint carrying a card envelope as a JSON string, so the body can stay a
+/// plain-text fallback for daemons without card support
+pub const CARD_HINT: &str = "x-xnotid-card";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CardChoice {
     pub id: String,
     pub label: String,
 }
 
+/// A structured prompt carried in the `x-xnotid-card` hint or the
+/// notification body. Every answer is sent back as `ActionInvoked` with a
+/// JSON payload whose `type` is the card's type (see README).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum NotificationCard {
@@ -18,31 +32,462 @@ pub enum NotificationCard {
         choices: Vec<CardChoice>,
         #[serde(default)]
         allow_other: bool,
+        #[serde(default)]
+        mode: ChoiceMode,
+        /// Fewest answers Submit accepts; the free-text answer counts as one
+        #[serde(default)]
+        min_selected: Option<usize>,
+        /// Most answers Submit accepts; always 1 in single mode
+        #[serde(default)]
+        max_selected: Option<usize>,
+        /// Shorthand for `min_selected: 1`
+        #[serde(default)]
+        required: bool,
     },
+    /// Allow once / allow always / deny
     Permission {
         question: String,
         #[serde(default = "default_allow_label")]
         allow_label: String,
+        #[serde(default = "default_deny_label")]
+        deny_label: String,
+        /// Offer a second allow button whose decision is "allow_always"
+        #[serde(default)]
+        allow_always: bool,
+        #[serde(default = "default_allow_always_label")]
+        allow_always_label: String,
+        /// Show an optional reason entry sent along with the decision
+        #[serde(default)]
+        ask_reason: bool,
+    },
+    /// Free text, optionally multi-line and checked against a regex
+    TextInput {
+        question: String,
+        #[serde(default)]
+        placeholder: Option<String>,
+        #[serde(default)]
+        multiline: bool,
+        /// Regex the whole answer has to match before it can be submitted
+        #[serde(default)]
+        pattern: Option<String>,
+        /// Shown when the answer doesn't match `pattern`
+        #[serde(default)]
+        pattern_error: Option<String>,
+    },
+    /// Two buttons with arbitrary labels
+    Confirm {
+        question: String,
+        #[serde(default = "default_confirm_label")]
+        confirm_label: String,
+        #[serde(default = "default_cancel_label")]
+        cancel_label: String,
+    },
+    /// 1..=max stars
+    Rating {
+        question: String,
+        #[serde(default = "default_rating_max")]
+        max: u8,
+    },
+    Slider {
+        question: String,
+        #[serde(default)]
+        min: f64,
+        #[serde(default = "default_slider_max")]
+        max: f64,
+        #[serde(default = "default_slider_step")]
+        step: f64,
+        /// Initial position; defaults to `min`
+        #[serde(default)]
+        value: Option<f64>,
+    },
+    Datetime {
+        question: String,
+        #[serde(default)]
+        mode: DatetimeMode,
+        /// Initial value in the reply format of `mode`; defaults to now
+        #[serde(default)]
+        value: Option<String>,
     },
 }
 
+/// Whether a multiple-choice card takes one answer or several
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChoiceMode {
+    /// Radio semantics: picking a row clears the others, and a hotkey submits
+    Single,
+    #[default]
+    Multi,
+}
+
+/// What a `datetime` card asks for, and the format of its reply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatetimeMode {
+    /// "YYYY-MM-DD"
+    Date,
+    /// "HH:MM"
+    Time,
+    /// "YYYY-MM-DDTHH:MM"
+    #[default]
+    Datetime,
+}
+
+impl DatetimeMode {
+    /// chrono format of the reply value
+    pub fn format(self) -> &'static str {
+        match self {
+            DatetimeMode::Date => "%Y-%m-%d",
+            DatetimeMode::Time => "%H:%M",
+            DatetimeMode::Datetime => "%Y-%m-%dT%H:%M",
+        }
+    }
+
+    /// Parse a value in this mode's format; a bare date or time counts as
+    /// that date at midnight or that time today
+    pub fn parse(self, value: &str) -> Option<NaiveDateTime> {
+        let value = value.trim();
+        match self {
+            DatetimeMode::Date => NaiveDate::parse_from_str(value, self.format())
+                .ok()
+                .map(|d| d.and_time(NaiveTime::MIN)),
+            DatetimeMode::Time => NaiveTime::parse_from_str(value, self.format())
+                .ok()
+                .map(|t| Local::now().date_naive().and_time(t)),
+            DatetimeMode::Datetime => NaiveDateTime::parse_from_str(value, self.format()).ok(),
+        }
+    }
+}
+
+impl NotificationCard {
+    /// Parse a card envelope: v1 has the card's fields inline,
+    /// `{"xnotid_card":"v1","type":..}`, v2 nests them,
+    /// `{"xnotid_card":"v2","card":{"type":..}}`. Without the marker the
+    /// value is taken for a bare card.
+    pub fn from_envelope(value: serde_json::Value) -> Result<Self, String> {
+        Self::parse_envelope(value).map(|(card, _)| card)
+    }
+
+    /// [`from_envelope`](Self::from_envelope), also returning the card's
+    /// `deadline_seconds` and `default_choice`
+    pub fn parse_envelope(
+        mut value: serde_json::Value,
+    ) -> Result<(Self, Option<CardDeadline>), String> {
+        let Some(envelope) = value.as_object_mut() else {
+            return Err("a card must be a JSON object".into());
+        };
+        let card = match envelope.remove("xnotid_card") {
+            None => value,
+            Some(marker) => match card_version(&marker) {
+                Some(1) => value,
+                Some(2..=CARD_VERSION) => envelope
+                    .remove("card")
+                    .ok_or("a v2 envelope needs a `card` object")?,
+                _ => return Err(format!("unsupported card version {}", marker)),
+            },
+        };
+        let deadline: DeadlineFields =
+            serde_json::from_value(card.clone()).map_err(|e| e.to_string())?;
+        let card: NotificationCard = serde_json::from_value(card).map_err(|e| e.to_string())?;
+        card.validate()?;
+        let deadline = match deadline {
+            DeadlineFields { deadline_seconds: None, default_choice: Some(_) } => {
+                return Err("`default_choice` needs `deadline_seconds`".into());
+            }
+            DeadlineFields { deadline_seconds: None, .. } => None,
+            DeadlineFields { deadline_seconds: Some(0), .. } => {
+                return Err("`deadline_seconds` must be greater than 0".into());
+            }
+            DeadlineFields { deadline_seconds: Some(seconds), default_choice } => {
+                let answer = card.default_answer(default_choice.as_ref())?;
+                Some(CardDeadline { seconds, answer })
+            }
+        };
+        Ok((card, deadline))
+    }
+
+    /// The reply sent for `default_choice` when the deadline passes: a
+    /// choice id (or list of ids), a permission decision, the text, `true`
+    /// to confirm, the rating, slider or date value. Without one the card's
+    /// "nothing chosen" reply is used (deny, cancel, the slider's start).
+    fn default_answer(
+        &self,
+        default: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, String> {
+        use serde_json::{Value, json};
+        let invalid = |what: &str| format!("`default_choice` must be {}", what);
+        let reply = match (self, default) {
+            (NotificationCard::MultipleChoice { choices, .. }, default) => {
+                let ids: Vec<&Value> = match default {
+                    None => Vec::new(),
+                    Some(Value::Array(ids)) => ids.iter().collect(),
+                    Some(id) => vec![id],
+                };
+                let selected = ids
+                    .into_iter()
+                    .map(|id| {
+                        choices
+                            .iter()
+                            .find(|c| id.as_str() == Some(c.id.as_str()))
+                            .map(|c| json!({ "id": c.id, "label": c.label }))
+                            .ok_or_else(|| invalid("one of the choice ids"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Also catches a required card without a default
+                if let Some(why) = self.selection_error(selected.len()) {
+                    return Err(format!("`default_choice` doesn't fit the card: {}", why));
+                }
+                json!({ "type": "multiple-choice", "selected": selected, "other": null })
+            }
+            (NotificationCard::Permission { allow_always, .. }, default) => {
+                let decision = match default.map(|d| d.as_str()) {
+                    None => "deny",
+                    Some(Some(d @ ("allow" | "deny"))) => d,
+                    Some(Some("allow_always")) if *allow_always => "allow_always",
+                    Some(_) => return Err(invalid("\"allow\", \"allow_always\" or \"deny\"")),
+                };
+                json!({ "type": "permission", "decision": decision, "reason": null })
+            }
+            (NotificationCard::TextInput { .. }, None) => {
+                json!({ "type": "text-input", "text": null })
+            }
+            (NotificationCard::TextInput { pattern, .. }, Some(Value::String(text))) => {
+                // Anchored like the input's own check
+                let pattern = pattern.as_ref().map(|p| regex::Regex::new(&format!("^(?:{})$", p)));
+                if let Some(pattern) = pattern {
+                    let pattern = pattern.map_err(|e| format!("invalid `pattern`: {}", e))?;
+                    if !pattern.is_match(text) {
+                        return Err(invalid("a string matching `pattern`"));
+                    }
+                }
+                json!({ "type": "text-input", "text": text })
+            }
+            (NotificationCard::Confirm { confirm_label, cancel_label, .. }, default) => {
+                let confirmed = match default {
+                    None => false,
+                    Some(Value::Bool(confirmed)) => *confirmed,
+                    Some(_) => return Err(invalid("true or false")),
+                };
+                let label = if confirmed { confirm_label } else { cancel_label };
+                json!({ "type": "confirm", "confirmed": confirmed, "label": label })
+            }
+            (NotificationCard::Rating { max, .. }, None) => {
+                json!({ "type": "rating", "value": null, "max": max })
+            }
+            (NotificationCard::Rating { max, .. }, Some(value)) => {
+                let value = value.as_u64().filter(|v| (1..=u64::from(*max)).contains(v));
+                let value = value.ok_or_else(|| invalid(&format!("a rating from 1 to {}", max)))?;
+                json!({ "type": "rating", "value": value, "max": max })
+            }
+            (NotificationCard::Slider { min, max, value, .. }, default) => {
+                let value = match default {
+                    None => value.unwrap_or(*min),
+                    Some(v) => v
+                        .as_f64()
+                        .filter(|v| (*min..=*max).contains(v))
+                        .ok_or_else(|| invalid(&format!("a number from {} to {}", min, max)))?,
+                };
+                json!({ "type": "slider", "value": value })
+            }
+            (NotificationCard::Datetime { .. }, None) => {
+                json!({ "type": "datetime", "value": null })
+            }
+            (NotificationCard::Datetime { mode, .. }, Some(Value::String(value)))
+                if mode.parse(value).is_some() =>
+            {
+                json!({ "type": "datetime", "value": value })
+            }
+            (NotificationCard::TextInput { .. }, Some(_)) => return Err(invalid("a string")),
+            (NotificationCard::Datetime { mode, .. }, Some(_)) => {
+                return Err(invalid(&format!("a value formatted as {}", mode.format())));
+            }
+        };
+        Ok(reply)
+    }
+
+    pub fn question(&self) -> &str {
+        match self {
+            NotificationCard::MultipleChoice { question, .. }
+            | NotificationCard::Permission { question, .. }
+            | NotificationCard::TextInput { question, .. }
+            | NotificationCard::Confirm { question, .. }
+            | NotificationCard::Rating { question, .. }
+            | NotificationCard::Slider { question, .. }
+            | NotificationCard::Datetime { question, .. } => question,
+        }
+    }
+
+    /// Fewest and most answers a multiple-choice card accepts
+    pub fn selection_limits(&self) -> Option<(usize, usize)> {
+        let NotificationCard::MultipleChoice {
+            mode,
+            min_selected,
+            max_selected,
+            required,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        let min = min_selected.unwrap_or(0).max(usize::from(*required));
+        let max = match mode {
+            ChoiceMode::Single => 1,
+            ChoiceMode::Multi => max_selected.unwrap_or(usize::MAX),
+        };
+        Some((min, max))
+    }
+
+    /// Why `count` answers don't fit a multiple-choice card, if they don't
+    pub fn selection_error(&self, count: usize) -> Option<String> {
+        let (min, max) = self.selection_limits()?;
+        if count < min {
+            Some(match min {
+                1 => "Pick an answer".to_string(),
+                min => format!("Pick at least {} answers", min),
+            })
+        } else if count > max {
+            Some(match max {
+                1 => "Pick only one answer".to_string(),
+                max => format!("Pick at most {} answers", max),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Checks serde can't express: compiling patterns, sane ranges
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            NotificationCard::MultipleChoice {
+                choices,
+                allow_other,
+                mode,
+                max_selected,
+                ..
+            } => {
+                if choices.is_empty() && !allow_other {
+                    return Err("`choices` is empty and `allow_other` is off".into());
+                }
+                if *mode == ChoiceMode::Single && max_selected.is_some_and(|max| max != 1) {
+                    return Err("`max_selected` must be 1 in single mode".into());
+                }
+                let (min, max) = self.selection_limits().unwrap_or_default();
+                let options = choices.len() + usize::from(*allow_other);
+                if min > max {
+                    return Err(format!("`min_selected` {} is above `max_selected` {}", min, max));
+                }
+                if min > options {
+                    return Err(format!("`min_selected` {} is above the {} options", min, options));
+                }
+            }
+            NotificationCard::TextInput { pattern: Some(pattern), .. } => {
+                regex::Regex::new(pattern).map_err(|e| format!("invalid `pattern`: {}", e))?;
+            }
+            NotificationCard::Rating { max, .. } => {
+                if !(1..=10).contains(max) {
+                    return Err(format!("`max` must be between 1 and 10, got {}", max));
+                }
+            }
+            NotificationCard::Slider { min, max, step, value, .. } => {
+                if !(min.is_finite() && max.is_finite() && min < max) {
+                    return Err(format!("`min` must be below `max`, got {} and {}", min, max));
+                }
+                if !(step.is_finite() && *step > 0.0) {
+                    return Err(format!("`step` must be greater than 0, got {}", step));
+                }
+                if value.is_some_and(|v| v < *min || v > *max) {
+                    return Err("`value` is outside `min`..`max`".into());
+                }
+            }
+            NotificationCard::Datetime { mode, value: Some(value), .. } => {
+                if mode.parse(value).is_none() {
+                    return Err(format!("`value` {:?} doesn't match {}", value, mode.format()));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 fn default_allow_label() -> String {
     "Allow".to_string()
 }
 
-#[derive(Debug, Deserialize)]
-struct CardEnvelope {
-    #[serde(rename = "xnotid_card")]
-    marker: String,
-    #[serde(flatten)]
-    card: NotificationCard,
+fn default_deny_label() -> String {
+    "Deny".to_string()
+}
+
+fn default_allow_always_label() -> String {
+    "Always allow".to_string()
+}
+
+fn default_confirm_label() -> String {
+    "OK".to_string()
+}
+
+fn default_cancel_label() -> String {
+    "Cancel".to_string()
+}
+
+fn default_rating_max() -> u8 {
+    5
+}
+
+fn default_slider_max() -> f64 {
+    100.0
+}
+
+fn default_slider_step() -> f64 {
+    1.0
+}
+
+/// A card's `deadline_seconds` and `default_choice`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardDeadline {
+    /// Counted from when the notification arrived
+    pub seconds: u64,
+    /// The card's reply for `default_choice`
+    pub answer: serde_json::Value,
+}
+
+impl CardDeadline {
+    /// The `ActionInvoked` payload sent when the deadline passes
+    pub fn timed_out_answer(&self) -> serde_json::Value {
+        let mut answer = self.answer.clone();
+        if let Some(fields) = answer.as_object_mut() {
+            fields.insert("timed_out".to_string(), true.into());
+        }
+        answer
+    }
+}
+
+/// Deadline fields sit next to the card's own in the card object
+#[derive(Deserialize)]
+struct DeadlineFields {
+    #[serde(default)]
+    deadline_seconds: Option<u64>,
+    #[serde(default)]
+    default_choice: Option<serde_json::Value>,
+}
+
+/// The version of an `xnotid_card` marker: "v2", "2" or 2
+fn card_version(marker: &serde_json::Value) -> Option<u32> {
+    match marker {
+        serde_json::Value::String(s) => s.strip_prefix('v').unwrap_or(s).parse().ok(),
+        serde_json::Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+        _ => None,
+    }
 }
 
 /// Urgency levels per the freedesktop notification spec
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Urgency {
+    #[serde(alias = "low")]
     Low = 0,
+    #[serde(alias = "normal")]
     Normal = 1,
+    #[serde(alias = "critical")]
     Critical = 2,
 }
 
@@ -72,8 +517,19 @@ pub struct Action {
     pub label: String,
 }
 
+/// The freedesktop sound hints, kept for the sound player
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SoundHints {
+    /// `sound-file`: path of a sound to play
+    pub file: Option<String>,
+    /// `sound-name`: themeable name from the freedesktop sound naming spec
+    pub name: Option<String>,
+    /// `suppress-sound`: play nothing, not even a configured default
+    pub suppress: bool,
+}
+
 /// Image data from hints (raw pixel data or a path/icon name)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ImageData {
     Raw {
         width: i32,
@@ -82,6 +538,7 @@ pub enum ImageData {
         has_alpha: bool,
         bits_per_sample: i32,
         channels: i32,
+        #[serde(with = "base64_bytes")]
         data: Vec<u8>,
     },
     Path(String),
@@ -89,8 +546,25 @@ pub enum ImageData {
     None,
 }
 
+/// Pixel data as one base64 string (in the saved state) instead of a JSON
+/// array of numbers several times its size
+mod base64_bytes {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        STANDARD.decode(text).map_err(serde::de::Error::custom)
+    }
+}
+
 /// Core notification data structure
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
     /// Internal auto-incrementing ID (matches D-Bus replaces_id protocol)
     pub id: u32,
@@ -128,8 +602,37 @@ pub struct Notification {
     pub progress: Option<i32>,
     /// Per-notification CSS class override
     pub css_class: Option<String>,
-    /// Optional structured card payload parsed from body JSON
+    /// Optional structured card from the `x-xnotid-card` hint or body JSON
     pub card: Option<NotificationCard>,
+    /// Why the card the notification carried couldn't be used
+    #[serde(default)]
+    pub card_error: Option<String>,
+    /// When the card answers itself with its default
+    #[serde(default)]
+    pub card_deadline: Option<CardDeadline>,
+    /// Set by a `no_log` rule: keep this notification out of the JSONL log
+    #[serde(default)]
+    pub skip_log: bool,
+    /// Stays after an action is invoked until dismissed or closed by the sender
+    #[serde(default)]
+    pub resident: bool,
+    /// Action keys double as icon names, shown instead of the labels
+    #[serde(default)]
+    pub action_icons: bool,
+    /// Spec category such as "email.arrived"
+    #[serde(default)]
+    pub category: Option<String>,
+    /// PID of the sending process, if it told us
+    #[serde(default)]
+    pub sender_pid: Option<u32>,
+    #[serde(default)]
+    pub sound: SoundHints,
+    /// `x-kde-reply-placeholder-text`: hint shown in an empty reply entry
+    #[serde(default)]
+    pub reply_placeholder: Option<String>,
+    /// `x-kde-reply-submit-button-text`: label of the reply's send button
+    #[serde(default)]
+    pub reply_submit_label: Option<String>,
 }
 
 impl Notification {
@@ -190,6 +693,19 @@ impl Notification {
         })
     }
 
+    fn get_hint_u32(hints: &HashMap<String, OwnedValue>, key: &str) -> Option<u32> {
+        hints.get(key).and_then(|v| {
+            let val: Value<'_> = v.try_into().ok()?;
+            match val {
+                Value::U32(n) => Some(n),
+                Value::I32(n) => u32::try_from(n).ok(),
+                Value::I64(n) => u32::try_from(n).ok(),
+                Value::U64(n) => u32::try_from(n).ok(),
+                _ => None,
+            }
+        })
+    }
+
     pub fn new(
         id: u32,
         app_name: String,
@@ -200,7 +716,16 @@ impl Notification {
         hints: HashMap<String, OwnedValue>,
         expire_timeout: i32,
     ) -> Self {
-        let card = Self::parse_card_body(&body);
+        let card_hint = Self::get_hint_string(&hints, CARD_HINT);
+        let (card, card_deadline, card_error) =
+            match Self::parse_card(card_hint.as_deref(), &body) {
+                Ok(Some((card, deadline))) => (Some(card), deadline, None),
+                Ok(None) => (None, None, None),
+                Err(e) => {
+                    log::warn!("Invalid card from {:?}: {}", app_name, e);
+                    (None, None, Some(e))
+                }
+            };
 
         // Parse actions: they come as [key, label, key, label, ...]
         let actions: Vec<Action> = actions_raw
@@ -247,6 +772,22 @@ impl Notification {
         // Parse image data from hints
         let image = Self::parse_image(&hints, &app_icon);
 
+        let resident = Self::get_hint_bool(&hints, "resident").unwrap_or(false);
+        let action_icons = Self::get_hint_bool(&hints, "action-icons").unwrap_or(false);
+        let category = Self::get_hint_string(&hints, "category").filter(|c| !c.is_empty());
+        let sender_pid = Self::get_hint_u32(&hints, "sender-pid");
+
+        let reply_placeholder = Self::get_hint_string(&hints, "x-kde-reply-placeholder-text")
+            .filter(|t| !t.is_empty());
+        let reply_submit_label = Self::get_hint_string(&hints, "x-kde-reply-submit-button-text")
+            .filter(|t| !t.is_empty());
+
+        let sound = SoundHints {
+            file: Self::get_hint_string(&hints, "sound-file").filter(|f| !f.is_empty()),
+            name: Self::get_hint_string(&hints, "sound-name").filter(|n| !n.is_empty()),
+            suppress: Self::get_hint_bool(&hints, "suppress-sound").unwrap_or(false),
+        };
+
         // Store simple string representations of remaining hints
         let hints_simple: HashMap<String, String> = hints
             .iter()
@@ -261,7 +802,7 @@ impl Notification {
                         | "icon_data"
                 )
             })
-            .map(|(k, v)| (k.clone(), format!("{:?}", v)))
+            .map(|(k, v)| (k.clone(), Self::hint_to_string(v)))
             .collect();
 
         Self {
@@ -284,15 +825,78 @@ impl Notification {
             progress,
             css_class,
             card,
+            card_error,
+            card_deadline,
+            skip_log: false,
+            resident,
+            action_icons,
+            category,
+            sender_pid,
+            sound,
+            reply_placeholder,
+            reply_submit_label,
         }
     }
 
-    fn parse_card_body(body: &str) -> Option<NotificationCard> {
-        let parsed = serde_json::from_str::<CardEnvelope>(body).ok()?;
-        if parsed.marker != "v1" {
-            return None;
+    /// The `inline-reply` action, if the sender takes typed replies
+    pub fn reply_action(&self) -> Option<&Action> {
+        self.actions.iter().find(|a| a.key == INLINE_REPLY)
+    }
+
+    /// CSS classes for the category: "email.arrived" gives "category-email"
+    /// and "category-email-arrived"
+    pub fn category_classes(&self) -> Vec<String> {
+        let Some(ref category) = self.category else {
+            return Vec::new();
+        };
+        let mut classes = Vec::new();
+        let mut class = "category".to_string();
+        for part in category.split('.').filter(|p| !p.is_empty()) {
+            class.push('-');
+            class.extend(part.chars().map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            }));
+            classes.push(class.clone());
         }
-        Some(parsed.card)
+        classes
+    }
+
+    /// Plain text form of a hint value (what rules match against)
+    fn hint_to_string(value: &OwnedValue) -> String {
+        match Value::try_from(value) {
+            Ok(Value::Str(s)) => s.to_string(),
+            Ok(Value::ObjectPath(p)) => p.to_string(),
+            Ok(Value::Bool(b)) => b.to_string(),
+            Ok(Value::U8(n)) => n.to_string(),
+            Ok(Value::I16(n)) => n.to_string(),
+            Ok(Value::U16(n)) => n.to_string(),
+            Ok(Value::I32(n)) => n.to_string(),
+            Ok(Value::U32(n)) => n.to_string(),
+            Ok(Value::I64(n)) => n.to_string(),
+            Ok(Value::U64(n)) => n.to_string(),
+            Ok(Value::F64(n)) => n.to_string(),
+            _ => format!("{:?}", value),
+        }
+    }
+
+    /// The card in the `x-xnotid-card` hint, else in the body. A body only
+    /// counts as a card when it is a JSON object with the `xnotid_card` marker.
+    fn parse_card(
+        hint: Option<&str>,
+        body: &str,
+    ) -> Result<Option<(NotificationCard, Option<CardDeadline>)>, String> {
+        let value = match hint {
+            Some(hint) => serde_json::from_str(hint).map_err(|e| format!("invalid JSON: {}", e))?,
+            None => match serde_json::from_str::<serde_json::Value>(body) {
+                Ok(value) if value.get("xnotid_card").is_some() => value,
+                _ => return Ok(None),
+            },
+        };
+        NotificationCard::parse_envelope(value).map(Some)
     }
 
     fn parse_image(
@@ -334,7 +938,7 @@ impl Notification {
 }
 
 /// Log entry for the JSONL notification log
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
     pub uuid: String,
     pub timestamp: String,
@@ -362,3 +966,39 @@ pub struct LogEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub group: Option<String>,
 }
+
+impl LogEntry {
+    /// Rebuild a notification from a "received" record so it can be shown
+    /// again. Actions and images are not logged, so they are not restored.
+    pub fn to_notification(&self) -> Notification {
+        let mut noti = Notification::new(
+            0,
+            self.app_name.clone().unwrap_or_default(),
+            self.app_icon.clone().unwrap_or_default(),
+            self.summary.clone().unwrap_or_default(),
+            self.body.clone().unwrap_or_default(),
+            Vec::new(),
+            HashMap::new(),
+            -1,
+        );
+        noti.urgency = match self.urgency.as_deref() {
+            Some("Low") => Urgency::Low,
+            Some("Critical") => Urgency::Critical,
+            _ => Urgency::Normal,
+        };
+        noti.group = self.group.clone();
+        noti.desktop_entry = self.desktop_entry.clone();
+        noti.hints = self.hints.clone().unwrap_or_default();
+        noti.category = noti.hints.get("category").cloned();
+        if let Some(hint) = noti.hints.get(CARD_HINT) {
+            (noti.card, noti.card_error) = match Notification::parse_card(Some(hint), &noti.body) {
+                Ok(card) => (card.map(|(card, _)| card), None),
+                Err(e) => (None, Some(e)),
+            };
+            noti.acknowledge_to_dismiss = noti.card.is_some();
+        }
+        // Nobody is waiting for a replayed card's answer
+        noti.card_deadline = None;
+        noti
+    }
+}
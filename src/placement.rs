@@ -0,0 +1,257 @@
+use crate::config::{Config, PositionX, PositionY};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::*;
+use x11rb::rust_connection::RustConnection;
+
+/// _NET_MOVERESIZE_WINDOW flags: NorthWest gravity, x + y present, source = pager
+const MOVERESIZE_FLAGS: u32 = 1 | (1 << 8) | (1 << 9) | (2 << 12);
+
+/// A screen rectangle in X11 (device pixel) coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Area {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Area {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Overlap of two rectangles, or None if they don't touch
+    pub fn intersect(&self, other: &Area) -> Option<Area> {
+        let x1 = self.x.max(other.x);
+        let y1 = self.y.max(other.y);
+        let x2 = (self.x + self.width).min(other.x + other.width);
+        let y2 = (self.y + self.height).min(other.y + other.height);
+        if x2 > x1 && y2 > y1 {
+            Some(Area::new(x1, y1, x2 - x1, y2 - y1))
+        } else {
+            None
+        }
+    }
+}
+
+/// Top-left origin for a `width`x`height` window anchored at the configured
+/// corner of `area`. `margin_right` is the gap to the anchored vertical edge
+/// (left or right) and `margin_top` the gap to the anchored horizontal edge
+/// (top or bottom), so a bottom-anchored window grows upward.
+pub fn window_origin(config: &Config, area: &Area, width: i32, height: i32) -> (i32, i32) {
+    let x = match config.position_x {
+        PositionX::Left => area.x + config.margin_right,
+        PositionX::Center => area.x + (area.width - width) / 2,
+        PositionX::Right => area.x + area.width - width - config.margin_right,
+    };
+
+    let y = match config.position_y {
+        PositionY::Bottom => area.y + area.height - height - config.margin_top,
+        PositionY::Top => area.y + config.margin_top,
+    };
+
+    (x, y)
+}
+
+struct Atoms {
+    net_supported: Atom,
+    net_workarea: Atom,
+    net_current_desktop: Atom,
+    net_moveresize_window: Atom,
+    net_wm_pid: Atom,
+    net_wm_name: Atom,
+    net_active_window: Atom,
+    net_wm_state: Atom,
+    net_wm_state_fullscreen: Atom,
+    utf8_string: Atom,
+}
+
+fn intern_atom(conn: &RustConnection, name: &str) -> Result<Atom, Box<dyn std::error::Error>> {
+    Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+}
+
+/// Moves our own top-level windows on X11. GTK4 has no window.move(), so we
+/// look the windows up by `_NET_WM_PID` + title and move them with x11rb.
+pub struct X11Placer {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+    /// Window title -> XID, resolved lazily
+    windows: RefCell<HashMap<String, Window>>,
+}
+
+impl X11Placer {
+    pub fn connect() -> Option<Self> {
+        match Self::try_connect() {
+            Ok(placer) => Some(placer),
+            Err(err) => {
+                log::warn!("X11 placement unavailable: {}", err);
+                None
+            }
+        }
+    }
+
+    fn try_connect() -> Result<Self, Box<dyn std::error::Error>> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms {
+            net_supported: intern_atom(&conn, "_NET_SUPPORTED")?,
+            net_workarea: intern_atom(&conn, "_NET_WORKAREA")?,
+            net_current_desktop: intern_atom(&conn, "_NET_CURRENT_DESKTOP")?,
+            net_moveresize_window: intern_atom(&conn, "_NET_MOVERESIZE_WINDOW")?,
+            net_wm_pid: intern_atom(&conn, "_NET_WM_PID")?,
+            net_wm_name: intern_atom(&conn, "_NET_WM_NAME")?,
+            net_active_window: intern_atom(&conn, "_NET_ACTIVE_WINDOW")?,
+            net_wm_state: intern_atom(&conn, "_NET_WM_STATE")?,
+            net_wm_state_fullscreen: intern_atom(&conn, "_NET_WM_STATE_FULLSCREEN")?,
+            utf8_string: intern_atom(&conn, "UTF8_STRING")?,
+        };
+        Ok(Self {
+            conn,
+            root,
+            atoms,
+            windows: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn property32(&self, window: Window, property: Atom) -> Option<Vec<u32>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+            .ok()?
+            .reply()
+            .ok()?;
+        Some(reply.value32()?.collect())
+    }
+
+    fn property_string(&self, window: Window, property: Atom, type_: Atom) -> Option<String> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, type_, 0, 1024)
+            .ok()?
+            .reply()
+            .ok()?;
+        if reply.value.is_empty() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&reply.value).into_owned())
+    }
+
+    /// Usable area of the current desktop (`_NET_WORKAREA`), if the WM sets it
+    pub fn workarea(&self) -> Option<Area> {
+        let values = self.property32(self.root, self.atoms.net_workarea)?;
+        let desktop = self
+            .property32(self.root, self.atoms.net_current_desktop)
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0) as usize;
+        let rect = values
+            .chunks_exact(4)
+            .nth(desktop)
+            .or_else(|| values.chunks_exact(4).next())?;
+        Some(Area::new(
+            rect[0] as i32,
+            rect[1] as i32,
+            rect[2] as i32,
+            rect[3] as i32,
+        ))
+    }
+
+    /// Monitor geometry clipped to the workarea (panels, docks, struts)
+    pub fn usable_area(&self, monitor: &Area) -> Area {
+        self.workarea()
+            .and_then(|work| work.intersect(monitor))
+            .unwrap_or(*monitor)
+    }
+
+    /// Whether the focused window (`_NET_ACTIVE_WINDOW`) is fullscreen
+    pub fn active_window_fullscreen(&self) -> bool {
+        let active = self
+            .property32(self.root, self.atoms.net_active_window)
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0);
+        active != 0
+            && self
+                .property32(active, self.atoms.net_wm_state)
+                .is_some_and(|state| state.contains(&self.atoms.net_wm_state_fullscreen))
+    }
+
+    /// Move the window titled `title` to the configured corner of `monitor`.
+    pub fn place(&self, title: &str, monitor: &Area, config: &Config, width: i32, height: i32) {
+        let area = self.usable_area(monitor);
+        let (x, y) = window_origin(config, &area, width, height);
+
+        let Some(window) = self.find_window(title) else {
+            log::debug!("Window {:?} not found for placement yet", title);
+            return;
+        };
+
+        if let Err(err) = self.move_window(window, x, y) {
+            log::warn!("Failed to move {:?} to ({}, {}): {}", title, x, y, err);
+            self.windows.borrow_mut().remove(title);
+        } else {
+            log::debug!("Placed {:?} at ({}, {}) {}x{}", title, x, y, width, height);
+        }
+    }
+
+    fn find_window(&self, title: &str) -> Option<Window> {
+        if let Some(&window) = self.windows.borrow().get(title) {
+            return Some(window);
+        }
+
+        let pid = std::process::id();
+        let mut queue = VecDeque::from([self.root]);
+        while let Some(window) = queue.pop_front() {
+            let owned = self
+                .property32(window, self.atoms.net_wm_pid)
+                .and_then(|v| v.first().copied())
+                == Some(pid);
+            if owned && self.window_title(window).as_deref() == Some(title) {
+                self.windows.borrow_mut().insert(title.to_string(), window);
+                return Some(window);
+            }
+            if let Some(tree) = self.conn.query_tree(window).ok().and_then(|c| c.reply().ok()) {
+                queue.extend(tree.children);
+            }
+        }
+        None
+    }
+
+    fn window_title(&self, window: Window) -> Option<String> {
+        self.property_string(window, self.atoms.net_wm_name, self.atoms.utf8_string)
+            .or_else(|| self.property_string(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+    }
+
+    fn supports_moveresize(&self) -> bool {
+        self.property32(self.root, self.atoms.net_supported)
+            .is_some_and(|atoms| atoms.contains(&self.atoms.net_moveresize_window))
+    }
+
+    fn move_window(&self, window: Window, x: i32, y: i32) -> Result<(), Box<dyn std::error::Error>> {
+        // Direct configure works for unmanaged windows and most WMs honour it
+        // as a ConfigureRequest; EWMH WMs also get an explicit move request.
+        self.conn
+            .configure_window(window, &ConfigureWindowAux::new().x(x).y(y))?;
+
+        if self.supports_moveresize() {
+            let msg = ClientMessageEvent {
+                response_type: CLIENT_MESSAGE_EVENT,
+                format: 32,
+                sequence: 0,
+                window,
+                type_: self.atoms.net_moveresize_window,
+                data: ClientMessageData::from([MOVERESIZE_FLAGS, x as u32, y as u32, 0, 0]),
+            };
+            self.conn.send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                msg,
+            )?;
+        }
+
+        self.conn.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::notification::{Notification, Urgency};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One entry of the `rules:` list in config.yaml. Every criterion given in
+/// `match` must hold; the `set` overrides of all matching rules are applied
+/// in order, so later rules win unless an earlier one has `stop: true`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, rename = "match")]
+    pub criteria: RuleMatch,
+    #[serde(default)]
+    pub set: RuleActions,
+    /// Don't evaluate later rules once this one matched
+    #[serde(default)]
+    pub stop: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleMatch {
+    /// Exact app_name, case-insensitive
+    pub app_name: Option<String>,
+    /// Exact desktop-entry hint, case-insensitive
+    pub desktop_entry: Option<String>,
+    /// Regex searched in the summary
+    pub summary: Option<String>,
+    /// Regex searched in the body
+    pub body: Option<String>,
+    pub urgency: Option<Urgency>,
+    /// Spec category, case-insensitive; "email" also matches "email.arrived"
+    pub category: Option<String>,
+    /// Hint name -> regex searched in the hint's value
+    #[serde(default)]
+    pub hints: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleActions {
+    pub urgency: Option<Urgency>,
+    /// Popup timeout in seconds, 0 = never expire
+    pub timeout: Option<u32>,
+    pub css_class: Option<String>,
+    pub group: Option<String>,
+    pub transient: Option<bool>,
+    pub acknowledge: Option<bool>,
+    /// Don't show a popup; the notification only goes to the center
+    #[serde(default)]
+    pub no_popup: bool,
+    /// Keep out of the center and saved state (popup only, like `transient`)
+    #[serde(default)]
+    pub no_history: bool,
+    /// Don't write this notification to the JSONL log
+    #[serde(default)]
+    pub no_log: bool,
+}
+
+/// What the store still has to do after the rules rewrote a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleOutcome {
+    pub popup: bool,
+}
+
+struct CompiledRule {
+    rule: Rule,
+    summary: Option<Regex>,
+    body: Option<Regex>,
+    hints: Vec<(String, Regex)>,
+}
+
+/// Rules with their patterns compiled once at config load
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// Compile `rules`; a rule with an invalid regex is logged and skipped.
+    pub fn compile(rules: &[Rule]) -> Self {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, rule)| match CompiledRule::new(rule) {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    log::warn!("Skipping rule {}: {}", rule_label(idx, rule), e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Apply the overrides of every matching rule to `noti`.
+    pub fn apply(&self, noti: &mut Notification) -> RuleOutcome {
+        let mut outcome = RuleOutcome { popup: true };
+        for (idx, compiled) in self.rules.iter().enumerate() {
+            if !compiled.matches(noti) {
+                continue;
+            }
+            log::debug!("Rule {} matched notification {:?}", rule_label(idx, &compiled.rule), noti.summary);
+
+            let set = &compiled.rule.set;
+            if let Some(urgency) = set.urgency {
+                noti.urgency = urgency;
+            }
+            if let Some(timeout) = set.timeout {
+                // Notification::timeout is in D-Bus milliseconds, 0 = never
+                noti.timeout = timeout.saturating_mul(1000).min(i32::MAX as u32) as i32;
+            }
+            if let Some(ref css_class) = set.css_class {
+                noti.css_class = Some(css_class.clone());
+            }
+            if let Some(ref group) = set.group {
+                noti.group = Some(group.clone());
+            }
+            if let Some(transient) = set.transient {
+                noti.transient = transient;
+            }
+            if let Some(acknowledge) = set.acknowledge {
+                noti.acknowledge_to_dismiss = acknowledge;
+            }
+            if set.no_history {
+                noti.transient = true;
+            }
+            if set.no_log {
+                noti.skip_log = true;
+            }
+            if set.no_popup {
+                outcome.popup = false;
+            }
+
+            if compiled.rule.stop {
+                break;
+            }
+        }
+        outcome
+    }
+}
+
+impl Rule {
+    /// Check that every pattern of the rule compiles
+    pub fn validate(&self) -> Result<(), regex::Error> {
+        CompiledRule::new(self).map(|_| ())
+    }
+}
+
+impl CompiledRule {
+    fn new(rule: &Rule) -> Result<Self, regex::Error> {
+        let compile = |pattern: &Option<String>| pattern.as_deref().map(Regex::new).transpose();
+        let mut hints = rule
+            .criteria
+            .hints
+            .iter()
+            .map(|(key, pattern)| Ok((key.clone(), Regex::new(pattern)?)))
+            .collect::<Result<Vec<_>, regex::Error>>()?;
+        hints.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Self {
+            summary: compile(&rule.criteria.summary)?,
+            body: compile(&rule.criteria.body)?,
+            hints,
+            rule: rule.clone(),
+        })
+    }
+
+    fn matches(&self, noti: &Notification) -> bool {
+        let criteria = &self.rule.criteria;
+        if criteria
+            .app_name
+            .as_ref()
+            .is_some_and(|app| !app.eq_ignore_ascii_case(&noti.app_name))
+        {
+            return false;
+        }
+        if criteria.desktop_entry.as_ref().is_some_and(|entry| {
+            !noti
+                .desktop_entry
+                .as_deref()
+                .is_some_and(|de| entry.eq_ignore_ascii_case(de))
+        }) {
+            return false;
+        }
+        if criteria.urgency.is_some_and(|urgency| urgency != noti.urgency) {
+            return false;
+        }
+        if criteria.category.as_ref().is_some_and(|category| {
+            !noti
+                .category
+                .as_deref()
+                .is_some_and(|c| category_matches(category, c))
+        }) {
+            return false;
+        }
+        if self.summary.as_ref().is_some_and(|re| !re.is_match(&noti.summary)) {
+            return false;
+        }
+        if self.body.as_ref().is_some_and(|re| !re.is_match(&noti.body)) {
+            return false;
+        }
+        self.hints.iter().all(|(key, re)| {
+            noti.hints.get(key).is_some_and(|value| re.is_match(value))
+        })
+    }
+}
+
+/// `wanted` is the category itself or its class ("email" for "email.arrived")
+fn category_matches(wanted: &str, category: &str) -> bool {
+    category.eq_ignore_ascii_case(wanted)
+        || category
+            .split_once('.')
+            .is_some_and(|(class, _)| class.eq_ignore_ascii_case(wanted))
+}
+
+fn rule_label(idx: usize, rule: &Rule) -> String {
+    match rule.name {
+        Some(ref name) => format!("#{} ({})", idx + 1, name),
+        None => format!("#{}", idx + 1),
+    }
+}
@@ -0,0 +1,221 @@
+use crate::config::Config;
+use crate::notification::{Notification, Urgency};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// File extensions of the freedesktop sound theme spec, in lookup order
+const EXTENSIONS: [&str; 3] = ["oga", "ogg", "wav"];
+
+/// Plays one sound file. Settings are read from the config passed to each
+/// call, so a reloaded config applies immediately.
+pub trait SoundPlayer: Send {
+    fn play(&mut self, config: &Config, file: &Path);
+}
+
+/// Runs `sound_command` (e.g. `paplay` or `aplay -q`) with the file appended
+#[derive(Debug, Default)]
+pub struct CommandPlayer;
+
+impl SoundPlayer for CommandPlayer {
+    fn play(&mut self, config: &Config, file: &Path) {
+        let Some((program, args)) = config.sound_command.split_first() else {
+            return;
+        };
+        let child = Command::new(program)
+            .args(args)
+            .arg(file)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            // Reap it off the main loop
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => log::warn!("Failed to run sound command {:?}: {}", program, e),
+        }
+    }
+}
+
+/// Plays nothing; the store's default until the daemon installs a real player
+#[derive(Debug, Default)]
+pub struct NullPlayer;
+
+impl SoundPlayer for NullPlayer {
+    fn play(&mut self, _config: &Config, _file: &Path) {}
+}
+
+/// Decides whether and what a notification plays, and hands it to the player
+pub struct Sounds {
+    player: Box<dyn SoundPlayer>,
+    /// When the last sound started, for `sound_min_interval_ms`
+    last_played: Option<Instant>,
+}
+
+impl Default for Sounds {
+    fn default() -> Self {
+        Self::new(Box::new(NullPlayer))
+    }
+}
+
+impl Sounds {
+    pub fn new(player: Box<dyn SoundPlayer>) -> Self {
+        Self {
+            player,
+            last_played: None,
+        }
+    }
+
+    pub fn set_player(&mut self, player: Box<dyn SoundPlayer>) {
+        self.player = player;
+    }
+
+    /// Play the sound for `noti`, unless sounds are off or one already
+    /// started within `sound_min_interval_ms`. Returns the file played.
+    pub fn play_for(&mut self, config: &Config, noti: &Notification) -> Option<PathBuf> {
+        if !config.sound_enabled {
+            return None;
+        }
+        let file = resolve(config, noti)?;
+
+        let now = Instant::now();
+        let interval = Duration::from_millis(config.sound_min_interval_ms.into());
+        if self.last_played.is_some_and(|last| now.duration_since(last) < interval) {
+            log::debug!("Skipping sound {:?}: rate limited", file);
+            return None;
+        }
+        self.last_played = Some(now);
+
+        log::debug!("Playing sound {:?}", file);
+        self.player.play(config, &file);
+        Some(file)
+    }
+}
+
+/// Which file `noti` should play: nothing if it has `suppress-sound`, else
+/// the `sound_apps` entry for its app, its `sound-file` or `sound-name`
+/// hint, or the configured default for its urgency. An empty `sound_apps`
+/// entry silences the app.
+pub fn resolve(config: &Config, noti: &Notification) -> Option<PathBuf> {
+    if noti.sound.suppress {
+        return None;
+    }
+    let app = config
+        .sound_apps
+        .iter()
+        .find(|(app, _)| app.eq_ignore_ascii_case(&noti.app_name));
+    if let Some((_, sound)) = app {
+        return find_sound(config, sound);
+    }
+    if let Some(ref file) = noti.sound.file {
+        return Some(PathBuf::from(file.strip_prefix("file://").unwrap_or(file)));
+    }
+    if let Some(ref name) = noti.sound.name {
+        let found = lookup(&config.sound_theme, name);
+        if found.is_none() {
+            log::debug!("No sound named {:?} in theme {:?}", name, config.sound_theme);
+        }
+        return found;
+    }
+    let default = match noti.urgency {
+        Urgency::Low => &config.sound_low,
+        Urgency::Normal => &config.sound_normal,
+        Urgency::Critical => &config.sound_critical,
+    };
+    default.as_deref().and_then(|sound| find_sound(config, sound))
+}
+
+/// A configured sound: a path if it has a slash (`~/` is the home
+/// directory), else a sound theme name
+fn find_sound(config: &Config, sound: &str) -> Option<PathBuf> {
+    let sound = sound.trim();
+    if sound.is_empty() {
+        None
+    } else if let (Some(rest), Some(home)) = (sound.strip_prefix("~/"), dirs::home_dir()) {
+        Some(home.join(rest))
+    } else if sound.contains('/') {
+        Some(PathBuf::from(sound))
+    } else {
+        lookup(&config.sound_theme, sound)
+    }
+}
+
+/// Look `name` up in the freedesktop sound theme `theme` under the XDG data
+/// directories
+pub fn lookup(theme: &str, name: &str) -> Option<PathBuf> {
+    lookup_in(&sound_dirs(), theme, name)
+}
+
+/// Look `name` up in `theme` (then the themes it inherits, then
+/// "freedesktop") under each of `dirs`. A name that isn't found falls back
+/// to its less specific forms: "message-new-email", then "message-new",
+/// then "message".
+pub fn lookup_in(dirs: &[PathBuf], theme: &str, name: &str) -> Option<PathBuf> {
+    let themes = theme_chain(dirs, theme);
+    let mut name = name;
+    loop {
+        for theme in &themes {
+            for dir in dirs {
+                for subdir in ["stereo", ""] {
+                    for ext in EXTENSIONS {
+                        let file = dir.join(theme).join(subdir).join(format!("{}.{}", name, ext));
+                        if file.is_file() {
+                            return Some(file);
+                        }
+                    }
+                }
+            }
+        }
+        name = name.rsplit_once('-')?.0;
+    }
+}
+
+/// `theme` followed by everything it inherits, ending with "freedesktop"
+fn theme_chain(dirs: &[PathBuf], theme: &str) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut pending = vec![theme.to_string()];
+    while let Some(theme) = pending.pop() {
+        if theme.is_empty() || chain.contains(&theme) {
+            continue;
+        }
+        let inherits = dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(&theme).join("index.theme")).ok())
+            .map(|index| inherited(&index))
+            .unwrap_or_default();
+        chain.push(theme);
+        pending.extend(inherits.into_iter().rev());
+    }
+    if !chain.iter().any(|t| t == "freedesktop") {
+        chain.push("freedesktop".to_string());
+    }
+    chain
+}
+
+/// The `Inherits=` list of an index.theme
+fn inherited(index: &str) -> Vec<String> {
+    index
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Inherits="))
+        .map(|list| {
+            list.split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `$XDG_DATA_HOME/sounds` followed by `sounds` under each `$XDG_DATA_DIRS`
+fn sound_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dirs::data_dir().into_iter().collect();
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    dirs.into_iter().map(|d| d.join("sounds")).collect()
+}
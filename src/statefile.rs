@@ -0,0 +1,133 @@
+use crate::notification::Notification;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the writer waits for newer snapshots before writing
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// On-disk snapshot of the notification center, restored after a restart
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub next_id: u32,
+    pub dnd: bool,
+    /// Center notifications, newest first
+    pub notifications: Vec<Notification>,
+}
+
+impl Snapshot {
+    /// Read the snapshot at `path`; `None` if there is none or it is unreadable.
+    pub fn load(path: &Path) -> Option<Self> {
+        let bytes = fs::read(path).ok()?;
+        match serde_json::from_slice(&bytes) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                log::warn!("Ignoring unreadable state file {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+}
+
+enum Job {
+    Save(PathBuf, Snapshot),
+    /// Write whatever is pending now, then answer
+    Sync(Sender<()>),
+}
+
+/// Writes snapshots to `state_path` on a background thread, so the store
+/// lock isn't held while they are serialised and written. Snapshots sent
+/// within `DEBOUNCE` of each other are coalesced into one write of the
+/// newest. The thread starts with the first save.
+#[derive(Default)]
+pub struct StateFile {
+    tx: OnceLock<Sender<Job>>,
+}
+
+impl StateFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `snapshot` to be written to `path`.
+    pub fn save(&self, path: &Path, snapshot: Snapshot) {
+        self.send(Job::Save(path.to_path_buf(), snapshot));
+    }
+
+    /// Queue `snapshot` and wait until it is on disk, e.g. before exiting.
+    pub fn save_now(&self, path: &Path, snapshot: Snapshot) {
+        self.save(path, snapshot);
+        let (done_tx, done_rx) = mpsc::channel();
+        self.send(Job::Sync(done_tx));
+        let _ = done_rx.recv();
+    }
+
+    fn send(&self, job: Job) {
+        let tx = self.tx.get_or_init(|| {
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || run(rx));
+            tx
+        });
+        let _ = tx.send(job);
+    }
+}
+
+fn run(rx: Receiver<Job>) {
+    let mut pending: Option<(PathBuf, Snapshot)> = None;
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let job = match deadline {
+            Some(at) => match rx.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                Ok(job) => job,
+                Err(RecvTimeoutError::Timeout) => {
+                    write_pending(&mut pending);
+                    deadline = None;
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match rx.recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            },
+        };
+        match job {
+            Job::Save(path, snapshot) => {
+                pending = Some((path, snapshot));
+                deadline.get_or_insert_with(|| Instant::now() + DEBOUNCE);
+            }
+            Job::Sync(done) => {
+                write_pending(&mut pending);
+                deadline = None;
+                let _ = done.send(());
+            }
+        }
+    }
+    // The store is gone; don't lose its last snapshot
+    write_pending(&mut pending);
+}
+
+fn write_pending(pending: &mut Option<(PathBuf, Snapshot)>) {
+    if let Some((path, snapshot)) = pending.take() {
+        write(&path, &snapshot);
+    }
+}
+
+/// Write atomically, via a temp file
+fn write(path: &Path, snapshot: &Snapshot) {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let tmp = path.with_extension("json.tmp");
+    let result = serde_json::to_vec(snapshot)
+        .map_err(std::io::Error::other)
+        .and_then(|json| fs::write(&tmp, json))
+        .and_then(|_| fs::rename(&tmp, path));
+    if let Err(e) = result {
+        log::warn!("Failed to save state to {:?}: {}", path, e);
+    }
+}
@@ -1,11 +1,18 @@
 use crate::config::Config;
-use crate::notification::{CloseReason, LogEntry, Notification};
-use chrono::Utc;
+use crate::dbus_server::{self, DbusSignal};
+use crate::dnd;
+use crate::logfile::LogFile;
+use crate::notification::{CloseReason, INLINE_REPLY, LogEntry, Notification, Urgency};
+use crate::rules::RuleSet;
+use crate::sound::{SoundPlayer, Sounds};
+use crate::statefile::{Snapshot, StateFile};
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fs::{self, OpenOptions};
-use std::io::Write;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Shared application state
 pub struct Store {
@@ -18,27 +25,105 @@ pub struct Store {
     pub groups: HashMap<String, Vec<u32>>,
     /// Next ID to assign
     pub next_id: u32,
-    /// Do Not Disturb state
+    /// IDs currently occupying popup slots (at most `max_visible`)
+    pub popup_slots: Vec<u32>,
+    /// IDs waiting for a popup slot, critical first, then by arrival
+    pub popup_queue: Vec<u32>,
+    /// Do Not Disturb, as toggled by the user
     pub dnd: bool,
+    /// DND because a fullscreen window is focused
+    dnd_fullscreen: bool,
+    /// DND because `dnd_schedule` covers the current time
+    dnd_scheduled: bool,
+    /// Effective DND as of the last `sync_dnd`, to spot it ending
+    dnd_was_active: bool,
+    /// Popups held back by DND since it started: app name -> count
+    dnd_suppressed: Vec<(String, u32)>,
+    /// Popups held back until resumed (org.xnotid.Control Pause/Resume)
+    pub paused: bool,
+    /// Whether the notification center window is shown (reported by the UI)
+    pub center_visible: bool,
+    /// Status last announced on D-Bus, to emit only real changes
+    published: RefCell<Option<Status>>,
+    /// Notification reporting the last config.yaml that failed to load
+    config_error_id: Option<u32>,
     /// IDs that were replaced in-place and need UI widget rebuild
     pub replaced_ids: Vec<u32>,
     /// Callback: notify the UI that something changed
     pub on_change: Option<Box<dyn Fn() + Send>>,
+    /// Sender for D-Bus signals (ActionInvoked, NotificationClosed)
+    pub signal_tx: Option<UnboundedSender<DbusSignal>>,
+    /// Open JSONL log, flushed on every `notify_change`
+    log: LogFile,
+    /// `config.rules`, compiled
+    rules: RuleSet,
+    /// Sound playback; silent until `set_sound_player`
+    sounds: Sounds,
+    /// Background writer for `state_path`
+    state: StateFile,
 }
 
 pub type SharedStore = Arc<Mutex<Store>>;
 
+/// What bar widgets show: mirrored by the org.xnotid.Control properties and
+/// sent as JSON by `StatusChanged` / `xnotidctl watch`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Status {
+    /// Notifications in the center
+    pub count: u32,
+    pub urgency: UrgencyCounts,
+    pub dnd: bool,
+    pub paused: bool,
+    pub center_visible: bool,
+    /// Newest notification in the center
+    pub latest: Option<LatestNotification>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UrgencyCounts {
+    pub low: u32,
+    pub normal: u32,
+    pub critical: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatestNotification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub urgency: &'static str,
+}
+
 impl Store {
     pub fn new(config: Config) -> Self {
+        let rules = RuleSet::compile(&config.rules);
+        if !rules.is_empty() {
+            log::info!("Loaded {} notification rules", rules.len());
+        }
         Self {
             config,
             notifications: HashMap::new(),
             order: Vec::new(),
             groups: HashMap::new(),
             next_id: 1,
+            popup_slots: Vec::new(),
+            popup_queue: Vec::new(),
             dnd: false,
+            dnd_fullscreen: false,
+            dnd_scheduled: false,
+            dnd_was_active: false,
+            dnd_suppressed: Vec::new(),
+            config_error_id: None,
+            paused: false,
+            center_visible: false,
+            published: RefCell::new(None),
             replaced_ids: Vec::new(),
             on_change: None,
+            signal_tx: None,
+            log: LogFile::new(),
+            rules,
+            sounds: Sounds::default(),
+            state: StateFile::new(),
         }
     }
 
@@ -47,15 +132,31 @@ impl Store {
     }
 
     /// Add a notification, returning its assigned ID.
-    /// If replaces_id > 0 and exists, replaces it.
+    /// Config rules are applied first. If replaces_id > 0 and exists, replaces
+    /// it. The replaced content is reported closed with NotificationClosed
+    /// (reason undefined) before the new one takes over the same ID.
     pub fn add(&mut self, mut noti: Notification, replaces_id: u32) -> u32 {
+        let outcome = self.rules.apply(&mut noti);
+
+        // Sound goes with a new popup; replacements update in place silently
+        let mut sound = false;
         let id = if replaces_id > 0 && self.notifications.contains_key(&replaces_id) {
             // Replace existing
+            self.emit(DbusSignal::NotificationClosed {
+                id: replaces_id,
+                reason: CloseReason::Undefined,
+            });
             noti.id = replaces_id;
             self.notifications.insert(replaces_id, noti.clone());
             if !self.replaced_ids.contains(&replaces_id) {
                 self.replaced_ids.push(replaces_id);
             }
+            // A no_popup rule matching the new content takes the popup down
+            if !outcome.popup {
+                self.popup_slots.retain(|&x| x != replaces_id);
+                self.popup_queue.retain(|&x| x != replaces_id);
+                self.promote_queued();
+            }
             replaces_id
         } else {
             let id = self.next_id;
@@ -72,19 +173,44 @@ impl Store {
 
             self.order.insert(0, id); // newest first
             self.notifications.insert(id, noti.clone());
+
+            // DND or a no_popup rule suppresses the popup; the notification
+            // still lands in the center
+            if outcome.popup {
+                if self.dnd_lets_through(&noti) {
+                    self.enqueue_popup(id, noti.urgency, noti.group.as_deref());
+                    sound = true;
+                } else {
+                    self.record_suppressed(&noti.app_name);
+                }
+            }
             id
         };
 
+        if sound {
+            self.sounds.play_for(&self.config, &noti);
+        }
+
         // Log
         self.log_event(&noti, "received", None);
 
+        // Transient with no popup (no_popup rule, held back by DND): there is
+        // nowhere to show it, so it expires unseen. Queued ones stay queued.
+        if noti.transient && !self.popup_slots.contains(&id) && !self.popup_queue.contains(&id) {
+            self.close(id, CloseReason::Expired);
+        }
+
         id
     }
 
     /// Close/remove a notification by ID with a reason.
+    /// Emits NotificationClosed(id, reason) when the ID was known.
     pub fn close(&mut self, id: u32, reason: CloseReason) -> Option<Notification> {
         if let Some(noti) = self.notifications.remove(&id) {
             self.order.retain(|&x| x != id);
+            self.popup_slots.retain(|&x| x != id);
+            self.popup_queue.retain(|&x| x != id);
+            self.promote_queued();
 
             // Remove from group
             if let Some(ref group_key) = noti.group {
@@ -103,6 +229,7 @@ impl Store {
                 CloseReason::Undefined => "undefined",
             };
             self.log_event(&noti, event, None);
+            self.emit(DbusSignal::NotificationClosed { id, reason });
 
             Some(noti)
         } else {
@@ -110,27 +237,376 @@ impl Store {
         }
     }
 
+    /// Invoke an action: emits ActionInvoked, logs it, then closes the
+    /// notification as dismissed (which emits NotificationClosed) unless it
+    /// is resident.
+    pub fn invoke_action(&mut self, id: u32, action_key: &str) {
+        let Some(resident) = self.notifications.get(&id).map(|n| n.resident) else {
+            return;
+        };
+        self.emit(DbusSignal::ActionInvoked {
+            id,
+            action_key: action_key.to_string(),
+        });
+        self.log_action(id, action_key);
+        if !resident {
+            self.close(id, CloseReason::Dismissed);
+        }
+    }
+
+    /// Answer cards whose `deadline_seconds` have passed with their default
+    /// (marked `"timed_out": true`), then close them as expired. Returns
+    /// whether any were.
+    pub fn expire_cards(&mut self) -> bool {
+        self.expire_cards_at(Utc::now())
+    }
+
+    pub fn expire_cards_at(&mut self, now: DateTime<Utc>) -> bool {
+        let mut due: Vec<(u32, String)> = self
+            .notifications
+            .values()
+            .filter_map(|noti| {
+                let deadline = noti.card_deadline.as_ref()?;
+                let seconds = TimeDelta::try_seconds(deadline.seconds.try_into().ok()?)?;
+                let at = noti.created_at.checked_add_signed(seconds)?;
+                (now >= at).then(|| (noti.id, deadline.timed_out_answer().to_string()))
+            })
+            .collect();
+        due.sort();
+        for (id, answer) in &due {
+            log::info!("Card {} timed out", id);
+            self.emit(DbusSignal::ActionInvoked {
+                id: *id,
+                action_key: answer.clone(),
+            });
+            self.log_action(*id, answer);
+            self.close(*id, CloseReason::Expired);
+        }
+        !due.is_empty()
+    }
+
+    /// Send a typed reply: emits NotificationReplied(id, text), then invokes
+    /// the `inline-reply` action like a button click. Does nothing unless the
+    /// notification offers that action.
+    pub fn reply(&mut self, id: u32, text: &str) -> bool {
+        let offers_reply = self
+            .notifications
+            .get(&id)
+            .is_some_and(|n| n.reply_action().is_some());
+        if !offers_reply {
+            return false;
+        }
+        self.emit(DbusSignal::NotificationReplied {
+            id,
+            text: text.to_string(),
+        });
+        self.invoke_action(id, INLINE_REPLY);
+        true
+    }
+
     /// Record an action invocation
-    pub fn log_action(&self, id: u32, action_key: &str) {
-        if let Some(noti) = self.notifications.get(&id) {
-            self.log_event(noti, "action", Some(action_key.to_string()));
+    pub fn log_action(&mut self, id: u32, action_key: &str) {
+        let entry = self
+            .notifications
+            .get(&id)
+            .and_then(|noti| self.log_entry(noti, "action", Some(action_key.to_string())));
+        if let Some(entry) = entry {
+            self.write_log(&entry);
+        }
+    }
+
+    /// Play notification sounds through `player` (e.g. `CommandPlayer`)
+    pub fn set_sound_player(&mut self, player: Box<dyn SoundPlayer>) {
+        self.sounds.set_player(player);
+    }
+
+    fn emit(&self, signal: DbusSignal) {
+        if let Some(ref tx) = self.signal_tx {
+            let _ = tx.send(signal);
+        }
+    }
+
+    /// Swap in a reloaded config: recompiles rules and re-evaluates DND and
+    /// popup slots, and takes down a pending config error. The UI picks up
+    /// geometry and CSS through `Frontend::apply_config`.
+    pub fn set_config(&mut self, config: Config) {
+        self.rules = RuleSet::compile(&config.rules);
+        self.config = config;
+        if !self.config.dnd_fullscreen {
+            self.dnd_fullscreen = false;
         }
+        if let Some(id) = self.config_error_id.take() {
+            self.close(id, CloseReason::Closed);
+        }
+        self.refresh_dnd();
+        self.promote_queued();
+        log::info!("Config reloaded ({} rules)", self.rules.len());
+    }
+
+    /// Tell the user config.yaml could not be loaded (the running config is
+    /// kept). A newer error replaces the previous one.
+    pub fn report_config_error(&mut self, error: &str) {
+        log::warn!("Config not applied: {}", error);
+        let noti = Notification::new(
+            0,
+            "xnotid".to_string(),
+            "dialog-warning".to_string(),
+            "config.yaml was not applied".to_string(),
+            error.to_string(),
+            Vec::new(),
+            HashMap::new(),
+            0,
+        );
+        let replaces = self
+            .config_error_id
+            .filter(|id| self.notifications.contains_key(id))
+            .unwrap_or(0);
+        self.config_error_id = Some(self.add(noti, replaces));
+    }
+
+    /// Hold popups back (they queue up) or release them again
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused == self.paused {
+            return;
+        }
+        self.paused = paused;
+        if paused {
+            let mut held = std::mem::take(&mut self.popup_slots);
+            held.append(&mut self.popup_queue);
+            self.popup_queue = held;
+        } else {
+            self.promote_queued();
+        }
+        log::info!("Popups {}", if paused { "paused" } else { "resumed" });
+    }
+
+    pub fn set_center_visible(&mut self, visible: bool) {
+        self.center_visible = visible;
+        self.publish_status();
+    }
+
+    /// Current status snapshot
+    pub fn status(&self) -> Status {
+        let center = self.all_notifications();
+        let mut urgency = UrgencyCounts::default();
+        for noti in &center {
+            match noti.urgency {
+                Urgency::Low => urgency.low += 1,
+                Urgency::Normal => urgency.normal += 1,
+                Urgency::Critical => urgency.critical += 1,
+            }
+        }
+        Status {
+            count: center.len() as u32,
+            urgency,
+            dnd: self.dnd_active(),
+            paused: self.paused,
+            center_visible: self.center_visible,
+            latest: center.first().map(|n| LatestNotification {
+                id: n.id,
+                app_name: n.app_name.clone(),
+                summary: n.summary.clone(),
+                urgency: dbus_server::urgency_name(n.urgency),
+            }),
+        }
+    }
+
+    /// Emit StatusChanged and DndChanged/CountChanged/PausedChanged/
+    /// CenterVisibleChanged for whatever differs from the last announcement.
+    fn publish_status(&self) {
+        let status = self.status();
+        let last = self.published.replace(Some(status.clone()));
+        if last.as_ref() == Some(&status) {
+            return;
+        }
+        match serde_json::to_string(&status) {
+            Ok(json) => self.emit(DbusSignal::StatusChanged(json)),
+            Err(e) => log::warn!("Failed to serialize status: {}", e),
+        }
+        let last = last.as_ref();
+        if last.is_none_or(|l| l.dnd != status.dnd) {
+            self.emit(DbusSignal::DndChanged(status.dnd));
+        }
+        if last.is_none_or(|l| l.count != status.count) {
+            self.emit(DbusSignal::CountChanged(status.count));
+        }
+        if last.is_none_or(|l| l.paused != status.paused) {
+            self.emit(DbusSignal::PausedChanged(status.paused));
+        }
+        if last.is_none_or(|l| l.center_visible != status.center_visible) {
+            self.emit(DbusSignal::CenterVisibleChanged(status.center_visible));
+        }
+    }
+
+    /// Whether Do Not Disturb is in effect: toggled on, scheduled, or a
+    /// fullscreen window is focused. Always false when `dnd_enabled` is off.
+    pub fn dnd_active(&self) -> bool {
+        self.config.dnd_enabled && (self.dnd || self.dnd_scheduled || self.dnd_fullscreen)
+    }
+
+    /// DND comes from the schedule or a fullscreen window, not the toggle
+    pub fn dnd_automatic(&self) -> bool {
+        self.dnd_active() && !self.dnd
+    }
+
+    /// Toggle DND by hand
+    pub fn set_dnd(&mut self, on: bool) {
+        self.dnd = on;
+        self.sync_dnd();
+    }
+
+    /// Report whether a fullscreen window is focused (`dnd_fullscreen`)
+    pub fn set_fullscreen_dnd(&mut self, fullscreen: bool) {
+        self.dnd_fullscreen = self.config.dnd_fullscreen && fullscreen;
+        self.sync_dnd();
+    }
+
+    /// Re-evaluate `dnd_schedule` against the local clock. Returns whether the
+    /// effective DND state changed.
+    pub fn refresh_dnd(&mut self) -> bool {
+        self.refresh_dnd_at(Local::now().naive_local())
     }
 
-    /// Get visible notifications (respecting DND)
+    pub fn refresh_dnd_at(&mut self, now: NaiveDateTime) -> bool {
+        self.dnd_scheduled = dnd::scheduled(&self.config.dnd_schedule, now);
+        self.sync_dnd()
+    }
+
+    /// Track DND transitions; when it ends, pop up a summary of what it held
+    /// back. Returns whether the effective state changed.
+    fn sync_dnd(&mut self) -> bool {
+        let active = self.dnd_active();
+        if active == self.dnd_was_active {
+            return false;
+        }
+        self.dnd_was_active = active;
+        log::info!("Do Not Disturb {}", if active { "on" } else { "off" });
+
+        let suppressed = std::mem::take(&mut self.dnd_suppressed);
+        if !active && !suppressed.is_empty() {
+            let (summary, body) = dnd::suppressed_summary(&suppressed);
+            let mut noti = Notification::new(
+                0,
+                "xnotid".to_string(),
+                "notifications-disabled-symbolic".to_string(),
+                summary,
+                body,
+                Vec::new(),
+                HashMap::new(),
+                -1,
+            );
+            noti.transient = true;
+            self.add(noti, 0);
+        }
+        true
+    }
+
+    /// Allowlisted apps always pop up during DND; critical ones do unless
+    /// blocklisted.
+    fn dnd_lets_through(&self, noti: &Notification) -> bool {
+        if !self.dnd_active() {
+            return true;
+        }
+        let listed = |apps: &[String]| apps.iter().any(|app| app.eq_ignore_ascii_case(&noti.app_name));
+        if listed(&self.config.dnd_allow_apps) {
+            return true;
+        }
+        noti.urgency == Urgency::Critical && !listed(&self.config.dnd_block_apps)
+    }
+
+    fn record_suppressed(&mut self, app_name: &str) {
+        match self.dnd_suppressed.iter_mut().find(|(app, _)| app == app_name) {
+            Some((_, count)) => *count += 1,
+            None => self.dnd_suppressed.push((app_name.to_string(), 1)),
+        }
+    }
+
+    /// Give `id` a popup slot if one is free, otherwise queue it behind
+    /// everything of equal or higher urgency. A same-group arrival takes over
+    /// the slot (or queue entry) of the earlier member instead; a displaced
+    /// transient is then shown nowhere, so it expires.
+    fn enqueue_popup(&mut self, id: u32, urgency: Urgency, group: Option<&str>) {
+        for other in self.place_popup(id, urgency, group) {
+            if self.notifications.get(&other).is_some_and(|n| n.transient) {
+                self.close(other, CloseReason::Expired);
+            }
+        }
+    }
+
+    /// `enqueue_popup` without the cleanup; returns the same-group members
+    /// `id` displaced
+    fn place_popup(&mut self, id: u32, urgency: Urgency, group: Option<&str>) -> Vec<u32> {
+        if let Some(group) = group {
+            let same_group = |store: &Self, other: &u32| {
+                store
+                    .notifications
+                    .get(other)
+                    .is_some_and(|n| n.group.as_deref() == Some(group))
+            };
+            if let Some(pos) = self.popup_slots.iter().position(|other| same_group(self, other)) {
+                return vec![std::mem::replace(&mut self.popup_slots[pos], id)];
+            }
+            let queued: Vec<u32> = self
+                .popup_queue
+                .iter()
+                .copied()
+                .filter(|other| same_group(self, other))
+                .collect();
+            self.popup_queue.retain(|other| !queued.contains(other));
+            self.push_popup(id, urgency);
+            return queued;
+        }
+        self.push_popup(id, urgency);
+        Vec::new()
+    }
+
+    /// Take a free slot, or queue behind everything of equal or higher urgency
+    fn push_popup(&mut self, id: u32, urgency: Urgency) {
+        // `validate` keeps max_visible at 1 or more
+        let max = self.config.max_visible as usize;
+        if !self.paused && self.popup_slots.len() < max {
+            self.popup_slots.push(id);
+            return;
+        }
+
+        let pos = self
+            .popup_queue
+            .iter()
+            .position(|qid| {
+                self.notifications
+                    .get(qid)
+                    .is_some_and(|n| (n.urgency as u8) < (urgency as u8))
+            })
+            .unwrap_or(self.popup_queue.len());
+        self.popup_queue.insert(pos, id);
+    }
+
+    /// Move queued popups into free slots
+    fn promote_queued(&mut self) {
+        if self.paused {
+            return;
+        }
+        let max = self.config.max_visible as usize;
+        while !self.popup_queue.is_empty() && self.popup_slots.len() < max {
+            let id = self.popup_queue.remove(0);
+            self.popup_slots.push(id);
+        }
+    }
+
+    /// Get notifications that currently hold a popup slot (newest first)
     pub fn visible_popups(&self) -> Vec<&Notification> {
         self.order
             .iter()
+            .filter(|id| self.popup_slots.contains(id))
             .filter_map(|id| self.notifications.get(id))
-            .filter(|n| {
-                if self.dnd && n.urgency != crate::notification::Urgency::Critical {
-                    return false;
-                }
-                true
-            })
             .collect()
     }
 
+    /// Number of notifications waiting for a popup slot
+    pub fn queued_count(&self) -> usize {
+        self.popup_queue.len()
+    }
+
     /// Get all notifications for the notification center
     pub fn all_notifications(&self) -> Vec<&Notification> {
         self.order
@@ -140,6 +616,19 @@ impl Store {
             .collect()
     }
 
+    /// Number of live notifications in a group
+    pub fn group_size(&self, group_key: &str) -> usize {
+        self.groups.get(group_key).map_or(0, |ids| ids.len())
+    }
+
+    /// Dismiss every notification in a group
+    pub fn dismiss_group(&mut self, group_key: &str) {
+        let ids = self.groups.get(group_key).cloned().unwrap_or_default();
+        for id in ids {
+            self.close(id, CloseReason::Dismissed);
+        }
+    }
+
     /// Clear all notifications
     pub fn clear_all(&mut self) {
         let ids: Vec<u32> = self.order.clone();
@@ -148,12 +637,19 @@ impl Store {
         }
     }
 
-    fn log_event(&self, noti: &Notification, event: &str, action_key: Option<String>) {
-        if !self.config.log_enabled {
-            return;
+    fn log_event(&mut self, noti: &Notification, event: &str, action_key: Option<String>) {
+        if let Some(entry) = self.log_entry(noti, event, action_key) {
+            self.write_log(&entry);
+        }
+    }
+
+    /// The log record for `event`, or None if `noti` isn't logged
+    fn log_entry(&self, noti: &Notification, event: &str, action_key: Option<String>) -> Option<LogEntry> {
+        if !self.config.log_enabled || noti.skip_log {
+            return None;
         }
 
-        let entry = LogEntry {
+        Some(LogEntry {
             uuid: noti.uuid.clone(),
             timestamp: Utc::now().to_rfc3339(),
             event: event.to_string(),
@@ -166,7 +662,7 @@ impl Store {
             },
             summary: Some(noti.summary.clone()),
             body: if event == "received" {
-                Some(noti.body.clone())
+                Some(self.loggable_body(noti))
             } else {
                 None
             },
@@ -192,29 +688,115 @@ impl Store {
             },
             action_key,
             group: noti.group.clone(),
-        };
+        })
+    }
 
-        if let Ok(json) = serde_json::to_string(&entry) {
-            let log_path = &self.config.log_path;
-            if let Some(parent) = Path::new(log_path).parent() {
-                let _ = fs::create_dir_all(parent);
-            }
-            if let Ok(mut f) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(log_path)
-            {
-                let _ = writeln!(f, "{}", json);
+    fn write_log(&mut self, entry: &LogEntry) {
+        if let Ok(json) = serde_json::to_string(entry) {
+            if let Err(e) = self.log.append(&self.config, &json) {
+                log::warn!("Failed to write log {:?}: {}", self.config.log_path, e);
             }
         }
     }
 
-    pub fn notify_change(&self) {
+    /// Body text as written to the log, redacted for `log_redact_apps`
+    fn loggable_body(&self, noti: &Notification) -> String {
+        let redact = self
+            .config
+            .log_redact_apps
+            .iter()
+            .any(|app| app.eq_ignore_ascii_case(&noti.app_name));
+        if redact && !noti.body.is_empty() {
+            "[redacted]".to_string()
+        } else {
+            noti.body.clone()
+        }
+    }
+
+    /// Drop log records older than `log_max_age_days` (run once at startup)
+    pub fn purge_log(&mut self) {
+        if let Err(e) = self.log.purge_expired(&self.config) {
+            log::warn!("Failed to purge log {:?}: {}", self.config.log_path, e);
+        }
+    }
+
+    pub fn notify_change(&mut self) {
+        if let Err(e) = self.log.flush() {
+            log::warn!("Failed to flush log {:?}: {}", self.config.log_path, e);
+        }
+        self.save_state();
+        self.publish_status();
         if let Some(ref cb) = self.on_change {
             cb();
         }
     }
 
+    /// Write out the log and saved state and wait for both, e.g. before exiting
+    pub fn flush(&mut self) {
+        if let Err(e) = self.log.flush() {
+            log::warn!("Failed to flush log {:?}: {}", self.config.log_path, e);
+        }
+        if let Some(snapshot) = self.snapshot() {
+            self.state.save_now(Path::new(&self.config.state_path), snapshot);
+        }
+    }
+
+    /// Queue non-transient notifications, display order, DND and next_id to
+    /// be written to `state_path`. The write happens off the store lock and
+    /// is debounced, so a burst of changes costs one write.
+    pub fn save_state(&self) {
+        if let Some(snapshot) = self.snapshot() {
+            self.state.save(Path::new(&self.config.state_path), snapshot);
+        }
+    }
+
+    fn snapshot(&self) -> Option<Snapshot> {
+        if !self.config.persist_history {
+            return None;
+        }
+        Some(Snapshot {
+            next_id: self.next_id,
+            dnd: self.dnd,
+            notifications: self.all_notifications().into_iter().cloned().collect(),
+        })
+    }
+
+    /// Restore the center from the snapshot written by `save_state`.
+    /// Restored notifications go to the center only, not back to popups.
+    pub fn restore_state(&mut self) {
+        if !self.config.persist_history {
+            return;
+        }
+
+        let path = Path::new(&self.config.state_path);
+        let Some(snapshot) = Snapshot::load(path) else {
+            return;
+        };
+
+        // Oldest first so group membership keeps arrival order
+        for mut noti in snapshot.notifications.into_iter().rev() {
+            // Whoever asked is gone with the old daemon; don't answer for them
+            noti.card_deadline = None;
+            let id = noti.id;
+            if let Some(ref group_key) = noti.group {
+                self.groups.entry(group_key.clone()).or_default().push(id);
+            }
+            self.order.insert(0, id);
+            self.next_id = self.next_id.max(id + 1);
+            self.notifications.insert(id, noti);
+        }
+        self.next_id = self.next_id.max(snapshot.next_id);
+        self.dnd = snapshot.dnd;
+        self.dnd_was_active = self.dnd_active();
+
+        log::info!(
+            "Restored {} notifications from {:?} (dnd={})",
+            self.order.len(),
+            path,
+            self.dnd
+        );
+    }
+
     pub fn take_replaced_ids(&mut self) -> Vec<u32> {
         std::mem::take(&mut self.replaced_ids)
     }
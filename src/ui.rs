@@ -1,82 +1,90 @@
-use crate::config::Config;
-use crate::dbus_server::DbusSignal;
-use crate::notification::{CloseReason, ImageData, Notification, NotificationCard, Urgency};
+use crate::card_widgets;
+use crate::config::{Config, PositionY};
+use crate::frontend::Frontend;
+use crate::history_panel::HistoryPanel;
+use crate::notification::{
+    Action, CARD_HINT, ChoiceMode, CloseReason, INLINE_REPLY, ImageData, Notification,
+    NotificationCard, Urgency,
+};
+use crate::placement::{Area, X11Placer};
 use crate::store::SharedStore;
 use gdk4::gdk_pixbuf::Pixbuf;
 use gtk4::prelude::*;
 use gtk4::{
     Align, Box as GtkBox, Button, CssProvider, Entry, EventControllerKey, GestureClick, Image, Label,
-    Orientation, Revealer, RevealerTransitionType, ScrolledWindow, Separator, Window,
+    Orientation, Revealer, RevealerTransitionType, ScrolledWindow, Separator, Stack, StackSwitcher,
+    Window,
 };
+use gio2::{FileMonitor, FileMonitorEvent, FileMonitorFlags};
 use serde_json::json;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::rc::Rc;
 
 /// Manages the popup notification window and the notification center panel.
 pub struct Ui {
     store: SharedStore,
-    config: Config,
-    /// Sender for D-Bus signals (ActionInvoked, etc.)
-    signal_tx: std::sync::mpsc::Sender<DbusSignal>,
+    /// Copy of the store's config, swapped by `apply_config`
+    config: Rc<RefCell<Config>>,
     /// The popup window (always present, visibility toggled)
     popup_window: Window,
+    popup_scroll: ScrolledWindow,
     /// Max popup height in pixels (80% of screen)
-    max_popup_h: i32,
+    max_popup_h: Cell<i32>,
     /// The popup container holding notification slots
     popup_box: GtkBox,
     /// Map of notification ID -> popup widget for removal
     popup_widgets: Rc<RefCell<HashMap<u32, GtkBox>>>,
+    /// "+N more" row shown below the popups while the overflow queue is non-empty
+    overflow_row: GtkBox,
+    overflow_label: Label,
+    /// Header DND toggle ("active" when on by hand, "auto" when scheduled/fullscreen)
+    dnd_button: Button,
     /// Timeout source IDs for auto-dismiss
     timeout_sources: Rc<RefCell<HashMap<u32, glib2::SourceId>>>,
     /// The notification center window
     center_window: Window,
+    center_scroll: ScrolledWindow,
     /// The notification center list container
     center_box: GtkBox,
     /// Map of notification ID -> center widget for removal
     center_widgets: Rc<RefCell<HashMap<u32, GtkBox>>>,
+    /// Map of group key -> (member IDs, stacked center card)
+    center_groups: Rc<RefCell<HashMap<String, (Vec<u32>, GtkBox)>>>,
+    /// Group keys the user expanded in the center
+    expanded_groups: Rc<RefCell<HashSet<String>>>,
+    /// Configured monitor geometry in X11 pixels
+    monitor_area: Rc<Cell<Area>>,
+    /// Moves our windows on X11 (None when X11 is unavailable)
+    placer: Option<Rc<X11Placer>>,
+    /// Default CSS + style.css, reloaded in place
+    css_provider: CssProvider,
+    /// Watches the config directory (see `watch_config`)
+    config_monitors: RefCell<Vec<FileMonitor>>,
+    /// The daemon's main loop, left on `quit`
+    main_loop: glib2::MainLoop,
 }
 
 impl Ui {
     const POPUP_ANIMATION_MS: u32 = 500;
+    const POPUP_TITLE: &str = "xnotid-popups";
+    const CENTER_TITLE: &str = "xnotid-center";
 
-    pub fn new(store: SharedStore, signal_tx: std::sync::mpsc::Sender<DbusSignal>) -> Self {
+    pub fn new(store: SharedStore) -> Self {
         let config = {
             let s = store.lock().unwrap();
             s.config.clone()
         };
 
         // Load CSS
-        Self::load_css(&config);
+        let css_provider = Self::load_css(&config);
 
-        // Detect screen height upfront (display available after gtk4::init)
-        let display = gdk4::Display::default().expect("No display");
-        let monitors = gdk4::prelude::DisplayExt::monitors(&display);
-        let monitor_idx = config.monitor.min(monitors.n_items() as i32 - 1).max(0);
-        let screen_h = if let Some(obj) = monitors.item(monitor_idx as u32) {
-            if let Ok(mon) = obj.downcast::<gdk4::Monitor>() {
-                gdk4::prelude::MonitorExt::geometry(&mon).height()
-            } else { 1080 }
-        } else { 1080 };
-
-        // Compute max heights: use config if > 0, else fall back to screen percentage
-        let screen_popup_max = (screen_h as f64 * 0.8) as i32;
-        let screen_center_max = (screen_h as f64 * 0.85) as i32;
-        let max_popup_h = if config.max_popup_height > 0 {
-            config.max_popup_height.min(screen_popup_max)
-        } else {
-            screen_popup_max
-        };
-        let max_center_h = if config.max_center_height > 0 {
-            config.max_center_height.min(screen_center_max)
-        } else {
-            screen_center_max
-        };
-        log::info!("Screen {}px → popup max {}px, center max {}px", screen_h, max_popup_h, max_center_h);
+        let (monitor_area, max_popup_h, max_center_h) = Self::geometry(&config);
 
         // Create popup window
         let popup_window = Window::builder()
-            .title("xnotid-popups")
+            .title(Self::POPUP_TITLE)
             .decorated(false)
             .default_width(config.popup_width)
             .css_name("popup-window")
@@ -109,7 +117,7 @@ impl Ui {
 
         // Create notification center window
         let center_window = Window::builder()
-            .title("xnotid-center")
+            .title(Self::CENTER_TITLE)
             .decorated(false)
             .resizable(false)
             .default_width(config.popup_width)
@@ -122,7 +130,7 @@ impl Ui {
         let center_main_box = GtkBox::new(Orientation::Vertical, 0);
         center_main_box.set_widget_name("center-main");
 
-        // Header with title + DND + Clear All
+        // Header with Notifications/History tabs + DND + Clear All
         let header_box = GtkBox::new(Orientation::Horizontal, 8);
         header_box.set_widget_name("center-header");
         header_box.set_margin_start(12);
@@ -130,25 +138,25 @@ impl Ui {
         header_box.set_margin_top(8);
         header_box.set_margin_bottom(8);
 
-        let title_label = Label::new(Some("Notifications"));
-        title_label.set_widget_name("center-title");
-        title_label.set_hexpand(true);
-        title_label.set_halign(Align::Start);
-        header_box.append(&title_label);
+        let center_stack = Stack::new();
+        let tabs = StackSwitcher::new();
+        tabs.set_widget_name("center-tabs");
+        tabs.set_stack(Some(&center_stack));
+        tabs.set_hexpand(true);
+        tabs.set_halign(Align::Start);
+        header_box.append(&tabs);
 
-        // DND toggle button
+        // DND toggle button; refresh() keeps its classes in sync with the store
         let dnd_btn = Button::with_label("DND");
         dnd_btn.set_widget_name("dnd-button");
+        dnd_btn.set_visible(config.dnd_enabled);
+        Self::update_dnd_button(&dnd_btn, &store);
         let store_dnd = store.clone();
-        dnd_btn.connect_clicked(move |btn| {
+        dnd_btn.connect_clicked(move |_| {
             let mut s = store_dnd.lock().unwrap();
-            s.dnd = !s.dnd;
-            if s.dnd {
-                btn.add_css_class("active");
-            } else {
-                btn.remove_css_class("active");
-            }
-            log::info!("DND toggled: {}", s.dnd);
+            let on = !s.dnd;
+            s.set_dnd(on);
+            log::info!("DND toggled: {}", on);
             s.notify_change();
         });
         header_box.append(&dnd_btn);
@@ -196,12 +204,39 @@ impl Ui {
         empty_label.set_css_classes(&["dim-label"]);
         center_box.append(&empty_label);
 
-        center_main_box.append(&scrolled);
+        center_stack.add_titled(&scrolled, Some("notifications"), "Notifications");
+
+        // History tab re-reads the log each time it is opened
+        let history = HistoryPanel::new(store.clone(), max_center_h);
+        center_stack.add_titled(history.widget(), Some("history"), "History");
+        center_stack.connect_visible_child_name_notify(move |stack| {
+            if stack.visible_child_name().is_some_and(|name| name.as_str() == "history") {
+                history.reload();
+            }
+        });
+
+        center_main_box.append(&center_stack);
         center_window.set_child(Some(&center_main_box));
         center_window.set_visible(false);
 
         let popup_widgets = Rc::new(RefCell::new(HashMap::new()));
 
+        // Overflow indicator: clicking it opens the notification center
+        let overflow_row = GtkBox::new(Orientation::Horizontal, 0);
+        overflow_row.set_widget_name("popup-overflow");
+        overflow_row.set_margin_start(8);
+        overflow_row.set_margin_end(8);
+        let overflow_label = Label::new(None);
+        overflow_label.set_hexpand(true);
+        overflow_row.append(&overflow_label);
+        let overflow_click = GestureClick::new();
+        let center_for_overflow = center_window.clone();
+        let popup_for_overflow = popup_window.clone();
+        overflow_click.connect_released(move |_, _, _, _| {
+            Self::open_center(&popup_for_overflow, &center_for_overflow);
+        });
+        overflow_row.add_controller(overflow_click);
+
         // ESC on center window should behave like tray bell toggle
         let center_for_esc = center_window.clone();
         let popup_for_esc = popup_window.clone();
@@ -217,8 +252,7 @@ impl Ui {
                         popup_for_esc.present();
                     }
                 } else {
-                    popup_for_esc.set_visible(false);
-                    center_for_esc.present();
+                    Self::open_center(&popup_for_esc, &center_for_esc);
                 }
                 glib2::Propagation::Stop
             } else {
@@ -227,22 +261,94 @@ impl Ui {
         });
         center_window.add_controller(key_controller);
 
+        // Every path that shows or hides the center reports it to the store
+        // (org.xnotid.Control CenterVisible)
+        let store_visible = store.clone();
+        center_window.connect_visible_notify(move |w| {
+            store_visible.lock().unwrap().set_center_visible(w.is_visible());
+        });
+
         Self {
             store,
-            config,
-            signal_tx,
+            config: Rc::new(RefCell::new(config)),
             popup_window,
-            max_popup_h,
+            popup_scroll,
+            max_popup_h: Cell::new(max_popup_h),
             popup_box,
             popup_widgets,
+            overflow_row,
+            overflow_label,
+            dnd_button: dnd_btn,
             timeout_sources: Rc::new(RefCell::new(HashMap::new())),
             center_window,
+            center_scroll: scrolled,
             center_box,
             center_widgets: Rc::new(RefCell::new(HashMap::new())),
+            center_groups: Rc::new(RefCell::new(HashMap::new())),
+            expanded_groups: Rc::new(RefCell::new(HashSet::new())),
+            monitor_area: Rc::new(Cell::new(monitor_area)),
+            placer: X11Placer::connect().map(Rc::new),
+            css_provider,
+            config_monitors: RefCell::new(Vec::new()),
+            main_loop: glib2::MainLoop::new(None, false),
         }
     }
 
-    fn load_css(_config: &Config) {
+    /// Run the GLib main loop until `quit`
+    pub fn run(&self) {
+        self.main_loop.run();
+    }
+
+    /// Configured monitor area plus max popup and center heights: the
+    /// configured maximum if set, capped by a share of the screen height
+    fn geometry(config: &Config) -> (Area, i32, i32) {
+        // Display is available after gtk4::init
+        let display = gdk4::Display::default().expect("No display");
+        let monitors = gdk4::prelude::DisplayExt::monitors(&display);
+        let monitor_idx = config.monitor.min(monitors.n_items() as i32 - 1).max(0);
+        let screen_h = if let Some(obj) = monitors.item(monitor_idx as u32) {
+            if let Ok(mon) = obj.downcast::<gdk4::Monitor>() {
+                gdk4::prelude::MonitorExt::geometry(&mon).height()
+            } else { 1080 }
+        } else { 1080 };
+        let monitor_area = Self::monitor_area(&display, monitor_idx);
+
+        let screen_popup_max = (screen_h as f64 * 0.8) as i32;
+        let screen_center_max = (screen_h as f64 * 0.85) as i32;
+        let max_popup_h = if config.max_popup_height > 0 {
+            config.max_popup_height.min(screen_popup_max)
+        } else {
+            screen_popup_max
+        };
+        let max_center_h = if config.max_center_height > 0 {
+            config.max_center_height.min(screen_center_max)
+        } else {
+            screen_center_max
+        };
+        log::info!("Screen {}px → popup max {}px, center max {}px", screen_h, max_popup_h, max_center_h);
+        (monitor_area, max_popup_h, max_center_h)
+    }
+
+    /// Geometry of the given monitor, scaled to X11 device pixels
+    fn monitor_area(display: &gdk4::Display, monitor_idx: i32) -> Area {
+        let monitors = gdk4::prelude::DisplayExt::monitors(display);
+        monitors
+            .item(monitor_idx as u32)
+            .and_then(|obj| obj.downcast::<gdk4::Monitor>().ok())
+            .map(|mon| {
+                let geom = gdk4::prelude::MonitorExt::geometry(&mon);
+                let scale = gdk4::prelude::MonitorExt::scale_factor(&mon);
+                Area::new(
+                    geom.x() * scale,
+                    geom.y() * scale,
+                    geom.width() * scale,
+                    geom.height() * scale,
+                )
+            })
+            .unwrap_or(Area::new(0, 0, 1920, 1080))
+    }
+
+    fn load_css(_config: &Config) -> CssProvider {
         let css_path = Config::css_path();
 
         // If no CSS file on disk yet, write the built-in default
@@ -254,9 +360,26 @@ impl Ui {
             log::info!("Wrote default CSS to {:?}", css_path);
         }
 
+        let provider = CssProvider::new();
+        provider.connect_parsing_error(|_, section, error| {
+            log::warn!("CSS error at {}: {}", section.to_str(), error);
+        });
+        Self::fill_css(&provider);
+
+        gtk4::style_context_add_provider_for_display(
+            &gdk4::Display::default().expect("No display"),
+            &provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+        provider
+    }
+
+    /// (Re)load `provider` with the default CSS followed by style.css;
+    /// GTK restyles existing widgets when it changes.
+    fn fill_css(provider: &CssProvider) {
+        let css_path = Config::css_path();
         // Always start from built-in defaults so newly introduced selectors
         // exist even if user style.css is from an older version.
-        let provider = CssProvider::new();
         let mut css = include_str!("default.css").to_string();
         if let Ok(user_css) = std::fs::read_to_string(&css_path) {
             css.push_str("\n\n/* --- user overrides --- */\n");
@@ -266,81 +389,199 @@ impl Ui {
             log::info!("Loaded default CSS only");
         }
         provider.load_from_string(&css);
+    }
 
-        gtk4::style_context_add_provider_for_display(
-            &gdk4::Display::default().expect("No display"),
-            &provider,
-            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-        );
+    fn update_dnd_button(button: &Button, store: &SharedStore) {
+        let (manual, automatic) = {
+            let s = store.lock().unwrap();
+            (s.dnd, s.dnd_automatic())
+        };
+        for (class, on) in [("active", manual), ("auto", automatic)] {
+            if on {
+                button.add_css_class(class);
+            } else {
+                button.remove_css_class(class);
+            }
+        }
+        button.set_tooltip_text(automatic.then_some("Do Not Disturb is on automatically"));
     }
 
-    /// Position the popup window at the configured screen corner.
-    pub fn position_popup(&self) {
-        // GTK4 doesn't have direct window.move() — we rely on the WM
-        // For X11 override-redirect approach, we'd need to use X11 surface directly.
-        // For now, present the window and let it be positioned.
-        // Real positioning will be done after realize via the X11 surface.
-        self.popup_window.present();
+    /// Poll whether the focused X11 window is fullscreen and feed it to the
+    /// store's auto-DND (`dnd_fullscreen`). The setting is checked on every
+    /// tick, so a reloaded config can turn it on or off.
+    pub fn watch_fullscreen(&self) {
+        let Some(placer) = self.placer.clone() else {
+            if self.config.borrow().dnd_fullscreen {
+                log::warn!("dnd_fullscreen needs X11; fullscreen DND disabled");
+            }
+            return;
+        };
 
-        // After the window is realized, position it
+        let store = self.store.clone();
         let config = self.config.clone();
-        let popup = self.popup_window.clone();
-        popup.connect_realize(move |window| {
-            Self::position_window_x11(window, &config, true);
+        glib2::timeout_add_seconds_local(1, move || {
+            if !config.borrow().dnd_enabled || !config.borrow().dnd_fullscreen {
+                return glib2::ControlFlow::Continue;
+            }
+            let fullscreen = placer.active_window_fullscreen();
+            let mut s = store.lock().unwrap();
+            let before = s.dnd_active();
+            s.set_fullscreen_dnd(fullscreen);
+            if s.dnd_active() != before {
+                s.notify_change();
+            }
+            glib2::ControlFlow::Continue
         });
     }
 
-    fn position_window_x11(window: &Window, config: &Config, is_popup: bool) {
-        // On X11 with GTK4, positioning is best-effort.
-        let display = gtk4::prelude::WidgetExt::display(window);
-        let monitors = gdk4::prelude::DisplayExt::monitors(&display);
-        let monitor_idx = config.monitor.min(monitors.n_items() as i32 - 1).max(0);
-        if let Some(obj) = monitors.item(monitor_idx as u32) {
-            if let Ok(monitor) = obj.downcast::<gdk4::Monitor>() {
-                let geom = gdk4::prelude::MonitorExt::geometry(&monitor);
+    /// Anchor the popup and center windows at the configured screen corner.
+    pub fn position_windows(&self) {
+        self.popup_window.present();
 
-                let w = config.popup_width;
-                let h = if is_popup { 1 } else { 600 };
+        self.anchor_window(&self.popup_window, Self::POPUP_TITLE);
+        self.anchor_window(&self.center_window, Self::CENTER_TITLE);
+    }
 
-                let x = match config.position_x.as_str() {
-                    "left" => geom.x() + config.margin_right,
-                    "center" => geom.x() + (geom.width() - w) / 2,
-                    _ => geom.x() + geom.width() - w - config.margin_right,
-                };
+    /// Re-place `window` whenever it is mapped or its height changes, so a
+    /// bottom-anchored stack keeps its bottom edge fixed while it grows.
+    fn anchor_window(&self, window: &Window, title: &'static str) {
+        let Some(place) = self.placement(title) else {
+            log::info!("No X11 placement; relying on WM rules for {}", title);
+            return;
+        };
 
-                let y = match config.position_y.as_str() {
-                    "bottom" => geom.y() + geom.height() - h - config.margin_top,
-                    _ => geom.y() + config.margin_top,
-                };
+        let place_resize = place.clone();
+        window.connect_default_height_notify(move |w| place_resize(w));
+
+        window.connect_map(move |w| {
+            place(w);
+            // The WM may only manage the window after GTK maps it; place again once it has.
+            let place_later = place.clone();
+            let w = w.clone();
+            glib2::timeout_add_local_once(std::time::Duration::from_millis(100), move || {
+                place_later(&w);
+            });
+        });
+    }
+
+    /// Moves a window to the configured corner, following config reloads
+    fn placement(&self, title: &'static str) -> Option<Rc<dyn Fn(&Window)>> {
+        let placer = self.placer.clone()?;
+        let config = self.config.clone();
+        let area = self.monitor_area.clone();
+        Some(Rc::new(move |w: &Window| {
+            let config = config.borrow();
+            placer.place(title, &area.get(), &config, config.popup_width, w.default_height().max(1));
+        }))
+    }
 
-                log::info!("Positioning window at ({}, {})", x, y);
+    /// Reload config.yaml and style.css when they change on disk. A config
+    /// that fails to parse is reported as a notification and not applied.
+    pub fn watch_config(self: &Rc<Self>) {
+        // --config / --style may put the two files in different directories
+        let ours: Vec<PathBuf> = [Config::config_path(), Config::css_path()]
+            .into_iter()
+            .map(|p| std::path::absolute(&p).unwrap_or(p))
+            .collect();
+        let mut dirs: Vec<PathBuf> =
+            ours.iter().filter_map(|p| p.parent().map(PathBuf::from)).collect();
+        dirs.dedup();
+
+        let mut monitors = Vec::new();
+        for dir in dirs {
+            match gio2::File::for_path(&dir)
+                .monitor_directory(FileMonitorFlags::WATCH_MOVES, gio2::Cancellable::NONE)
+            {
+                Ok(monitor) => monitors.push(monitor),
+                Err(e) => log::warn!("Can't watch {:?} for changes: {}", dir, e),
             }
         }
+
+        // Editors save in bursts (write, rename, chmod); reload once it settles
+        let pending: Rc<Cell<Option<glib2::SourceId>>> = Rc::new(Cell::new(None));
+        let ours = Rc::new(ours);
+        for monitor in &monitors {
+            let (ui, pending, ours) = (Rc::downgrade(self), pending.clone(), ours.clone());
+            monitor.connect_changed(move |_, file, other, event| {
+                let relevant = matches!(
+                    event,
+                    FileMonitorEvent::ChangesDoneHint
+                        | FileMonitorEvent::Created
+                        | FileMonitorEvent::MovedIn
+                        | FileMonitorEvent::Renamed
+                );
+                let touches_ours = [Some(file), other]
+                    .into_iter()
+                    .flatten()
+                    .any(|f| f.path().is_some_and(|path| ours.contains(&path)));
+                if !relevant || !touches_ours {
+                    return;
+                }
+
+                if let Some(source) = pending.take() {
+                    source.remove();
+                }
+                let ui = ui.clone();
+                let pending_done = pending.clone();
+                pending.set(Some(glib2::timeout_add_local_once(
+                    std::time::Duration::from_millis(200),
+                    move || {
+                        pending_done.set(None);
+                        if let Some(ui) = ui.upgrade() {
+                            ui.reload_from_disk();
+                        }
+                    },
+                )));
+            });
+        }
+        *self.config_monitors.borrow_mut() = monitors;
+    }
+
+    fn reload_from_disk(&self) {
+        let applied = {
+            let mut s = self.store.lock().unwrap();
+            let applied = match Config::try_load() {
+                Ok(config) => {
+                    s.set_config(config);
+                    true
+                }
+                Err(e) => {
+                    s.report_config_error(&e);
+                    false
+                }
+            };
+            s.notify_change();
+            applied
+        };
+        if applied {
+            self.apply_config();
+        } else {
+            // The stylesheet may still have changed
+            Self::fill_css(&self.css_provider);
+        }
     }
 
     /// Show a notification popup
     pub fn show_notification(&self, noti: &Notification) {
         let id = noti.id;
-        let config = self.config.clone();
         let was_empty = self.popup_widgets.borrow().is_empty();
 
         // Compute effective timeout upfront (needed by widget for hover-pause)
-        // D-Bus spec: -1 = server decides, 0 = never expire, >0 = ms
-        let effective_timeout = if noti.timeout == 0 {
-            0 // never expire
-        } else if noti.timeout < 0 {
-            config.timeout_for_urgency(noti.urgency as u8) // server decides (seconds)
-        } else {
-            // Client-specified timeout in milliseconds, convert to seconds (min 1s)
-            ((noti.timeout as u32) / 1000).max(1)
-        };
+        let effective_timeout = self.effective_timeout(noti);
 
         // Build the notification widget
         let slot = self.build_notification_widget(noti, true, effective_timeout);
 
         // Wrap in a Revealer for animation
+        // Bottom-anchored stacks grow upward: new popups go on top so the
+        // existing ones stay put against the screen edge.
+        let grows_up = self.config.borrow().position_y == PositionY::Bottom;
         let revealer = Revealer::new();
-        revealer.set_transition_type(RevealerTransitionType::SlideDown);
+        revealer.set_transition_type(if grows_up {
+            RevealerTransitionType::SlideUp
+        } else {
+            RevealerTransitionType::SlideDown
+        });
         revealer.set_transition_duration(Self::POPUP_ANIMATION_MS);
         revealer.set_child(Some(&slot));
         revealer.set_reveal_child(false);
@@ -349,7 +590,11 @@ impl Ui {
         slot_wrapper.append(&revealer);
         slot_wrapper.set_opacity(0.0);
 
-        self.popup_box.append(&slot_wrapper);
+        if grows_up {
+            self.popup_box.prepend(&slot_wrapper);
+        } else {
+            self.popup_box.append(&slot_wrapper);
+        }
         self.popup_widgets
             .borrow_mut()
             .insert(id, slot_wrapper.clone());
@@ -364,12 +609,12 @@ impl Ui {
             let target_h = Self::first_popup_target_height(
                 &self.popup_box,
                 &slot,
-                self.config.popup_width,
-                self.max_popup_h,
+                self.config.borrow().popup_width,
+                self.max_popup_h.get(),
             );
 
             self.popup_window
-                .set_default_size(self.config.popup_width, target_h);
+                .set_default_size(self.config.borrow().popup_width, target_h);
             self.popup_window.queue_resize();
 
             let revealer_for_appear = revealer.clone();
@@ -389,12 +634,12 @@ impl Ui {
         } else {
             let target_h = Self::projected_popup_height(
                 &self.popup_box,
-                self.config.popup_width,
-                self.max_popup_h,
+                self.config.borrow().popup_width,
+                self.max_popup_h.get(),
             );
 
             self.popup_window
-                .set_default_size(self.config.popup_width, target_h);
+                .set_default_size(self.config.borrow().popup_width, target_h);
             self.popup_window.queue_resize();
 
             let revealer_for_appear = revealer.clone();
@@ -413,7 +658,26 @@ impl Ui {
             }
         }
 
-        // Schedule auto-dismiss timeout
+        self.schedule_popup_timeout(noti, effective_timeout);
+        // Note: center widget is added by refresh(), not here
+    }
+
+    /// Timeout in seconds for a popup (0 = never expire)
+    fn effective_timeout(&self, noti: &Notification) -> u32 {
+        // D-Bus spec: -1 = server decides, 0 = never expire, >0 = ms
+        if noti.timeout == 0 {
+            0 // never expire
+        } else if noti.timeout < 0 {
+            self.config.borrow().timeout_for_urgency(noti.urgency as u8) // server decides (seconds)
+        } else {
+            // Client-specified timeout in milliseconds, convert to seconds (min 1s)
+            ((noti.timeout as u32) / 1000).max(1)
+        }
+    }
+
+    /// Schedule auto-dismiss for a popup
+    fn schedule_popup_timeout(&self, noti: &Notification, effective_timeout: u32) {
+        let id = noti.id;
         if effective_timeout > 0 && !noti.acknowledge_to_dismiss {
             let store = self.store.clone();
             let widgets = self.popup_widgets.clone();
@@ -424,6 +688,7 @@ impl Ui {
             let source_id = glib2::timeout_add_seconds_local_once(effective_timeout, move || {
                 Self::dismiss_popup_static(
                     id,
+                    CloseReason::Expired,
                     &store,
                     &widgets,
                     &timeouts,
@@ -434,7 +699,26 @@ impl Ui {
 
             self.timeout_sources.borrow_mut().insert(id, source_id);
         }
-        // Note: center widget is added by refresh(), not here
+    }
+
+    /// Swap a popup slot over to a newer member of the same group in place,
+    /// instead of animating out the old card and pushing a new one.
+    fn collapse_into_popup(&self, old_id: u32, noti: &Notification) {
+        if let Some(source_id) = self.timeout_sources.borrow_mut().remove(&old_id) {
+            source_id.remove();
+        }
+        let Some(wrapper) = self.popup_widgets.borrow_mut().remove(&old_id) else {
+            self.show_notification(noti);
+            return;
+        };
+
+        let effective_timeout = self.effective_timeout(noti);
+        let slot = self.build_notification_widget(noti, true, effective_timeout);
+        if let Some(revealer) = wrapper.first_child().and_then(|c| c.downcast::<Revealer>().ok()) {
+            revealer.set_child(Some(&slot));
+        }
+        self.popup_widgets.borrow_mut().insert(noti.id, wrapper);
+        self.schedule_popup_timeout(noti, effective_timeout);
     }
 
     /// Build a notification widget (used for both popup and center)
@@ -450,6 +734,9 @@ impl Ui {
             },
         ]);
 
+        for class in noti.category_classes() {
+            slot.add_css_class(&class);
+        }
         if let Some(ref class) = noti.css_class {
             slot.add_css_class(class);
         }
@@ -481,14 +768,40 @@ impl Ui {
         summary.set_halign(Align::Start);
         summary.set_ellipsize(gtk4::pango::EllipsizeMode::End);
         summary.set_max_width_chars(40);
-        text_box.append(&summary);
+
+        // Collapsed group popups carry a member counter next to the summary
+        let group_count = match (&noti.group, is_popup) {
+            (Some(group), true) => self.store.lock().unwrap().group_size(group),
+            _ => 1,
+        };
+        if group_count > 1 {
+            let summary_row = GtkBox::new(Orientation::Horizontal, 6);
+            summary.set_hexpand(true);
+            summary_row.append(&summary);
+            summary_row.append(&Self::group_count_badge(group_count));
+            text_box.append(&summary_row);
+        } else {
+            text_box.append(&summary);
+        }
 
         let body_is_truncated = Rc::new(RefCell::new(false));
         if let Some(card) = &noti.card {
             let card_widget = self.build_card_widget(noti, card, is_popup);
             text_box.append(&card_widget);
         } else {
-            if !noti.body.is_empty() {
+            if let Some(error) = &noti.card_error {
+                let invalid = Label::new(Some(&format!("Invalid card: {}", error)));
+                invalid.set_widget_name("notification-card-invalid");
+                invalid.set_css_classes(&["notification-card-error"]);
+                invalid.set_halign(Align::Start);
+                invalid.set_wrap(true);
+                invalid.set_max_width_chars(50);
+                text_box.append(&invalid);
+            }
+
+            // A broken card sent in the body would only show its raw JSON
+            let body_is_card = noti.card_error.is_some() && !noti.hints.contains_key(CARD_HINT);
+            if !noti.body.is_empty() && !body_is_card {
                 let body = Label::new(Some(&noti.body));
                 body.set_widget_name("notification-body");
                 body.set_css_classes(&["body"]);
@@ -507,25 +820,36 @@ impl Ui {
                 text_box.append(&body);
             }
 
-            if !noti.actions.is_empty() {
+            // The inline-reply action becomes a reply entry below the buttons
+            let buttons: Vec<_> = noti.actions.iter().filter(|a| a.key != INLINE_REPLY).collect();
+            if !buttons.is_empty() {
                 let actions_box = GtkBox::new(Orientation::Horizontal, 4);
                 actions_box.set_widget_name("notification-actions");
                 actions_box.set_margin_top(4);
 
-                for action in &noti.actions {
-                    let btn = Button::with_label(&action.label);
+                for action in buttons {
+                    let btn = if noti.action_icons {
+                        let btn = Button::from_icon_name(&action.key);
+                        btn.set_tooltip_text(Some(&action.label));
+                        btn
+                    } else {
+                        Button::with_label(&action.label)
+                    };
                     btn.set_css_classes(&["notification-action"]);
                     let store = self.store.clone();
-                    let signal_tx = self.signal_tx.clone();
                     let action_key = action.key.clone();
                     let noti_id = noti.id;
                     btn.connect_clicked(move |_| {
-                        Self::invoke_action(&store, &signal_tx, noti_id, action_key.clone());
+                        Self::invoke_action(&store, noti_id, action_key.clone());
                     });
                     actions_box.append(&btn);
                 }
                 text_box.append(&actions_box);
             }
+
+            if let Some(reply) = noti.reply_action() {
+                text_box.append(&self.build_reply_row(noti, reply, is_popup));
+            }
         }
 
         // Progress bar
@@ -536,10 +860,26 @@ impl Ui {
             text_box.append(&pbar);
         }
 
+        // Dismiss every member of a collapsed group at once
+        if let (Some(group), true) = (&noti.group, group_count > 1) {
+            let btn = Button::with_label(&format!("Dismiss all {}", group_count));
+            btn.set_css_classes(&["notification-action", "dismiss-group"]);
+            btn.set_halign(Align::Start);
+            btn.set_margin_top(4);
+            let store = self.store.clone();
+            let group = group.clone();
+            btn.connect_clicked(move |_| {
+                let mut s = store.lock().unwrap();
+                s.dismiss_group(&group);
+                s.notify_change();
+            });
+            text_box.append(&btn);
+        }
+
         slot.append(&text_box);
 
         // Close button (only if configured)
-        if self.config.close_button_on_hover {
+        if self.config.borrow().close_button_on_hover {
             let close_btn = Button::with_label("×");
             close_btn.set_css_classes(&["close-button"]);
             close_btn.set_valign(Align::Start);
@@ -571,7 +911,13 @@ impl Ui {
                 let popup_window = self.popup_window.clone();
                 close_btn.connect_clicked(move |_| {
                     Self::dismiss_popup_static(
-                        noti_id, &store, &widgets, &timeouts, &popup_box, &popup_window,
+                        noti_id,
+                        CloseReason::Dismissed,
+                        &store,
+                        &widgets,
+                        &timeouts,
+                        &popup_box,
+                        &popup_window,
                     );
                 });
             } else {
@@ -588,7 +934,7 @@ impl Ui {
         }
 
         // Hover-to-pause timeout (popup only)
-        if self.config.hover_pause && is_popup {
+        if self.config.borrow().hover_pause && is_popup {
             let timeouts = self.timeout_sources.clone();
             let noti_id = noti.id;
             let widgets = self.popup_widgets.clone();
@@ -616,6 +962,7 @@ impl Ui {
                     let sid = glib2::timeout_add_seconds_local_once(original_timeout, move || {
                         Self::dismiss_popup_static(
                             noti_id,
+                            CloseReason::Expired,
                             &store3,
                             &widgets2,
                             &timeouts3,
@@ -631,7 +978,7 @@ impl Ui {
         }
 
         // Click-to-dismiss (only if close_button_on_hover is OFF — they're mutually exclusive)
-        if self.config.click_to_dismiss && !self.config.close_button_on_hover && !noti.acknowledge_to_dismiss {
+        if self.config.borrow().click_to_dismiss && !self.config.borrow().close_button_on_hover && !noti.acknowledge_to_dismiss {
             if is_popup {
                 let click = GestureClick::new();
                 let noti_id = noti.id;
@@ -651,6 +998,7 @@ impl Ui {
                     }
                     Self::dismiss_popup_static(
                         noti_id,
+                        CloseReason::Dismissed,
                         &store,
                         &widgets,
                         &timeouts,
@@ -677,52 +1025,124 @@ impl Ui {
         slot
     }
 
-    fn invoke_action(
-        store: &SharedStore,
-        signal_tx: &std::sync::mpsc::Sender<DbusSignal>,
-        noti_id: u32,
-        action_key: String,
-    ) {
+    fn invoke_action(store: &SharedStore, noti_id: u32, action_key: String) {
         log::info!("Action invoked: id={} key={}", noti_id, action_key);
-        let _ = signal_tx.send(DbusSignal::ActionInvoked {
-            id: noti_id,
-            action_key: action_key.clone(),
-        });
         let mut s = store.lock().unwrap();
-        s.log_action(noti_id, &action_key);
-        s.close(noti_id, CloseReason::Dismissed);
+        s.invoke_action(noti_id, &action_key);
         s.notify_change();
     }
 
+    /// Entry and send button for an `inline-reply` action
+    fn build_reply_row(&self, noti: &Notification, reply: &Action, is_popup: bool) -> GtkBox {
+        let row = GtkBox::new(Orientation::Horizontal, 4);
+        row.set_widget_name("notification-reply");
+        row.set_margin_top(4);
+
+        let entry = Entry::new();
+        entry.set_widget_name("notification-reply-entry");
+        entry.set_hexpand(true);
+        let placeholder = noti.reply_placeholder.as_deref().unwrap_or(&reply.label);
+        entry.set_placeholder_text(Some(placeholder));
+
+        let send = Button::with_label(noti.reply_submit_label.as_deref().unwrap_or("Send"));
+        send.set_css_classes(&["notification-action", "notification-submit"]);
+        send.set_sensitive(false);
+
+        let send_changed = send.clone();
+        entry.connect_changed(move |entry| {
+            send_changed.set_sensitive(!entry.text().trim().is_empty());
+        });
+
+        let store = self.store.clone();
+        let noti_id = noti.id;
+        let entry_send = entry.clone();
+        send.connect_clicked(move |_| {
+            let text = entry_send.text().to_string();
+            if text.trim().is_empty() {
+                return;
+            }
+            log::info!("Reply sent: id={}", noti_id);
+            let mut s = store.lock().unwrap();
+            s.reply(noti_id, &text);
+            s.notify_change();
+        });
+        let send_activate = send.clone();
+        entry.connect_activate(move |_| send_activate.emit_clicked());
+
+        // Popups don't take keystrokes until the entry is clicked, so a chat
+        // message doesn't steal focus from whatever the user is typing in
+        if is_popup {
+            let popup_window = self.popup_window.clone();
+            let click = GestureClick::new();
+            click.connect_pressed(move |_, _, _, _| {
+                popup_window.set_focusable(true);
+                popup_window.present();
+            });
+            entry.add_controller(click);
+        }
+
+        row.append(&entry);
+        row.append(&send);
+        row
+    }
+
     fn build_card_widget(&self, noti: &Notification, card: &NotificationCard, is_popup: bool) -> GtkBox {
         let container = GtkBox::new(Orientation::Vertical, 6);
         container.set_widget_name("notification-card");
         container.set_margin_top(4);
 
-        let question = match card {
-            NotificationCard::MultipleChoice { question, .. } => question,
-            NotificationCard::Permission { question, .. } => question,
-        };
-
-        let question_label = Label::new(Some(question));
+        let question_label = Label::new(Some(card.question()));
         question_label.set_widget_name("notification-card-question");
         question_label.set_halign(Align::Start);
         question_label.set_hexpand(true);
         question_label.set_wrap(true);
         container.append(&question_label);
+        if let Some(deadline) = &noti.card_deadline {
+            container.append(&card_widgets::countdown(noti.created_at, deadline.seconds));
+        }
         container.append(&Separator::new(Orientation::Horizontal));
 
         match card {
             NotificationCard::MultipleChoice {
                 choices,
                 allow_other,
+                mode,
                 ..
             } => {
                 let choices_box = GtkBox::new(Orientation::Vertical, 4);
                 choices_box.set_widget_name("notification-actions");
 
-                let selected_ids = Rc::new(RefCell::new(HashSet::<String>::new()));
-                let option_rows = Rc::new(RefCell::new(Vec::<(String, GtkBox, Label)>::new()));
+                let submit_btn = Button::with_label("Submit");
+                submit_btn.set_css_classes(&["notification-action", "notification-submit"]);
+
+                let error_label = Label::new(None);
+                error_label.set_widget_name("notification-card-error");
+                error_label.set_css_classes(&["notification-card-error"]);
+                error_label.set_halign(Align::Start);
+                error_label.set_wrap(true);
+
+                // Submit only once the selection fits the card's limits; the
+                // free-text row counts once something is typed into it
+                let other_text = Rc::new(RefCell::new(String::new()));
+                let selection = {
+                    let card = card.clone();
+                    let btn = submit_btn.clone();
+                    let error = error_label.clone();
+                    let other_text = other_text.clone();
+                    card_widgets::ChoiceSelection::new(
+                        *mode == ChoiceMode::Single,
+                        move |selected| {
+                            let count = selected
+                                .iter()
+                                .filter(|id| *id != "other" || !other_text.borrow().is_empty())
+                                .count();
+                            let problem = card.selection_error(count);
+                            btn.set_sensitive(problem.is_none());
+                            error.set_text(problem.as_deref().unwrap_or(""));
+                            error.set_visible(problem.is_some());
+                        },
+                    )
+                };
                 let mut choice_label_map = HashMap::<String, String>::new();
 
                 for (index, choice) in choices.iter().enumerate() {
@@ -749,19 +1169,15 @@ impl Ui {
                     row.append(&text);
                     row.append(&check);
 
-                    let selected_click = selected_ids.clone();
+                    let selection_click = selection.clone();
                     let choice_id = choice.id.clone();
-                    let row_click = row.clone();
-                    let check_click = check.clone();
                     let click = GestureClick::new();
                     click.connect_released(move |_, _, _, _| {
-                        Self::toggle_choice_row(&selected_click, &choice_id, &row_click, &check_click);
+                        selection_click.toggle(&choice_id);
                     });
                     row.add_controller(click);
 
-                    option_rows
-                        .borrow_mut()
-                        .push((choice.id.clone(), row.clone(), check.clone()));
+                    selection.add_row(&choice.id, &row, &check);
                     choice_label_map.insert(choice.id.clone(), choice.label.clone());
                     choices_box.append(&row);
                 }
@@ -790,36 +1206,20 @@ impl Ui {
                     other_row.append(&entry);
                     other_row.append(&check);
 
-                    let other_key = "other".to_string();
-                    let selected_focus = selected_ids.clone();
-                    let row_focus = other_row.clone();
-                    let check_focus = check.clone();
-                    let key_focus = other_key.clone();
+                    let selection_focus = selection.clone();
                     entry.connect_has_focus_notify(move |entry_widget| {
                         if entry_widget.has_focus() {
-                            Self::set_choice_row_selected(
-                                &selected_focus,
-                                &key_focus,
-                                &row_focus,
-                                &check_focus,
-                                true,
-                            );
+                            selection_focus.set("other", true);
                         }
                     });
 
-                    let selected_change = selected_ids.clone();
-                    let row_change = other_row.clone();
-                    let check_change = check.clone();
-                    let key_change = other_key.clone();
+                    let selection_change = selection.clone();
+                    let other_text_change = other_text.clone();
                     entry.connect_changed(move |entry_widget| {
-                        let has_text = !entry_widget.text().trim().is_empty();
-                        Self::set_choice_row_selected(
-                            &selected_change,
-                            &key_change,
-                            &row_change,
-                            &check_change,
-                            has_text,
-                        );
+                        let text = entry_widget.text().trim().to_string();
+                        let has_text = !text.is_empty();
+                        *other_text_change.borrow_mut() = text;
+                        selection_change.set("other", has_text);
                     });
 
                     let entry_press_flag = Rc::new(RefCell::new(false));
@@ -830,10 +1230,7 @@ impl Ui {
                     });
                     entry.add_controller(entry_click);
 
-                    let selected_click = selected_ids.clone();
-                    let row_click = other_row.clone();
-                    let check_click = check.clone();
-                    let key_click = other_key.clone();
+                    let selection_click = selection.clone();
                     let press_flag_for_row = entry_press_flag.clone();
                     let click = GestureClick::new();
                     click.connect_released(move |_, _, _, _| {
@@ -843,38 +1240,15 @@ impl Ui {
                             *flag = false;
                             value
                         };
-
-                        if from_entry {
-                            Self::set_choice_row_selected(
-                                &selected_click,
-                                &key_click,
-                                &row_click,
-                                &check_click,
-                                true,
-                            );
-                        } else {
-                            Self::set_choice_row_selected(
-                                &selected_click,
-                                &key_click,
-                                &row_click,
-                                &check_click,
-                                false,
-                            );
-                        }
+                        selection_click.set("other", from_entry);
                     });
                     other_row.add_controller(click);
 
-                    option_rows
-                        .borrow_mut()
-                        .push(("other".to_string(), other_row.clone(), check.clone()));
+                    selection.add_row("other", &other_row, &check);
                     choices_box.append(&other_row);
 
                     if is_popup {
-                        let popup_window = self.popup_window.clone();
-                        glib2::timeout_add_local_once(std::time::Duration::from_millis(20), move || {
-                            popup_window.set_focusable(true);
-                            popup_window.present();
-                        });
+                        self.take_keyboard_focus();
                     }
 
                     Some(entry)
@@ -883,88 +1257,54 @@ impl Ui {
                 };
 
                 container.append(&choices_box);
+                container.append(&error_label);
                 container.append(&Separator::new(Orientation::Horizontal));
 
                 let submit_row = GtkBox::new(Orientation::Horizontal, 0);
                 submit_row.set_halign(Align::End);
 
-                let submit_btn = Button::with_label("Submit");
-                submit_btn.set_css_classes(&["notification-action", "notification-submit"]);
-
                 let store_submit = self.store.clone();
-                let signal_submit = self.signal_tx.clone();
-                let selected_submit = selected_ids.clone();
-                let options_submit = option_rows.clone();
-                let labels_submit = Rc::new(choice_label_map.clone());
-                let other_submit = other_entry.clone();
+                let selection_submit = selection.clone();
+                let labels_submit = choice_label_map;
                 let noti_id = noti.id;
 
-                submit_btn.connect_clicked(move |_| {
-                    let selected_now = selected_submit.borrow();
-                    let options_now = options_submit.borrow();
-
+                submit_btn.connect_clicked(move |btn| {
+                    if !btn.is_sensitive() {
+                        return;
+                    }
                     let mut selected = Vec::new();
-                    for (id, _, _) in options_now.iter() {
-                        if !selected_now.contains(id) {
+                    for id in selection_submit.selected() {
+                        if id == "other" {
                             continue;
                         }
                         let label = labels_submit
-                            .get(id)
+                            .get(&id)
                             .cloned()
                             .unwrap_or_else(|| id.clone());
                         selected.push(json!({"id": id, "label": label}));
                     }
 
-                    let other_text = other_submit
-                        .as_ref()
-                        .map(|entry| entry.text().trim().to_string())
-                        .unwrap_or_default();
-
+                    // Typed text that was deselected isn't part of the answer
+                    let other_text = other_text.borrow().clone();
                     let payload = json!({
                         "type": "multiple-choice",
                         "selected": selected,
-                        "other": if other_text.is_empty() { serde_json::Value::Null } else { serde_json::Value::String(other_text) }
+                        "other": if other_text.is_empty() || !selection_submit.is_selected("other") { serde_json::Value::Null } else { serde_json::Value::String(other_text) }
                     });
 
-                    Self::invoke_action(&store_submit, &signal_submit, noti_id, payload.to_string());
+                    Self::invoke_action(&store_submit, noti_id, payload.to_string());
                 });
 
                 if let Some(entry) = other_entry.clone() {
-                    let store_enter = self.store.clone();
-                    let signal_enter = self.signal_tx.clone();
-                    let selected_enter = selected_ids.clone();
-                    let options_enter = option_rows.clone();
-                    let labels_enter = Rc::new(choice_label_map);
-                    let noti_id_enter = noti.id;
-                    let other_entry_for_enter = entry.clone();
+                    let submit_enter = submit_btn.clone();
                     entry.connect_activate(move |_| {
-                        let selected_now = selected_enter.borrow();
-                        let options_now = options_enter.borrow();
-
-                        let mut selected = Vec::new();
-                        for (id, _, _) in options_now.iter() {
-                            if !selected_now.contains(id) {
-                                continue;
-                            }
-                            let label = labels_enter
-                                .get(id)
-                                .cloned()
-                                .unwrap_or_else(|| id.clone());
-                            selected.push(json!({"id": id, "label": label}));
-                        }
-
-                        let other_text = other_entry_for_enter.text().trim().to_string();
-                        let payload = json!({
-                            "type": "multiple-choice",
-                            "selected": selected,
-                            "other": if other_text.is_empty() { serde_json::Value::Null } else { serde_json::Value::String(other_text) }
-                        });
-                        Self::invoke_action(&store_enter, &signal_enter, noti_id_enter, payload.to_string());
+                        submit_enter.emit_clicked();
                     });
                 }
 
-                let options_key = option_rows.clone();
-                let selected_key = selected_ids.clone();
+                let selection_key = selection.clone();
+                let submit_key = submit_btn.clone();
+                let single = *mode == ChoiceMode::Single;
                 let other_entry_key = other_entry.clone();
                 let key_controller = EventControllerKey::new();
                 key_controller.connect_key_pressed(move |_, key, _, _| {
@@ -974,69 +1314,110 @@ impl Ui {
                         }
                     }
 
-                    if let Some(index) = Self::choice_hotkey_index(key) {
-                        let rows = options_key.borrow();
-                        if let Some((id, row, check)) = rows.get(index) {
-                            Self::toggle_choice_row(&selected_key, id, row, check);
-                            return glib2::Propagation::Stop;
+                    let id = Self::choice_hotkey_index(key).and_then(|i| selection_key.id_at(i));
+                    match id {
+                        // Picking the only answer is the whole interaction
+                        Some(id) if single && id != "other" => {
+                            selection_key.set(&id, true);
+                            submit_key.emit_clicked();
+                            glib2::Propagation::Stop
                         }
+                        Some(id) => {
+                            selection_key.toggle(&id);
+                            glib2::Propagation::Stop
+                        }
+                        None => glib2::Propagation::Proceed,
                     }
-                    glib2::Propagation::Proceed
                 });
                 container.add_controller(key_controller);
 
+                selection.changed();
                 submit_row.append(&submit_btn);
                 container.append(&submit_row);
             }
-            NotificationCard::Permission { allow_label, .. } => {
-                let actions_box = GtkBox::new(Orientation::Horizontal, 4);
-                actions_box.set_widget_name("notification-actions");
-                let btn = Button::with_label(allow_label);
-                btn.set_css_classes(&["notification-action", "notification-submit"]);
-                let store = self.store.clone();
-                let signal_tx = self.signal_tx.clone();
-                let noti_id = noti.id;
-                btn.connect_clicked(move |_| {
-                    Self::invoke_action(&store, &signal_tx, noti_id, "allow".to_string());
-                });
-                actions_box.append(&btn);
-                container.append(&actions_box);
+            NotificationCard::Permission {
+                allow_label,
+                deny_label,
+                allow_always,
+                allow_always_label,
+                ask_reason,
+                ..
+            } => {
+                let always = allow_always.then_some(allow_always_label.as_str());
+                container.append(&card_widgets::permission(
+                    allow_label,
+                    deny_label,
+                    always,
+                    *ask_reason,
+                    self.card_submit(noti.id),
+                ));
+                if *ask_reason && is_popup {
+                    self.take_keyboard_focus();
+                }
+            }
+            NotificationCard::TextInput {
+                placeholder,
+                multiline,
+                pattern,
+                pattern_error,
+                ..
+            } => {
+                container.append(&card_widgets::text_input(
+                    placeholder.as_deref(),
+                    *multiline,
+                    pattern.as_deref(),
+                    pattern_error.as_deref(),
+                    self.card_submit(noti.id),
+                ));
+                if is_popup {
+                    self.take_keyboard_focus();
+                }
+            }
+            NotificationCard::Confirm {
+                confirm_label,
+                cancel_label,
+                ..
+            } => {
+                let submit = self.card_submit(noti.id);
+                container.append(&card_widgets::confirm(confirm_label, cancel_label, submit));
+            }
+            NotificationCard::Rating { max, .. } => {
+                container.append(&card_widgets::rating(*max, self.card_submit(noti.id)));
+            }
+            NotificationCard::Slider {
+                min,
+                max,
+                step,
+                value,
+                ..
+            } => {
+                let submit = self.card_submit(noti.id);
+                container.append(&card_widgets::slider(*min, *max, *step, *value, submit));
+            }
+            NotificationCard::Datetime { mode, value, .. } => {
+                let submit = self.card_submit(noti.id);
+                container.append(&card_widgets::datetime(*mode, value.as_deref(), submit));
             }
         }
 
         container
     }
 
-    fn toggle_choice_row(
-        selected_ids: &Rc<RefCell<HashSet<String>>>,
-        choice_id: &str,
-        row: &GtkBox,
-        check: &Label,
-    ) {
-        let is_selected = {
-            let selected = selected_ids.borrow();
-            !selected.contains(choice_id)
-        };
-        Self::set_choice_row_selected(selected_ids, choice_id, row, check, is_selected);
+    /// Send a card's JSON reply as the action key of notification `id`
+    fn card_submit(&self, id: u32) -> card_widgets::Submit {
+        let store = self.store.clone();
+        Rc::new(move |payload: serde_json::Value| {
+            Self::invoke_action(&store, id, payload.to_string())
+        })
     }
 
-    fn set_choice_row_selected(
-        selected_ids: &Rc<RefCell<HashSet<String>>>,
-        choice_id: &str,
-        row: &GtkBox,
-        check: &Label,
-        selected: bool,
-    ) {
-        let mut selected_set = selected_ids.borrow_mut();
-        if selected {
-            selected_set.insert(choice_id.to_string());
-            row.add_css_class("selected");
-            check.set_text("✓");
-        } else {
-            selected_set.remove(choice_id);
-            row.remove_css_class("selected");
-            check.set_text("");
-        }
+    /// Let the popup window take keystrokes, for cards with text entries
+    fn take_keyboard_focus(&self) {
+        let popup_window = self.popup_window.clone();
+        glib2::timeout_add_local_once(std::time::Duration::from_millis(20), move || {
+            popup_window.set_focusable(true);
+            popup_window.present();
+        });
     }
 
     fn choice_hotkey_index(key: gdk4::Key) -> Option<usize> {
@@ -1056,6 +1437,7 @@ impl Ui {
 
     fn dismiss_popup_static(
         id: u32,
+        reason: CloseReason,
         store: &SharedStore,
         widgets: &Rc<RefCell<HashMap<u32, GtkBox>>>,
         timeouts: &Rc<RefCell<HashMap<u32, glib2::SourceId>>>,
@@ -1071,7 +1453,7 @@ impl Ui {
 
         // Remove from store
         let mut s = store.lock().unwrap();
-        s.close(id, CloseReason::Dismissed);
+        s.close(id, reason);
         // Trigger refresh so center side gets cleaned up too
         s.notify_change();
     }
@@ -1197,8 +1579,8 @@ impl Ui {
 
     /// Deferred resize of the popup window so it grows/shrinks to fit content.
     fn schedule_popup_resize(&self) {
-        let max_h = self.max_popup_h;
-        let width = self.config.popup_width;
+        let max_h = self.max_popup_h.get();
+        let width = self.config.borrow().popup_width;
         let anim_ms = Self::POPUP_ANIMATION_MS;
 
         let popup_window_early = self.popup_window.clone();
@@ -1348,6 +1730,167 @@ impl Ui {
         }
     }
 
+    /// Show the notification center, hiding popups while it is open
+    fn open_center(popup_window: &Window, center_window: &Window) {
+        popup_window.set_visible(false);
+        center_window.present();
+        center_window.grab_focus();
+    }
+
+    /// Keep the "+N more" row at the far end of the popup stack
+    fn update_overflow_row(&self, queued: usize) {
+        if self.overflow_row.parent().is_some() {
+            self.popup_box.remove(&self.overflow_row);
+        }
+        if queued == 0 {
+            return;
+        }
+
+        self.overflow_label.set_text(&format!("+{} more", queued));
+        if self.config.borrow().position_y == PositionY::Bottom {
+            self.popup_box.prepend(&self.overflow_row);
+        } else {
+            self.popup_box.append(&self.overflow_row);
+        }
+    }
+
+    /// Split center notifications (newest first) into single cards and
+    /// groups with two or more members.
+    fn center_layout(notis: &[&Notification]) -> (Vec<Notification>, Vec<(String, Vec<Notification>)>) {
+        let mut counts = HashMap::<&str, usize>::new();
+        for noti in notis {
+            if let Some(group) = noti.group.as_deref() {
+                *counts.entry(group).or_default() += 1;
+            }
+        }
+
+        let mut singles = Vec::new();
+        let mut grouped: Vec<(String, Vec<Notification>)> = Vec::new();
+        for noti in notis {
+            match noti.group.as_deref() {
+                Some(group) if counts[group] > 1 => {
+                    match grouped.iter_mut().find(|(key, _)| key == group) {
+                        Some((_, members)) => members.push((*noti).clone()),
+                        None => grouped.push((group.to_string(), vec![(*noti).clone()])),
+                    }
+                }
+                _ => singles.push((*noti).clone()),
+            }
+        }
+        (singles, grouped)
+    }
+
+    /// The notification that took over `old_id`'s popup slot, if it is a
+    /// newer member of the same group without a widget yet.
+    fn group_successor(&self, old_id: u32, slot_ids: &[u32]) -> Option<Notification> {
+        let store = self.store.lock().unwrap();
+        let group = store.notifications.get(&old_id)?.group.clone()?;
+        let widgets = self.popup_widgets.borrow();
+        slot_ids
+            .iter()
+            .filter(|id| !widgets.contains_key(id))
+            .filter_map(|id| store.notifications.get(id))
+            .find(|n| n.group.as_deref() == Some(group.as_str()))
+            .cloned()
+    }
+
+    fn group_count_badge(count: usize) -> Label {
+        let badge = Label::new(Some(&count.to_string()));
+        badge.set_widget_name("group-count");
+        badge.set_css_classes(&["group-count"]);
+        badge.set_valign(Align::Center);
+        badge
+    }
+
+    /// Add a stacked group card to the center panel: header with count badge,
+    /// latest summary, expand/collapse and clear; latest card always visible,
+    /// older members revealed on expand.
+    fn add_group_to_center(&self, key: &str, members: &[Notification]) {
+        let container = GtkBox::new(Orientation::Vertical, 4);
+        container.set_widget_name("notification-group");
+        container.set_css_classes(&["notification-group"]);
+
+        let expanded = self.expanded_groups.borrow().contains(key);
+
+        let header = GtkBox::new(Orientation::Horizontal, 8);
+        header.set_widget_name("notification-group-header");
+        header.set_margin_start(8);
+        header.set_margin_end(8);
+
+        header.append(&Self::group_count_badge(members.len()));
+
+        let title = Label::new(Some(&members[0].summary));
+        title.set_widget_name("notification-group-title");
+        title.set_halign(Align::Start);
+        title.set_hexpand(true);
+        title.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        header.append(&title);
+
+        let toggle_btn = Button::with_label(if expanded { "Collapse" } else { "Expand" });
+        toggle_btn.set_css_classes(&["notification-action", "group-toggle"]);
+        header.append(&toggle_btn);
+
+        let clear_btn = Button::with_label("Clear");
+        clear_btn.set_css_classes(&["notification-action", "group-clear"]);
+        let store_clear = self.store.clone();
+        let key_clear = key.to_string();
+        clear_btn.connect_clicked(move |_| {
+            let mut s = store_clear.lock().unwrap();
+            s.dismiss_group(&key_clear);
+            s.notify_change();
+            log::info!("Group {:?} cleared", key_clear);
+        });
+        header.append(&clear_btn);
+        container.append(&header);
+
+        let latest = self.build_notification_widget(&members[0], false, 0);
+        if !expanded {
+            latest.add_css_class("stacked");
+        }
+        container.append(&latest);
+
+        let older_box = GtkBox::new(Orientation::Vertical, self.config.borrow().spacing);
+        for noti in &members[1..] {
+            older_box.append(&self.build_notification_widget(noti, false, 0));
+        }
+        let revealer = Revealer::new();
+        revealer.set_transition_type(RevealerTransitionType::SlideDown);
+        revealer.set_child(Some(&older_box));
+        revealer.set_reveal_child(expanded);
+        container.append(&revealer);
+
+        let expanded_groups = self.expanded_groups.clone();
+        let key_toggle = key.to_string();
+        toggle_btn.connect_clicked(move |btn| {
+            let now_expanded = {
+                let mut set = expanded_groups.borrow_mut();
+                if !set.remove(&key_toggle) {
+                    set.insert(key_toggle.clone());
+                }
+                set.contains(&key_toggle)
+            };
+            revealer.set_reveal_child(now_expanded);
+            btn.set_label(if now_expanded { "Collapse" } else { "Expand" });
+            if now_expanded {
+                latest.remove_css_class("stacked");
+            } else {
+                latest.add_css_class("stacked");
+            }
+        });
+
+        // Remove empty placeholder if present
+        if let Some(first) = self.center_box.first_child() {
+            if first.widget_name() == "center-empty" {
+                self.center_box.remove(&first);
+            }
+        }
+        let ids = members.iter().map(|n| n.id).collect();
+        self.center_groups
+            .borrow_mut()
+            .insert(key.to_string(), (ids, container.clone()));
+        self.center_box.append(&container);
+    }
+
     /// Add a notification to the center panel
     fn add_to_center(&self, noti: &Notification) {
         let widget = self.build_notification_widget(noti, false, 0);
@@ -1360,63 +1903,105 @@ impl Ui {
         self.center_widgets.borrow_mut().insert(noti.id, widget.clone());
         self.center_box.append(&widget);
     }
+}
 
-    /// Toggle the notification center visibility
-    pub fn toggle_center(&self) {
-        let visible = self.center_window.is_visible();
-        if visible {
-            self.center_window.set_visible(false);
-            // Re-show popups if there are any remaining
-            if !self.popup_widgets.borrow().is_empty() {
-                self.popup_window.set_visible(true);
-                self.popup_window.present();
-            }
+impl Frontend for Ui {
+    fn toggle_center(&self) {
+        if self.center_window.is_visible() {
+            self.hide_center();
         } else {
-            // Hide popups while center is open
-            self.popup_window.set_visible(false);
-            self.center_window.present();
-            self.center_window.grab_focus();
+            self.show_center();
         }
     }
 
-    /// Refresh the UI from the store (called after store changes)
-    pub fn refresh(&self) {
-        // Sync center: remove widgets for notifications no longer in store
-        let (store_ids, replaced_ids): (Vec<u32>, Vec<u32>) = {
-            let mut store = self.store.lock().unwrap();
-            (store.order.clone(), store.take_replaced_ids())
-        };
+    fn show_center(&self) {
+        if !self.center_window.is_visible() {
+            Self::open_center(&self.popup_window, &self.center_window);
+        }
+    }
+
+    fn hide_center(&self) {
+        if !self.center_window.is_visible() {
+            return;
+        }
+        self.center_window.set_visible(false);
+        // Re-show popups if there are any remaining
+        if !self.popup_widgets.borrow().is_empty() {
+            self.popup_window.set_visible(true);
+            self.popup_window.present();
+        }
+    }
+
+    fn refresh(&self) {
+        Self::update_dnd_button(&self.dnd_button, &self.store);
 
-        for id in replaced_ids {
-            if let Some(widget) = self.center_widgets.borrow_mut().remove(&id) {
+        let replaced_ids: Vec<u32> = self.store.lock().unwrap().take_replaced_ids();
+
+        for id in &replaced_ids {
+            if let Some(widget) = self.center_widgets.borrow_mut().remove(id) {
                 self.center_box.remove(&widget);
             }
 
-            if let Some(source_id) = self.timeout_sources.borrow_mut().remove(&id) {
+            if let Some(source_id) = self.timeout_sources.borrow_mut().remove(id) {
                 source_id.remove();
             }
 
             Self::animate_remove_popup_by_id(
-                id,
+                *id,
                 &self.popup_widgets,
                 &self.popup_box,
                 &self.popup_window,
             );
         }
 
+        // Desired center layout: single cards by ID, stacked cards by group key
+        let (singles, grouped) = {
+            let store = self.store.lock().unwrap();
+            Self::center_layout(&store.all_notifications())
+        };
+
+        // Sync center: remove single cards that were closed or joined a group
         let center_ids: Vec<u32> = self.center_widgets.borrow().keys().cloned().collect();
         for id in center_ids {
-            if !store_ids.contains(&id) {
+            if !singles.iter().any(|n| n.id == id) {
                 if let Some(widget) = self.center_widgets.borrow_mut().remove(&id) {
                     self.center_box.remove(&widget);
                 }
             }
         }
 
-        // Sync popups: remove widgets for notifications no longer in store
+        // Rebuild stacked cards whose membership changed
+        let group_keys: Vec<String> = self.center_groups.borrow().keys().cloned().collect();
+        for key in group_keys {
+            let current = grouped
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, members)| members.iter().map(|n| n.id).collect::<Vec<u32>>());
+            let stale = {
+                let groups = self.center_groups.borrow();
+                let (ids, _) = &groups[&key];
+                current.as_ref() != Some(ids) || ids.iter().any(|id| replaced_ids.contains(id))
+            };
+            if stale {
+                if let Some((_, widget)) = self.center_groups.borrow_mut().remove(&key) {
+                    self.center_box.remove(&widget);
+                }
+            }
+        }
+
+        // Sync popups: remove widgets for notifications that lost their slot
+        let (slot_ids, queued) = {
+            let store = self.store.lock().unwrap();
+            (store.popup_slots.clone(), store.queued_count())
+        };
         let popup_ids: Vec<u32> = self.popup_widgets.borrow().keys().cloned().collect();
         for id in popup_ids {
-            if !store_ids.contains(&id) {
+            if !slot_ids.contains(&id) {
+                // A newer member of the same group took over this slot
+                if let Some(newer) = self.group_successor(id, &slot_ids) {
+                    self.collapse_into_popup(id, &newer);
+                    continue;
+                }
                 if let Some(source_id) = self.timeout_sources.borrow_mut().remove(&id) {
                     source_id.remove();
                 }
@@ -1434,22 +2019,22 @@ impl Ui {
         }
 
         // Add new notifications to center (ALL notifications, not just visible_popups)
-        let all_notis: Vec<Notification> = {
-            let store = self.store.lock().unwrap();
-            let existing: Vec<u32> = self.center_widgets.borrow().keys().cloned().collect();
-            store
-                .all_notifications()
-                .into_iter()
-                .filter(|n| !existing.contains(&n.id))
-                .cloned()
-                .collect()
-        };
-        for noti in all_notis {
-            self.add_to_center(&noti);
+        for noti in singles {
+            if !self.center_widgets.borrow().contains_key(&noti.id) {
+                self.add_to_center(&noti);
+            }
+        }
+        for (key, members) in grouped {
+            if !self.center_groups.borrow().contains_key(&key) {
+                self.add_group_to_center(&key, &members);
+            }
         }
 
         // Show empty placeholder if center is now empty
-        if self.center_widgets.borrow().is_empty() && self.center_box.first_child().is_none() {
+        if self.center_widgets.borrow().is_empty()
+            && self.center_groups.borrow().is_empty()
+            && self.center_box.first_child().is_none()
+        {
             let empty_label = Label::new(Some("No Notifications"));
             empty_label.set_widget_name("center-empty");
             empty_label.set_css_classes(&["dim-label"]);
@@ -1472,9 +2057,49 @@ impl Ui {
             self.show_notification(&noti);
         }
 
+        self.update_overflow_row(queued);
+
         // Single deferred resize after all additions / removals are done
         if self.popup_box.first_child().is_some() {
             self.schedule_popup_resize();
         }
     }
+
+    /// Take over the store's config after a reload. Geometry, spacing and the
+    /// DND button change right away; per-notification settings (timeouts,
+    /// close button, click-to-dismiss) apply to notifications shown from now
+    /// on. The stylesheet is re-read as well.
+    fn apply_config(&self) {
+        let config = self.store.lock().unwrap().config.clone();
+        let (monitor_area, max_popup_h, max_center_h) = Self::geometry(&config);
+        self.monitor_area.set(monitor_area);
+        self.max_popup_h.set(max_popup_h);
+        self.popup_scroll.set_max_content_height(max_popup_h);
+        self.center_scroll.set_max_content_height(max_center_h);
+        self.popup_window.set_default_width(config.popup_width);
+        self.center_window.set_default_size(config.popup_width, max_center_h);
+        self.popup_box.set_spacing(config.spacing);
+        self.center_box.set_spacing(config.spacing);
+        self.dnd_button.set_visible(config.dnd_enabled);
+        *self.config.borrow_mut() = config;
+
+        for (window, title) in [
+            (&self.popup_window, Self::POPUP_TITLE),
+            (&self.center_window, Self::CENTER_TITLE),
+        ] {
+            if window.is_visible()
+                && let Some(place) = self.placement(title)
+            {
+                place(window);
+            }
+        }
+
+        Self::fill_css(&self.css_provider);
+    }
+
+    fn quit(&self) {
+        self.popup_window.set_visible(false);
+        self.center_window.set_visible(false);
+        self.main_loop.quit();
+    }
 }
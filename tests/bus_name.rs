@@ -0,0 +1,74 @@
+mod common;
+
+use common::TestDaemon;
+use std::sync::mpsc;
+use std::time::Duration;
+use xnotid::config::Config;
+use xnotid::dbus_server::{self, DbusSignal, UiCommand};
+use xnotid::store::{SharedStore, Store};
+use zbus::Connection;
+
+/// A second daemon on the test bus, with its own store and channels
+async fn second_daemon(
+    daemon: &TestDaemon,
+    replace: bool,
+) -> (SharedStore, mpsc::Receiver<UiCommand>, zbus::Result<Connection>) {
+    let mut config = Config::default();
+    config.log_path = daemon.bus.dir.join("second.jsonl").to_string_lossy().into_owned();
+    config.state_path = daemon.bus.dir.join("second.json").to_string_lossy().into_owned();
+    let store = Store::new_shared(config);
+    let (_signal_tx, signal_rx) = tokio::sync::mpsc::unbounded_channel::<DbusSignal>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<UiCommand>();
+    let server =
+        dbus_server::serve_on(daemon.bus.connect().await, store.clone(), cmd_tx, signal_rx, replace)
+            .await;
+    (store, cmd_rx, server)
+}
+
+#[tokio::test]
+async fn name_conflict_names_the_owner() {
+    let daemon = TestDaemon::start().await;
+
+    let (_store, _commands, server) = second_daemon(&daemon, false).await;
+    let message = server.err().expect("name is taken").to_string();
+    assert!(message.contains("org.freedesktop.Notifications"), "{}", message);
+    assert!(message.contains(&format!("pid {}", std::process::id())), "{}", message);
+    assert!(message.contains("--replace"), "{}", message);
+
+    // The running daemon keeps serving
+    assert_eq!(daemon.notify("still here", 0).await, 1);
+    assert_eq!(daemon.ui.quits.get(), 0);
+}
+
+#[tokio::test]
+async fn replace_takes_over_and_old_daemon_quits() {
+    let daemon = TestDaemon::start().await;
+
+    let (store, _commands, server) = second_daemon(&daemon, true).await;
+    let _server = server.expect("replace the running daemon");
+
+    // The old daemon hears NameLost and asks its UI to quit
+    for _ in 0..40 {
+        daemon.pump();
+        if daemon.ui.quits.get() > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(daemon.ui.quits.get(), 1);
+
+    // New notifications reach the new daemon
+    daemon.notify("to the new daemon", 0).await;
+    assert_eq!(store.lock().unwrap().order.len(), 1);
+    assert!(daemon.store.lock().unwrap().order.is_empty());
+}
+
+#[tokio::test]
+async fn release_names_frees_the_bus_names() {
+    let daemon = TestDaemon::start().await;
+
+    dbus_server::release_names(&daemon.server).await;
+
+    let (_store, _commands, server) = second_daemon(&daemon, false).await;
+    assert!(server.is_ok(), "{:?}", server.err());
+}
@@ -0,0 +1,193 @@
+mod common;
+
+use common::{next_signal, TestDaemon};
+use std::collections::HashMap;
+use xnotid::notification::{CARD_HINT, CloseReason, NotificationCard};
+use zbus::zvariant::Value;
+
+async fn send(daemon: &TestDaemon, body: &str, hints: HashMap<&str, Value<'_>>) -> u32 {
+    daemon
+        .proxy()
+        .await
+        .notify("agent", 0, "", "Question", body, &[], hints, -1)
+        .await
+        .unwrap()
+}
+
+fn question(daemon: &TestDaemon, id: u32) -> Option<String> {
+    let store = daemon.store.lock().unwrap();
+    let card = store.notifications[&id].card.as_ref()?;
+    Some(card.question().to_string())
+}
+
+#[tokio::test]
+async fn v1_and_v2_envelopes_are_accepted() {
+    let daemon = TestDaemon::start().await;
+
+    let v1 = r#"{"xnotid_card":"v1","type":"confirm","question":"Reboot?"}"#;
+    let id = send(&daemon, v1, HashMap::new()).await;
+    assert_eq!(question(&daemon, id).as_deref(), Some("Reboot?"));
+
+    let v2 = r#"{"xnotid_card":"v2","card":{"type":"rating","question":"Rate it"}}"#;
+    let id = send(&daemon, v2, HashMap::new()).await;
+    assert_eq!(question(&daemon, id).as_deref(), Some("Rate it"));
+
+    // Plain text that happens to be JSON isn't a card
+    let id = send(&daemon, r#"{"type":"confirm","question":"?"}"#, HashMap::new()).await;
+    assert_eq!(question(&daemon, id), None);
+    assert_eq!(daemon.store.lock().unwrap().notifications[&id].card_error, None);
+}
+
+#[tokio::test]
+async fn hint_cards_keep_the_body_as_fallback() {
+    let daemon = TestDaemon::start().await;
+
+    let envelope = r#"{"xnotid_card":"v2","card":{"type":"confirm","question":"Reboot now?"}}"#;
+    let hints = HashMap::from([(CARD_HINT, Value::from(envelope))]);
+    let id = send(&daemon, "Reboot now? (needs xnotid)", hints).await;
+
+    let store = daemon.store.lock().unwrap();
+    let noti = &store.notifications[&id];
+    assert!(matches!(noti.card, Some(NotificationCard::Confirm { .. })));
+    assert_eq!(noti.body, "Reboot now? (needs xnotid)");
+    assert!(noti.acknowledge_to_dismiss);
+}
+
+#[tokio::test]
+async fn invalid_cards_carry_an_error() {
+    let daemon = TestDaemon::start().await;
+
+    let cases = [
+        (r#"{"xnotid_card":"v9","card":{}}"#, "unsupported card version"),
+        (r#"{"xnotid_card":"v2","type":"confirm","question":"?"}"#, "`card` object"),
+        (r#"{"xnotid_card":"v1","type":"poll","question":"?"}"#, "unknown variant"),
+        (r#"{"xnotid_card":"v1","type":"rating","question":"?","max":0}"#, "`max`"),
+    ];
+    for (body, error) in cases {
+        let id = send(&daemon, body, HashMap::new()).await;
+        let store = daemon.store.lock().unwrap();
+        let noti = &store.notifications[&id];
+        assert!(noti.card.is_none(), "{}", body);
+        let card_error = noti.card_error.as_deref().unwrap_or_default();
+        assert!(card_error.contains(error), "{}: {}", body, card_error);
+    }
+
+    let hints = HashMap::from([(CARD_HINT, Value::from("{not json"))]);
+    let id = send(&daemon, "fallback", hints).await;
+    let store = daemon.store.lock().unwrap();
+    let noti = &store.notifications[&id];
+    assert!(noti.card_error.as_deref().unwrap().starts_with("invalid JSON"));
+    assert!(!noti.acknowledge_to_dismiss);
+}
+
+#[test]
+fn selection_limits() {
+    let card = |extra: serde_json::Value| {
+        let mut json = serde_json::json!({
+            "type": "multiple-choice",
+            "question": "?",
+            "choices": [{ "id": "a", "label": "A" }, { "id": "b", "label": "B" }],
+            "allow_other": true,
+        });
+        json.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        NotificationCard::from_envelope(json)
+    };
+
+    let any = card(serde_json::json!({})).unwrap();
+    assert_eq!(any.selection_limits(), Some((0, usize::MAX)));
+    assert_eq!(any.selection_error(0), None);
+
+    let single = card(serde_json::json!({ "mode": "single", "required": true })).unwrap();
+    assert_eq!(single.selection_limits(), Some((1, 1)));
+    assert_eq!(single.selection_error(0).as_deref(), Some("Pick an answer"));
+    assert_eq!(single.selection_error(1), None);
+
+    let ranged = card(serde_json::json!({ "min_selected": 2, "max_selected": 2 })).unwrap();
+    assert_eq!(ranged.selection_error(1).as_deref(), Some("Pick at least 2 answers"));
+    assert_eq!(ranged.selection_error(3).as_deref(), Some("Pick at most 2 answers"));
+
+    let err = card(serde_json::json!({ "min_selected": 3, "max_selected": 1 })).unwrap_err();
+    assert!(err.contains("above `max_selected`"), "{}", err);
+    let err = card(serde_json::json!({ "min_selected": 4 })).unwrap_err();
+    assert!(err.contains("above the 3 options"), "{}", err);
+}
+
+#[tokio::test]
+async fn deadlines_answer_with_the_default() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut actions = proxy.receive_action_invoked().await.unwrap();
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let card = r#"{"xnotid_card":"v2","card":{"type":"multiple-choice","question":"Retry?",
+        "choices":[{"id":"yes","label":"Yes"},{"id":"no","label":"No"}],
+        "deadline_seconds":30,"default_choice":"no"}}"#;
+    let id = send(&daemon, card, HashMap::new()).await;
+    let created_at = daemon.store.lock().unwrap().notifications[&id].created_at;
+
+    let early = created_at + chrono::TimeDelta::seconds(29);
+    assert!(!daemon.store.lock().unwrap().expire_cards_at(early));
+    let due = created_at + chrono::TimeDelta::seconds(30);
+    assert!(daemon.store.lock().unwrap().expire_cards_at(due));
+
+    let action = next_signal(&mut actions).await;
+    let args = action.args().unwrap();
+    assert_eq!(args.id, id);
+    let answer: serde_json::Value = serde_json::from_str(&args.action_key).unwrap();
+    assert_eq!(answer["selected"][0]["id"], "no");
+    assert_eq!(answer["timed_out"], true);
+
+    let signal = next_signal(&mut closed).await;
+    let args = signal.args().unwrap();
+    assert_eq!((args.id, args.reason), (id, CloseReason::Expired as u32));
+}
+
+#[test]
+fn default_choices_are_checked() {
+    let parse = |card: serde_json::Value| NotificationCard::parse_envelope(card).map(|(_, d)| d);
+
+    let permission = serde_json::json!({
+        "type": "permission", "question": "?", "deadline_seconds": 10,
+    });
+    let deadline = parse(permission).unwrap().unwrap();
+    assert_eq!(deadline.seconds, 10);
+    assert_eq!(deadline.answer["decision"], "deny");
+    assert_eq!(deadline.timed_out_answer()["timed_out"], true);
+
+    for card in [
+        serde_json::json!({ "type": "confirm", "question": "?", "default_choice": true }),
+        serde_json::json!({ "type": "confirm", "question": "?", "deadline_seconds": 0 }),
+        serde_json::json!({
+            "type": "permission", "question": "?", "deadline_seconds": 5,
+            "default_choice": "allow_always",
+        }),
+        serde_json::json!({
+            "type": "rating", "question": "?", "deadline_seconds": 5, "default_choice": 6,
+        }),
+        serde_json::json!({
+            "type": "multiple-choice", "question": "?", "choices": [{ "id": "a", "label": "A" }],
+            "deadline_seconds": 5, "default_choice": "b",
+        }),
+        serde_json::json!({
+            "type": "multiple-choice", "question": "?", "mode": "single",
+            "choices": [{ "id": "a", "label": "A" }, { "id": "b", "label": "B" }],
+            "deadline_seconds": 5, "default_choice": ["a", "b"],
+        }),
+        serde_json::json!({
+            "type": "multiple-choice", "question": "?", "required": true,
+            "choices": [{ "id": "a", "label": "A" }], "deadline_seconds": 5,
+        }),
+        serde_json::json!({
+            "type": "text-input", "question": "?", "pattern": "v[0-9]+",
+            "deadline_seconds": 5, "default_choice": "latest",
+        }),
+    ] {
+        assert!(parse(card.clone()).is_err(), "{}", card);
+    }
+
+    let tag = serde_json::json!({
+        "type": "text-input", "question": "?", "pattern": "v[0-9]+",
+        "deadline_seconds": 5, "default_choice": "v2",
+    });
+    assert_eq!(parse(tag).unwrap().unwrap().answer["text"], "v2");
+}
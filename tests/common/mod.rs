@@ -0,0 +1,316 @@
+//! Shared harness for integration tests: a private `dbus-daemon --session`
+//! bus with the xnotid server attached to it.
+
+#![allow(dead_code)]
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use xnotid::config::Config;
+use xnotid::dbus_server::{self, DbusSignal, NotificationRow, UiCommand};
+use xnotid::frontend::{self, Frontend};
+use xnotid::notification::LogEntry;
+use xnotid::store::{SharedStore, Store};
+use zbus::export::futures_core::Stream;
+use zbus::zvariant::Value;
+use zbus::{connection, proxy, Connection};
+
+pub const SIGNAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_replied(&self, id: u32, text: &str) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.xnotid.Control",
+    default_service = "org.xnotid.Control",
+    default_path = "/org/xnotid/Control"
+)]
+pub trait Control {
+    fn toggle_center(&self) -> zbus::Result<()>;
+    fn show_center(&self) -> zbus::Result<()>;
+    fn hide_center(&self) -> zbus::Result<()>;
+    fn set_dnd(&self, on: bool) -> zbus::Result<()>;
+    fn get_dnd(&self) -> zbus::Result<bool>;
+    fn dismiss_all(&self) -> zbus::Result<()>;
+    fn dismiss(&self, id: u32) -> zbus::Result<()>;
+    fn invoke_action(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+    fn reply(&self, id: u32, text: &str) -> zbus::Result<()>;
+    fn list_notifications(&self) -> zbus::Result<Vec<NotificationRow>>;
+    fn get_notification(&self, id: u32) -> zbus::Result<HashMap<String, zbus::zvariant::OwnedValue>>;
+    fn reload_config(&self) -> zbus::Result<()>;
+    fn pause(&self) -> zbus::Result<()>;
+    fn resume(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn dnd(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn count(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn center_visible(&self) -> zbus::Result<bool>;
+
+    #[zbus(signal, name = "DndChanged")]
+    fn signal_dnd_changed(&self, dnd: bool) -> zbus::Result<()>;
+    #[zbus(signal, name = "CountChanged")]
+    fn signal_count_changed(&self, count: u32) -> zbus::Result<()>;
+    #[zbus(signal, name = "CenterVisibleChanged")]
+    fn signal_center_visible_changed(&self, visible: bool) -> zbus::Result<()>;
+}
+
+/// A throwaway session bus owned by the test.
+pub struct PrivateBus {
+    child: Child,
+    pub address: String,
+    pub dir: PathBuf,
+}
+
+impl PrivateBus {
+    pub fn start() -> Self {
+        let dir = std::env::temp_dir().join(format!("xnotid-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create test dir");
+
+        let mut child = Command::new("dbus-daemon")
+            .arg("--session")
+            .arg("--nofork")
+            .arg("--print-address")
+            .arg(format!("--address=unix:path={}", dir.join("bus").display()))
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("dbus-daemon must be installed to run integration tests");
+
+        let stdout = child.stdout.take().expect("dbus-daemon stdout");
+        let mut address = String::new();
+        BufReader::new(stdout)
+            .read_line(&mut address)
+            .expect("read dbus-daemon address");
+
+        Self {
+            child,
+            address: address.trim().to_string(),
+            dir,
+        }
+    }
+
+    pub async fn connect(&self) -> Connection {
+        connection::Builder::address(self.address.as_str())
+            .expect("valid bus address")
+            .build()
+            .await
+            .expect("connect to private bus")
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// What the UI would have shown after one refresh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    pub popups: Vec<u32>,
+    pub center: Vec<u32>,
+    pub replaced: Vec<u32>,
+}
+
+/// Recording stand-in for the GTK `Ui`.
+pub struct FakeUi {
+    store: SharedStore,
+    pub frames: RefCell<Vec<Frame>>,
+    pub center_toggles: Cell<u32>,
+    pub config_applies: Cell<u32>,
+    pub quits: Cell<u32>,
+}
+
+impl FakeUi {
+    pub fn new(store: SharedStore) -> Self {
+        Self {
+            store,
+            frames: RefCell::new(Vec::new()),
+            center_toggles: Cell::new(0),
+            config_applies: Cell::new(0),
+            quits: Cell::new(0),
+        }
+    }
+
+    pub fn last_frame(&self) -> Frame {
+        self.frames.borrow().last().cloned().unwrap_or_default()
+    }
+}
+
+impl Frontend for FakeUi {
+    fn refresh(&self) {
+        let mut store = self.store.lock().unwrap();
+        let replaced = store.take_replaced_ids();
+        let frame = Frame {
+            popups: store.visible_popups().iter().map(|n| n.id).collect(),
+            center: store.all_notifications().iter().map(|n| n.id).collect(),
+            replaced,
+        };
+        self.frames.borrow_mut().push(frame);
+    }
+
+    fn toggle_center(&self) {
+        self.center_toggles.set(self.center_toggles.get() + 1);
+        let mut store = self.store.lock().unwrap();
+        let visible = !store.center_visible;
+        store.set_center_visible(visible);
+    }
+
+    fn show_center(&self) {
+        self.store.lock().unwrap().set_center_visible(true);
+    }
+
+    fn hide_center(&self) {
+        self.store.lock().unwrap().set_center_visible(false);
+    }
+
+    fn apply_config(&self) {
+        self.config_applies.set(self.config_applies.get() + 1);
+    }
+
+    fn quit(&self) {
+        self.quits.set(self.quits.get() + 1);
+    }
+}
+
+/// The daemon side (store + D-Bus server + fake UI) plus a client connection.
+pub struct TestDaemon {
+    pub store: SharedStore,
+    pub ui: FakeUi,
+    pub server: Connection,
+    pub client: Connection,
+    pub log_path: PathBuf,
+    changes: Receiver<()>,
+    commands: Receiver<UiCommand>,
+    pub bus: PrivateBus,
+}
+
+impl TestDaemon {
+    pub async fn start() -> Self {
+        Self::start_with(Config::default()).await
+    }
+
+    pub async fn start_with(mut config: Config) -> Self {
+        let bus = PrivateBus::start();
+        let log_path = bus.dir.join("notifications.jsonl");
+        config.log_path = log_path.to_string_lossy().into_owned();
+        config.state_path = bus.dir.join("state.json").to_string_lossy().into_owned();
+
+        let store = Store::new_shared(config);
+        let ui = FakeUi::new(store.clone());
+        let changes = frontend::connect_store(&store);
+        let (signal_tx, signal_rx) = tokio::sync::mpsc::unbounded_channel::<DbusSignal>();
+        let (cmd_tx, commands) = mpsc::channel::<UiCommand>();
+        store.lock().unwrap().signal_tx = Some(signal_tx);
+
+        let server =
+            dbus_server::serve_on(bus.connect().await, store.clone(), cmd_tx, signal_rx, false)
+                .await
+                .expect("start xnotid on private bus");
+        let client = bus.connect().await;
+
+        Self {
+            store,
+            ui,
+            server,
+            client,
+            log_path,
+            changes,
+            commands,
+            bus,
+        }
+    }
+
+    /// Deliver pending store changes and commands to the fake UI, the way
+    /// the GTK main loop does in the daemon.
+    pub fn pump(&self) -> Frame {
+        frontend::pump(&self.ui, &self.changes, &self.commands);
+        self.ui.last_frame()
+    }
+
+    pub async fn proxy(&self) -> NotificationsProxy<'_> {
+        NotificationsProxy::new(&self.client)
+            .await
+            .expect("notifications proxy")
+    }
+
+    pub async fn control(&self) -> ControlProxy<'_> {
+        ControlProxy::new(&self.client).await.expect("control proxy")
+    }
+
+    /// Sends a plain notification with no actions or hints.
+    pub async fn notify(&self, summary: &str, replaces_id: u32) -> u32 {
+        self.notify_with(summary, replaces_id, HashMap::new()).await
+    }
+
+    /// Sends a notification with the given hints.
+    pub async fn notify_with(
+        &self,
+        summary: &str,
+        replaces_id: u32,
+        hints: HashMap<&str, Value<'_>>,
+    ) -> u32 {
+        self.proxy()
+            .await
+            .notify("test", replaces_id, "", summary, "", &[], hints, -1)
+            .await
+            .expect("Notify")
+    }
+
+    /// All records written to the JSONL log so far.
+    pub fn log_entries(&self) -> Vec<LogEntry> {
+        std::fs::read_to_string(&self.log_path)
+            .unwrap_or_default()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("valid log line"))
+            .collect()
+    }
+}
+
+/// Waits for the next item of a signal stream, failing the test on timeout.
+pub async fn next_signal<S: Stream + Unpin>(stream: &mut S) -> S::Item {
+    let next = std::future::poll_fn(|cx| std::pin::Pin::new(&mut *stream).poll_next(cx));
+    tokio::time::timeout(SIGNAL_TIMEOUT, next)
+        .await
+        .expect("timed out waiting for signal")
+        .expect("signal stream ended")
+}
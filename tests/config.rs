@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+use xnotid::cli::{self, Mode, Options};
+use xnotid::config::{Config, ConfigError, Overrides, PositionX, PositionY};
+
+fn errors(text: &str) -> Vec<ConfigError> {
+    Config::parse(text).expect_err(text)
+}
+
+#[test]
+fn valid_config_parses() {
+    let config =
+        Config::parse("position_x: center\nposition_y: bottom\npopup_width: 320\n").unwrap();
+    assert_eq!(config.position_x, PositionX::Center);
+    assert_eq!(config.position_y, PositionY::Bottom);
+    assert_eq!(config.popup_width, 320);
+
+    let empty = Config::parse("# nothing here yet\n\n").unwrap();
+    assert_eq!(empty.position_x, PositionX::Right);
+}
+
+#[test]
+fn misspellings_are_located_and_corrected() {
+    let errs = errors("max_visible: 2\npoup_width: 300\n");
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].line, Some(2));
+    assert_eq!(errs[0].column, Some(1));
+    assert_eq!(errs[0].message, "unknown field `poup_width`, did you mean `popup_width`?");
+
+    let errs = errors("position_x: rihgt\n");
+    assert_eq!(errs[0].line, Some(1));
+    assert!(errs[0].message.ends_with("did you mean `right`?"), "{}", errs[0].message);
+
+    let errs = errors("rules:\n  - match: { ap_name: mail }\n");
+    assert_eq!(errs[0].line, Some(2));
+    assert!(errs[0].message.contains("did you mean `app_name`?"), "{}", errs[0].message);
+
+    // Nothing close: the full list of names is kept
+    let errs = errors("frobnicate: 1\n");
+    assert!(errs[0].message.contains("expected one of"), "{}", errs[0].message);
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    let errs = errors("popup_width: 0\nspacing: -4\nmax_visible: 0\nscroll_speed: 0.0\n");
+    let found: Vec<(Option<usize>, &str)> = errs
+        .iter()
+        .map(|e| (e.line, e.message.split(' ').next().unwrap()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (Some(1), "`popup_width`"),
+            (Some(3), "`max_visible`"),
+            (Some(2), "`spacing`"),
+            (Some(4), "`scroll_speed`"),
+        ]
+    );
+}
+
+#[test]
+fn list_entries_are_pointed_at() {
+    let text = "\
+dnd_schedule:
+  - start: \"22:00\"
+    end: \"07:00\"
+  - days: [mon, funday]
+    start: \"25:00\"
+    end: \"08:00\"
+rules:
+  - match: { summary: \"(unclosed\" }
+";
+    let errs = errors(text);
+    let at: Vec<(Option<usize>, Option<usize>)> = errs.iter().map(|e| (e.line, e.column)).collect();
+    assert_eq!(at, vec![(Some(4), Some(3)), (Some(4), Some(3)), (Some(8), Some(3))]);
+    assert!(errs[0].message.starts_with("`dnd_schedule[1]`"));
+    assert!(errs[2].message.starts_with("`rules[0]` has an invalid pattern"));
+}
+
+#[test]
+fn report_prefixes_the_path() {
+    let errs = errors("popup_width: -1\n");
+    let report = ConfigError::report(&PathBuf::from("/etc/xnotid.yaml"), &errs);
+    assert_eq!(report, "/etc/xnotid.yaml:1:1: `popup_width` must be greater than 0, got -1");
+}
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn check_config_mode() {
+    let mode = |line: &str| cli::parse(&args(line)).map(|options| options.mode);
+    assert_eq!(mode(""), Ok(Mode::Run));
+    assert_eq!(mode("--check-config"), Ok(Mode::CheckConfig(None)));
+    assert_eq!(mode("--check-config --no-tray"), Ok(Mode::CheckConfig(None)));
+    assert_eq!(
+        mode("--check-config=/tmp/c.yaml"),
+        Ok(Mode::CheckConfig(Some("/tmp/c.yaml".into())))
+    );
+    assert_eq!(mode("--check-config /tmp/c.yaml"), mode("--check-config=/tmp/c.yaml"));
+    assert!(mode("--frobnicate").is_err());
+    assert!(mode("--no-tray=yes").is_err());
+
+    let dir = std::env::temp_dir().join(format!("xnotid-check-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.yaml");
+
+    std::fs::write(&path, "max_visible: 5\n").unwrap();
+    assert!(cli::check_config(Some(path.clone())).unwrap().ends_with(": OK"));
+
+    std::fs::write(&path, "max_visible: 5\nslot_hieght: 60\n").unwrap();
+    let report = cli::check_config(Some(path.clone())).unwrap_err();
+    assert!(report.starts_with(&format!("{}:2:1: ", path.display())), "{report}");
+
+    assert!(cli::check_config(Some(dir.join("missing.yaml"))).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn daemon_options_parse() {
+    let options = cli::parse(&args(
+        "--config /tmp/test.yaml --style /tmp/test.css --log-path /tmp/log.jsonl \
+         --profile work --no-tray --replace",
+    ))
+    .unwrap();
+    assert_eq!(
+        options,
+        Options {
+            mode: Mode::Run,
+            overrides: Overrides {
+                config: Some("/tmp/test.yaml".into()),
+                style: Some("/tmp/test.css".into()),
+                log_path: Some("/tmp/log.jsonl".into()),
+                profile: Some("work".into()),
+            },
+            no_tray: true,
+            replace: true,
+        }
+    );
+
+    for line in ["--config", "--profile ../etc", "--profile a/b", "-p"] {
+        assert!(cli::parse(&args(line)).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn profiles_and_overrides_pick_paths() {
+    let work = Overrides { profile: Some("work".into()), ..Overrides::default() };
+    assert!(work.config_path().ends_with("xnotid/profiles/work/config.yaml"));
+    assert!(work.css_path().ends_with("xnotid/profiles/work/style.css"));
+    assert!(work.data_dir().ends_with("xnotid/profiles/work"));
+
+    let plain = Overrides::default();
+    assert!(plain.config_path().ends_with("xnotid/config.yaml"));
+    assert!(plain.data_dir().ends_with("xnotid"));
+
+    // The command line wins over the environment, field by field
+    let cli = Overrides { style: Some("/tmp/cli.css".into()), ..Overrides::default() };
+    let env = Overrides {
+        style: Some("/tmp/env.css".into()),
+        config: Some("/tmp/env.yaml".into()),
+        ..work
+    };
+    let merged = cli.or(env);
+    assert_eq!(merged.css_path(), std::path::PathBuf::from("/tmp/cli.css"));
+    assert_eq!(merged.config_path(), std::path::PathBuf::from("/tmp/env.yaml"));
+    assert_eq!(merged.profile.as_deref(), Some("work"));
+}
@@ -0,0 +1,251 @@
+mod common;
+
+use common::{next_signal, TestDaemon};
+use std::collections::HashMap;
+use xnotid::config::Config;
+use xnotid::notification::{CloseReason, Urgency};
+use zbus::zvariant::Value;
+
+async fn send_with_actions(daemon: &TestDaemon, summary: &str, actions: &[&str]) -> u32 {
+    daemon
+        .proxy()
+        .await
+        .notify("app", 0, "", summary, "body text", actions, HashMap::new(), -1)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn center_visibility_is_reported() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+    let mut changes = control.receive_signal_center_visible_changed().await.unwrap();
+
+    control.show_center().await.unwrap();
+    daemon.pump();
+    // The first announcement may be the initial state
+    loop {
+        if next_signal(&mut changes).await.args().unwrap().visible {
+            break;
+        }
+    }
+    assert!(control.center_visible().await.unwrap());
+
+    control.hide_center().await.unwrap();
+    daemon.pump();
+    assert!(!next_signal(&mut changes).await.args().unwrap().visible);
+    assert!(!daemon.store.lock().unwrap().center_visible);
+}
+
+#[tokio::test]
+async fn dnd_can_be_set_and_read() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+    let mut changes = control.receive_signal_dnd_changed().await.unwrap();
+
+    control.set_dnd(true).await.unwrap();
+    assert!(control.get_dnd().await.unwrap());
+    loop {
+        if next_signal(&mut changes).await.args().unwrap().dnd {
+            break;
+        }
+    }
+    assert!(daemon.store.lock().unwrap().dnd);
+
+    control.set_dnd(false).await.unwrap();
+    assert!(!next_signal(&mut changes).await.args().unwrap().dnd);
+    assert!(!control.get_dnd().await.unwrap());
+}
+
+#[tokio::test]
+async fn list_and_get_notifications() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+    let first = send_with_actions(&daemon, "first", &["open", "Open"]).await;
+    let hints = HashMap::from([("urgency", Value::U8(2)), ("x-group", Value::from("g"))]);
+    let second = daemon.notify_with("second", 0, hints).await;
+
+    let list = control.list_notifications().await.unwrap();
+    let summary: Vec<(u32, &str, &str)> = list
+        .iter()
+        .map(|(id, _, summary, urgency, _)| (*id, summary.as_str(), urgency.as_str()))
+        .collect();
+    assert_eq!(summary, vec![(second, "second", "critical"), (first, "first", "normal")]);
+    assert!(list.iter().all(|(_, _, _, _, age)| *age < 60));
+
+    let fields = control.get_notification(first).await.unwrap();
+    let text = |key: &str| String::try_from(fields[key].clone()).unwrap();
+    assert_eq!(text("summary"), "first");
+    assert_eq!(text("body"), "body text");
+    assert_eq!(text("urgency"), "normal");
+    let actions: Vec<String> = fields["actions"].clone().try_into().unwrap();
+    assert_eq!(actions, vec!["open", "Open"]);
+
+    let grouped = control.get_notification(second).await.unwrap();
+    assert_eq!(String::try_from(grouped["group"].clone()).unwrap(), "g");
+
+    let err = control.get_notification(999).await.unwrap_err();
+    assert!(err.to_string().contains("999"), "{err}");
+}
+
+#[tokio::test]
+async fn dismiss_and_invoke_action() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+    let mut actions = proxy.receive_action_invoked().await.unwrap();
+
+    let dismissed = daemon.notify("dismiss me", 0).await;
+    control.dismiss(dismissed).await.unwrap();
+    let signal = next_signal(&mut closed).await;
+    let args = signal.args().unwrap();
+    assert_eq!((args.id, args.reason), (dismissed, CloseReason::Dismissed as u32));
+    assert!(control.dismiss(dismissed).await.is_err());
+
+    let actionable = send_with_actions(&daemon, "act", &["yes", "Yes"]).await;
+    assert!(control.invoke_action(actionable, "no").await.is_err());
+    control.invoke_action(actionable, "yes").await.unwrap();
+    let action = next_signal(&mut actions).await;
+    let args = action.args().unwrap();
+    assert_eq!((args.id, args.action_key), (actionable, "yes"));
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&actionable));
+}
+
+#[tokio::test]
+async fn resident_notifications_survive_actions() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+    let mut actions = daemon.proxy().await.receive_action_invoked().await.unwrap();
+
+    let hints = HashMap::from([("resident", Value::Bool(true))]);
+    let id = daemon
+        .proxy()
+        .await
+        .notify("player", 0, "", "Now playing", "", &["next", "Next"], hints, -1)
+        .await
+        .unwrap();
+
+    control.invoke_action(id, "next").await.unwrap();
+    let action = next_signal(&mut actions).await;
+    assert_eq!(action.args().unwrap().action_key, "next");
+    control.invoke_action(id, "next").await.unwrap();
+    assert!(daemon.store.lock().unwrap().notifications.contains_key(&id));
+}
+
+#[tokio::test]
+async fn dismiss_all_updates_count() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+    let mut counts = control.receive_signal_count_changed().await.unwrap();
+
+    daemon.notify("one", 0).await;
+    daemon.notify("two", 0).await;
+    loop {
+        if next_signal(&mut counts).await.args().unwrap().count == 2 {
+            break;
+        }
+    }
+
+    control.dismiss_all().await.unwrap();
+    assert_eq!(next_signal(&mut counts).await.args().unwrap().count, 0);
+    assert_eq!(control.count().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn pause_holds_popups_until_resume() {
+    let daemon = TestDaemon::start().await;
+    let control = daemon.control().await;
+
+    let shown = daemon.notify("before pause", 0).await;
+    assert_eq!(daemon.pump().popups, vec![shown]);
+
+    control.pause().await.unwrap();
+    let held = daemon.notify("while paused", 0).await;
+    let frame = daemon.pump();
+    assert!(frame.popups.is_empty());
+    assert_eq!(frame.center, vec![held, shown]);
+    assert!(control.paused().await.unwrap());
+
+    control.resume().await.unwrap();
+    assert_eq!(daemon.pump().popups, vec![held, shown]);
+}
+
+#[tokio::test]
+async fn set_config_applies_new_rules() {
+    let daemon = TestDaemon::start().await;
+    let config: Config = serde_yaml::from_str(
+        "rules:\n  - match: { app_name: test }\n    set: { urgency: low }\nmax_visible: 1\n",
+    )
+    .unwrap();
+    {
+        let mut store = daemon.store.lock().unwrap();
+        let paths = (store.config.log_path.clone(), store.config.state_path.clone());
+        store.set_config(Config {
+            log_path: paths.0,
+            state_path: paths.1,
+            ..config
+        });
+    }
+
+    let id = daemon.notify("after reload", 0).await;
+    let store = daemon.store.lock().unwrap();
+    assert_eq!(store.notifications[&id].urgency, Urgency::Low);
+    assert_eq!(store.config.max_visible, 1);
+}
+
+#[tokio::test]
+async fn config_errors_are_reported_until_fixed() {
+    let daemon = TestDaemon::start().await;
+    let config = daemon.store.lock().unwrap().config.clone();
+
+    let first = {
+        let mut store = daemon.store.lock().unwrap();
+        store.report_config_error("config.yaml: invalid type at line 3");
+        store.report_config_error("config.yaml: invalid type at line 4");
+        store.notify_change();
+        let center = store.all_notifications();
+        assert_eq!(center.len(), 1, "a newer error replaces the old one");
+        assert_eq!(center[0].app_name, "xnotid");
+        assert!(center[0].body.contains("line 4"));
+        center[0].id
+    };
+    assert_eq!(daemon.pump().center, vec![first]);
+
+    daemon.store.lock().unwrap().set_config(config);
+    assert!(daemon.store.lock().unwrap().all_notifications().is_empty());
+}
+
+#[tokio::test]
+async fn inline_replies_are_signalled() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let control = daemon.control().await;
+    let mut replies = proxy.receive_notification_replied().await.unwrap();
+    let mut actions = proxy.receive_action_invoked().await.unwrap();
+
+    let caps = proxy.get_capabilities().await.unwrap();
+    assert!(caps.iter().any(|c| c == "inline-reply"));
+
+    let hints = HashMap::from([("x-kde-reply-placeholder-text", Value::from("Reply to Ana"))]);
+    let id = proxy
+        .notify("chat", 0, "", "Ana", "lunch?", &["inline-reply", "Reply"], hints, -1)
+        .await
+        .unwrap();
+    let plain = send_with_actions(&daemon, "no reply", &["ok", "OK"]).await;
+    assert!(control.reply(plain, "sure").await.is_err());
+    {
+        let store = daemon.store.lock().unwrap();
+        let noti = &store.notifications[&id];
+        assert_eq!(noti.reply_action().map(|a| a.label.as_str()), Some("Reply"));
+        assert_eq!(noti.reply_placeholder.as_deref(), Some("Reply to Ana"));
+    }
+
+    control.reply(id, "sure, 12:30").await.unwrap();
+    let reply = next_signal(&mut replies).await;
+    let args = reply.args().unwrap();
+    assert_eq!((args.id, args.text), (id, "sure, 12:30"));
+    let action = next_signal(&mut actions).await;
+    assert_eq!(action.args().unwrap().action_key, "inline-reply");
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&id));
+}
@@ -0,0 +1,383 @@
+mod common;
+
+use common::{PrivateBus, TestDaemon};
+use xnotid::ctl::{self, Command, CtlError, DndOp, HintValue, SendArgs};
+use xnotid::notification::{CARD_HINT, Urgency};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+async fn run(daemon: &TestDaemon, line: &str) -> Result<String, CtlError> {
+    let command = ctl::parse(&args(line))?;
+    let mut out = Vec::new();
+    ctl::run(command, Some(&daemon.client), &mut out).await?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn send_maps_notify_send_flags() {
+    let command = ctl::parse(&args(
+        "send -u critical -t 5000 -a mail -A open=Open -A Later -h int:value:42 \
+         --group inbox --css-class=alert -e New Hello",
+    ))
+    .unwrap();
+    let Command::Send(send) = command else {
+        panic!("expected send, got {:?}", command);
+    };
+
+    assert_eq!(send.app_name, "mail");
+    assert_eq!(send.summary, "New");
+    assert_eq!(send.body, "Hello");
+    assert_eq!(send.expire_timeout, 5000);
+    assert_eq!(
+        send.actions,
+        vec![("open".to_string(), "Open".to_string()), ("1".to_string(), "Later".to_string())]
+    );
+    for hint in [
+        ("urgency", HintValue::Byte(2)),
+        ("value", HintValue::Int(42)),
+        ("x-group", HintValue::Str("inbox".into())),
+        ("x-css-class", HintValue::Str("alert".into())),
+        ("transient", HintValue::Bool(true)),
+    ] {
+        assert!(
+            send.hints.contains(&(hint.0.to_string(), hint.1.clone())),
+            "missing hint {:?}",
+            hint
+        );
+    }
+}
+
+#[test]
+fn bad_arguments_are_usage_errors() {
+    for line in [
+        "",
+        "frobnicate",
+        "dnd maybe",
+        "dismiss",
+        "dismiss abc",
+        "action 3",
+        "send",
+        "send -h int:value:nope hi",
+        "send --card {\"type\":\"nope\"} hi",
+        "list --yaml",
+    ] {
+        let err = ctl::parse(&args(line)).expect_err(line);
+        assert_eq!(err.exit_code(), ctl::EXIT_USAGE, "{:?}", line);
+    }
+    assert!(matches!(ctl::parse(&args("dnd")), Ok(Command::Dnd(DndOp::Status))));
+}
+
+#[tokio::test]
+async fn missing_daemon_is_reported() {
+    let bus = PrivateBus::start();
+    let conn = bus.connect().await;
+
+    for line in ["toggle", "dnd status", "send hello"] {
+        let command = ctl::parse(&args(line)).unwrap();
+        let err = ctl::run(command, Some(&conn), &mut Vec::new())
+            .await
+            .expect_err(line);
+        assert_eq!(err.exit_code(), ctl::EXIT_NOT_RUNNING, "{:?}", line);
+    }
+}
+
+#[tokio::test]
+async fn dnd_and_list_round_trip() {
+    let daemon = TestDaemon::start().await;
+
+    assert_eq!(run(&daemon, "dnd status").await.unwrap(), "off\n");
+    run(&daemon, "dnd on").await.unwrap();
+    assert!(daemon.store.lock().unwrap().dnd);
+    assert_eq!(run(&daemon, "dnd toggle").await.unwrap(), "off\n");
+    assert!(!daemon.store.lock().unwrap().dnd);
+
+    let id: u32 = run(&daemon, "send -p -u low -a build Compiled")
+        .await
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let list: serde_json::Value =
+        serde_json::from_str(&run(&daemon, "list --json").await.unwrap()).unwrap();
+    assert_eq!(list[0]["id"], id);
+    assert_eq!(list[0]["app_name"], "build");
+    assert_eq!(list[0]["urgency"], "low");
+
+    run(&daemon, &format!("dismiss {}", id)).await.unwrap();
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&id));
+    let err = run(&daemon, &format!("dismiss {}", id)).await.unwrap_err();
+    assert_eq!(err.exit_code(), ctl::EXIT_FAILURE);
+}
+
+#[tokio::test]
+async fn send_delivers_cards() {
+    let daemon = TestDaemon::start().await;
+    let command = ctl::parse(&[
+        "send".to_string(),
+        "--card".to_string(),
+        r#"{"type":"permission","question":"Deploy?"}"#.to_string(),
+        "Deploy".to_string(),
+    ])
+    .unwrap();
+    ctl::run(command, Some(&daemon.client), &mut Vec::new())
+        .await
+        .unwrap();
+
+    let store = daemon.store.lock().unwrap();
+    let noti = store.all_notifications()[0];
+    assert!(noti.card.is_some());
+    // The question doubles as the body for daemons without cards
+    assert_eq!(noti.body, "Deploy?");
+    assert!(noti.acknowledge_to_dismiss);
+    assert_eq!(noti.urgency, Urgency::Normal);
+}
+
+#[tokio::test]
+async fn history_greps_the_log() {
+    let daemon = TestDaemon::start().await;
+    daemon.notify("Build failed", 0).await;
+    daemon.notify("Lunch", 0).await;
+    daemon.notify("Build passed", 0).await;
+
+    let out = run(
+        &daemon,
+        &format!("history --grep build --log {}", daemon.log_path.display()),
+    )
+    .await
+    .unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("Build failed"));
+    assert!(lines[1].ends_with("Build passed"));
+}
+
+/// The envelope sent in the `x-xnotid-card` hint
+fn card_hint(send: &SendArgs) -> serde_json::Value {
+    let hint = send.hints.iter().find(|(name, _)| name == CARD_HINT);
+    let Some((_, HintValue::Str(envelope))) = hint else {
+        panic!("no card hint");
+    };
+    serde_json::from_str(envelope).unwrap()
+}
+
+#[test]
+fn ask_builds_cards_from_flags() {
+    let Ok(Command::Ask(ask)) = ctl::parse(&args(
+        "ask --timeout 30 --choice a=Alpha --choice b --allow-other Pick",
+    )) else {
+        panic!("expected ask");
+    };
+    assert_eq!(ask.timeout, Some(std::time::Duration::from_secs(30)));
+    let envelope = card_hint(&ask.send);
+    assert_eq!(envelope["xnotid_card"], "v2");
+    assert_eq!(ask.send.body, "Pick");
+    let card = &envelope["card"];
+    assert_eq!(card["type"], "multiple-choice");
+    assert_eq!(card["question"], "Pick");
+    assert_eq!(card["choices"][1]["label"], "b");
+    assert_eq!(card["allow_other"], true);
+
+    let Ok(Command::Ask(ask)) = ctl::parse(&args("ask Deploy Ship?")) else {
+        panic!("expected ask");
+    };
+    let card = &card_hint(&ask.send)["card"];
+    assert_eq!(card["type"], "permission");
+    assert_eq!(card["question"], "Ship?");
+    assert_eq!(ask.send.summary, "Deploy");
+
+    let Ok(Command::Ask(ask)) = ctl::parse(&args("ask --deny-label Never --allow-always Ship?"))
+    else {
+        panic!("expected ask");
+    };
+    let card = &card_hint(&ask.send)["card"];
+    assert_eq!(card["deny_label"], "Never");
+    assert_eq!(card["allow_always"], true);
+}
+
+#[test]
+fn invalid_cards_are_rejected() {
+    for card in [
+        r#"{"type":"text-input","question":"Tag?","pattern":"v[0-9"}"#,
+        r#"{"type":"rating","question":"How was it?","max":20}"#,
+        r#"{"type":"slider","question":"Volume?","min":10,"max":0}"#,
+        r#"{"type":"datetime","question":"When?","mode":"date","value":"tomorrow"}"#,
+        r#"{"type":"multiple-choice","question":"?","choices":[],"allow_other":true,"min_selected":2}"#,
+        r#"{"type":"multiple-choice","question":"?","choices":[],"mode":"single","max_selected":2}"#,
+    ] {
+        let line = ["send".to_string(), "--card".to_string(), card.to_string(), "x".to_string()];
+        let err = ctl::parse(&line).unwrap_err();
+        assert_eq!(err.exit_code(), ctl::EXIT_USAGE, "{}", card);
+    }
+
+    let card = r#"{"type":"datetime","question":"When?","mode":"time","value":"09:30"}"#;
+    let line = ["send".to_string(), "--card".to_string(), card.to_string(), "x".to_string()];
+    assert!(ctl::parse(&line).is_ok());
+}
+
+/// Start `xnotidctl ask` in the background and return it with the card's id.
+async fn ask(daemon: &TestDaemon, line: &str) -> (tokio::task::JoinHandle<(i32, String)>, u32) {
+    let command = ctl::parse(&args(line)).unwrap();
+    let conn = daemon.client.clone();
+    let task = tokio::spawn(async move {
+        let mut out = Vec::new();
+        let code = ctl::run(command, Some(&conn), &mut out).await.unwrap();
+        (code, String::from_utf8(out).unwrap())
+    });
+
+    for _ in 0..200 {
+        if let Some(&id) = daemon.store.lock().unwrap().notifications.keys().next() {
+            return (task, id);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("card never arrived");
+}
+
+#[tokio::test]
+async fn ask_reports_the_answer() {
+    let daemon = TestDaemon::start().await;
+
+    let (task, id) = ask(&daemon, "ask Deploy?").await;
+    daemon.store.lock().unwrap().invoke_action(id, "allow");
+    assert_eq!(task.await.unwrap(), (0, "allow\n".to_string()));
+
+    let (task, id) = ask(&daemon, "ask --json --choice a=Alpha --choice b=Beta Pick").await;
+    let payload = r#"{"type":"multiple-choice","selected":[{"id":"b","label":"Beta"}],"other":"gamma"}"#;
+    daemon.store.lock().unwrap().invoke_action(id, payload);
+    let (code, out) = task.await.unwrap();
+    assert_eq!(code, 0);
+    let answer: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(answer["id"], id);
+    assert_eq!(answer["result"], "choice");
+    assert_eq!(answer["selected"][0]["id"], "b");
+    assert_eq!(answer["other"], "gamma");
+}
+
+#[tokio::test]
+async fn ask_reports_card_replies() {
+    let daemon = TestDaemon::start().await;
+
+    let (task, id) = ask(&daemon, "ask --allow-always Deploy?").await;
+    let payload = r#"{"type":"permission","decision":"allow_always","reason":null}"#;
+    daemon.store.lock().unwrap().invoke_action(id, payload);
+    assert_eq!(task.await.unwrap(), (0, "allow_always\n".to_string()));
+
+    let (task, id) = ask(&daemon, "ask Deploy?").await;
+    let payload = r#"{"type":"permission","decision":"deny","reason":"not on a Friday"}"#;
+    daemon.store.lock().unwrap().invoke_action(id, payload);
+    let expected = "deny\nnot on a Friday\n".to_string();
+    assert_eq!(task.await.unwrap(), (ctl::EXIT_DENIED, expected));
+
+    let (task, id) = ask(&daemon, "ask Deploy?").await;
+    let payload = r#"{"type":"confirm","confirmed":false,"label":"Cancel"}"#;
+    daemon.store.lock().unwrap().invoke_action(id, payload);
+    assert_eq!(task.await.unwrap(), (ctl::EXIT_DENIED, "cancel\n".to_string()));
+
+    let (task, id) = ask(&daemon, "ask --json Deploy?").await;
+    let payload = r#"{"type":"rating","value":4,"max":5}"#;
+    daemon.store.lock().unwrap().invoke_action(id, payload);
+    let (code, out) = task.await.unwrap();
+    assert_eq!(code, 0);
+    let answer: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(answer["id"], id);
+    assert_eq!(answer["result"], "rating");
+    assert_eq!(answer["value"], 4);
+    assert_eq!(answer["max"], 5);
+}
+
+#[tokio::test]
+async fn ask_reports_deny_and_timeout() {
+    let daemon = TestDaemon::start().await;
+
+    let (task, id) = ask(&daemon, "ask Deploy?").await;
+    daemon.control().await.dismiss(id).await.unwrap();
+    assert_eq!(task.await.unwrap(), (ctl::EXIT_DENIED, "deny\n".to_string()));
+
+    let (task, id) = ask(&daemon, "ask --timeout 1 Deploy?").await;
+    assert_eq!(task.await.unwrap(), (ctl::EXIT_TIMEOUT, "timeout\n".to_string()));
+    // The unanswered card is taken down
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&id));
+}
+
+#[tokio::test]
+async fn ask_exits_when_the_daemon_goes_away() {
+    let daemon = TestDaemon::start().await;
+    let command = ctl::parse(&args("ask Deploy?")).unwrap();
+    let conn = daemon.client.clone();
+    let task = tokio::spawn(async move { ctl::run(command, Some(&conn), &mut Vec::new()).await });
+    for _ in 0..200 {
+        if !daemon.store.lock().unwrap().notifications.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    daemon.server.release_name("org.freedesktop.Notifications").await.unwrap();
+    let err = task.await.unwrap().unwrap_err();
+    assert_eq!(err.exit_code(), ctl::EXIT_NOT_RUNNING);
+}
+
+/// Forwards each complete written line to the test, parsed as JSON
+struct LineSink {
+    tx: tokio::sync::mpsc::UnboundedSender<serde_json::Value>,
+    buf: Vec<u8>,
+}
+
+impl std::io::Write for LineSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        while let Some(nl) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=nl).collect();
+            let _ = self.tx.send(serde_json::from_slice(&line).expect("JSON status line"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn watch_streams_status_changes() {
+    let daemon = TestDaemon::start().await;
+    let (tx, mut lines) = tokio::sync::mpsc::unbounded_channel();
+    let conn = daemon.client.clone();
+    let task = tokio::spawn(async move {
+        ctl::run(Command::Watch, Some(&conn), &mut LineSink { tx, buf: Vec::new() }).await
+    });
+
+    let mut next = async || {
+        tokio::time::timeout(common::SIGNAL_TIMEOUT, lines.recv())
+            .await
+            .expect("timed out waiting for status")
+            .expect("watch ended")
+    };
+
+    let status = next().await;
+    assert_eq!(status["count"], 0);
+    assert!(status["latest"].is_null());
+
+    let id = daemon
+        .notify_with("Disk full", 0, [("urgency", 2u8.into())].into())
+        .await;
+    let status = loop {
+        let status = next().await;
+        if status["count"] == 1 {
+            break status;
+        }
+    };
+    assert_eq!(status["urgency"]["critical"], 1);
+    assert_eq!(status["latest"]["id"], id);
+    assert_eq!(status["latest"]["summary"], "Disk full");
+    assert_eq!(status["latest"]["urgency"], "critical");
+
+    daemon.control().await.set_dnd(true).await.unwrap();
+    assert_eq!(next().await["dnd"], true);
+
+    daemon.server.release_name("org.xnotid.Control").await.unwrap();
+    let err = task.await.unwrap().unwrap_err();
+    assert_eq!(err.exit_code(), ctl::EXIT_NOT_RUNNING);
+}
@@ -0,0 +1,294 @@
+mod common;
+
+use common::{Frame, TestDaemon};
+use std::collections::HashMap;
+use xnotid::notification::{CloseReason, ImageData, Urgency};
+use zbus::zvariant::Value;
+
+#[tokio::test]
+async fn notify_assigns_ids_and_refreshes_ui() {
+    let daemon = TestDaemon::start().await;
+
+    let first = daemon.notify("one", 0).await;
+    let second = daemon.notify("two", 0).await;
+    assert_eq!((first, second), (1, 2));
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![second, first]);
+    assert_eq!(frame.center, vec![second, first]);
+}
+
+#[tokio::test]
+async fn close_notification_removes_from_store_and_ui() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+
+    let keep = daemon.notify("keep", 0).await;
+    let gone = daemon.notify("gone", 0).await;
+    proxy.close_notification(gone).await.unwrap();
+
+    assert_eq!(daemon.store.lock().unwrap().order, vec![keep]);
+    assert_eq!(daemon.pump().popups, vec![keep]);
+
+    // Closing an unknown ID is a no-op
+    proxy.close_notification(gone).await.unwrap();
+    assert_eq!(daemon.store.lock().unwrap().order, vec![keep]);
+}
+
+#[tokio::test]
+async fn replaces_id_updates_in_place() {
+    let daemon = TestDaemon::start().await;
+
+    let older = daemon.notify("older", 0).await;
+    let id = daemon.notify("v1", 0).await;
+    daemon.pump();
+
+    assert_eq!(daemon.notify("v2", id).await, id);
+    let frame = daemon.pump();
+    assert_eq!(
+        frame,
+        Frame {
+            popups: vec![id, older],
+            center: vec![id, older],
+            replaced: vec![id],
+        }
+    );
+
+    let store = daemon.store.lock().unwrap();
+    assert_eq!(store.notifications.len(), 2);
+    assert_eq!(store.notifications[&id].summary, "v2");
+}
+
+#[tokio::test]
+async fn unknown_replaces_id_allocates_new_id() {
+    let daemon = TestDaemon::start().await;
+
+    let id = daemon.notify("fresh", 42).await;
+    assert_eq!(id, 1);
+    assert!(daemon.pump().replaced.is_empty());
+}
+
+#[tokio::test]
+async fn hints_are_parsed() {
+    let daemon = TestDaemon::start().await;
+
+    let hints = HashMap::from([
+        ("urgency", Value::U8(2)),
+        ("x-group", Value::from("build")),
+        ("x-acknowledge", Value::Bool(true)),
+        ("desktop-entry", Value::from("org.example.App")),
+        ("value", Value::I32(40)),
+        ("x-css-class", Value::from("deploy")),
+        ("image-path", Value::from("/tmp/icon.png")),
+        ("x-custom", Value::from("kept")),
+    ]);
+    let id = daemon.notify_with("hinted", 0, hints).await;
+
+    let store = daemon.store.lock().unwrap();
+    let noti = &store.notifications[&id];
+    assert_eq!(noti.urgency, Urgency::Critical);
+    assert_eq!(noti.group.as_deref(), Some("build"));
+    assert!(noti.acknowledge_to_dismiss);
+    assert_eq!(noti.desktop_entry.as_deref(), Some("org.example.App"));
+    assert_eq!(noti.progress, Some(40));
+    assert_eq!(noti.css_class.as_deref(), Some("deploy"));
+    assert!(matches!(&noti.image, ImageData::Path(p) if p == "/tmp/icon.png"));
+    assert!(noti.hints.contains_key("x-custom"));
+    assert!(!noti.hints.contains_key("urgency"));
+    assert_eq!(store.groups["build"], vec![id]);
+}
+
+#[tokio::test]
+async fn spec_hints_are_parsed() {
+    let daemon = TestDaemon::start().await;
+
+    let hints = HashMap::from([
+        ("resident", Value::Bool(true)),
+        ("action-icons", Value::Bool(true)),
+        ("category", Value::from("email.arrived")),
+        ("sender-pid", Value::I64(4242)),
+        ("sound-name", Value::from("message-new-email")),
+        ("suppress-sound", Value::Bool(true)),
+    ]);
+    let id = daemon.notify_with("hinted", 0, hints).await;
+
+    let store = daemon.store.lock().unwrap();
+    let noti = &store.notifications[&id];
+    assert!(noti.resident);
+    assert!(noti.action_icons);
+    assert_eq!(noti.category.as_deref(), Some("email.arrived"));
+    assert_eq!(noti.category_classes(), vec!["category-email", "category-email-arrived"]);
+    assert_eq!(noti.sender_pid, Some(4242));
+    assert_eq!(noti.sound.name.as_deref(), Some("message-new-email"));
+    assert!(noti.sound.file.is_none());
+    assert!(noti.sound.suppress);
+    assert_eq!(noti.hints["sender-pid"], "4242");
+}
+
+#[tokio::test]
+async fn transient_notifications_skip_the_center() {
+    let daemon = TestDaemon::start().await;
+
+    let hints = HashMap::from([("transient", Value::Bool(true))]);
+    let id = daemon.notify_with("fleeting", 0, hints).await;
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![id]);
+    assert!(frame.center.is_empty());
+}
+
+#[tokio::test]
+async fn dnd_only_lets_critical_popups_through() {
+    let daemon = TestDaemon::start().await;
+    daemon.store.lock().unwrap().dnd = true;
+
+    let normal = daemon.notify("normal", 0).await;
+    let hints = HashMap::from([("urgency", Value::U8(2))]);
+    let critical = daemon.notify_with("critical", 0, hints).await;
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![critical]);
+    assert_eq!(frame.center, vec![critical, normal]);
+}
+
+#[tokio::test]
+async fn events_are_logged_as_jsonl() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+
+    let id = daemon.notify("logged", 0).await;
+    proxy.close_notification(id).await.unwrap();
+
+    let entries = daemon.log_entries();
+    let events: Vec<&str> = entries.iter().map(|e| e.event.as_str()).collect();
+    assert_eq!(events, vec!["received", "closed"]);
+    assert_eq!(entries[0].uuid, entries[1].uuid);
+    assert_eq!(entries[0].notification_id, Some(id));
+    assert_eq!(entries[0].summary.as_deref(), Some("logged"));
+    assert_eq!(entries[0].urgency.as_deref(), Some("Normal"));
+    assert!(entries[1].body.is_none());
+}
+
+#[tokio::test]
+async fn control_toggle_center_reaches_ui() {
+    let daemon = TestDaemon::start().await;
+
+    daemon.control().await.toggle_center().await.unwrap();
+    daemon.pump();
+    assert_eq!(daemon.ui.center_toggles.get(), 1);
+}
+
+#[tokio::test]
+async fn capabilities_are_advertised() {
+    let daemon = TestDaemon::start().await;
+
+    let caps = daemon.proxy().await.get_capabilities().await.unwrap();
+    for cap in ["body", "actions", "action-icons", "persistence", "x-xnotid-card-version"] {
+        assert!(caps.iter().any(|c| c == cap), "missing capability {cap}");
+    }
+    assert!(!caps.iter().any(|c| c == "body-images"));
+}
+
+#[tokio::test]
+async fn popups_are_capped_at_max_visible() {
+    let config = xnotid::config::Config {
+        max_visible: 2,
+        ..Default::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+
+    let ids = [
+        daemon.notify("one", 0).await,
+        daemon.notify("two", 0).await,
+        daemon.notify("three", 0).await,
+    ];
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![ids[1], ids[0]]);
+    assert_eq!(frame.center.len(), 3);
+    assert_eq!(daemon.store.lock().unwrap().queued_count(), 1);
+}
+
+#[tokio::test]
+async fn overflow_queue_promotes_critical_first() {
+    let config = xnotid::config::Config {
+        max_visible: 1,
+        ..Default::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+    let proxy = daemon.proxy().await;
+
+    let shown = daemon.notify("shown", 0).await;
+    let low = daemon
+        .notify_with("low", 0, HashMap::from([("urgency", Value::U8(0))]))
+        .await;
+    let normal = daemon.notify("normal", 0).await;
+    let critical = daemon
+        .notify_with("critical", 0, HashMap::from([("urgency", Value::U8(2))]))
+        .await;
+    assert_eq!(daemon.store.lock().unwrap().popup_queue, vec![critical, normal, low]);
+
+    proxy.close_notification(shown).await.unwrap();
+    assert_eq!(daemon.pump().popups, vec![critical]);
+
+    proxy.close_notification(critical).await.unwrap();
+    assert_eq!(daemon.pump().popups, vec![normal]);
+    assert_eq!(daemon.store.lock().unwrap().queued_count(), 1);
+}
+
+#[tokio::test]
+async fn same_group_arrivals_collapse_into_one_popup_slot() {
+    let daemon = TestDaemon::start().await;
+    let group = || HashMap::from([("x-group", Value::from("chat"))]);
+
+    let other = daemon.notify("other", 0).await;
+    let first = daemon.notify_with("msg 1", 0, group()).await;
+    let second = daemon.notify_with("msg 2", 0, group()).await;
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![second, other]);
+    assert_eq!(frame.center, vec![second, first, other]);
+    assert_eq!(daemon.store.lock().unwrap().group_size("chat"), 2);
+}
+
+#[tokio::test]
+async fn displaced_transient_group_member_expires() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+    let group = || {
+        HashMap::from([
+            ("x-group", Value::from("volume")),
+            ("transient", Value::Bool(true)),
+        ])
+    };
+
+    let first = daemon.notify_with("40%", 0, group()).await;
+    let second = daemon.notify_with("50%", 0, group()).await;
+
+    let signal = common::next_signal(&mut closed).await;
+    let args = signal.args().unwrap();
+    assert_eq!((args.id, args.reason), (first, CloseReason::Expired as u32));
+    assert_eq!(daemon.pump().popups, vec![second]);
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&first));
+}
+
+#[tokio::test]
+async fn dismiss_group_closes_every_member() {
+    let daemon = TestDaemon::start().await;
+    let group = || HashMap::from([("x-group", Value::from("build"))]);
+
+    let other = daemon.notify("other", 0).await;
+    daemon.notify_with("step 1", 0, group()).await;
+    daemon.notify_with("step 2", 0, group()).await;
+
+    {
+        let mut store = daemon.store.lock().unwrap();
+        store.dismiss_group("build");
+        store.notify_change();
+        assert!(!store.groups.contains_key("build"));
+    }
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![other]);
+    assert_eq!(frame.center, vec![other]);
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use common::TestDaemon;
+use std::collections::HashMap;
+use xnotid::config::Config;
+use xnotid::dnd::{self, DndWindow};
+use xnotid::notification::CloseReason;
+use zbus::zvariant::Value;
+
+fn at(date: &str, time: &str) -> NaiveDateTime {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .unwrap()
+        .and_time(chrono::NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+}
+
+fn window(days: &[&str], start: &str, end: &str) -> DndWindow {
+    DndWindow {
+        days: days.iter().map(|d| d.to_string()).collect(),
+        start: start.into(),
+        end: end.into(),
+    }
+}
+
+async fn send(daemon: &TestDaemon, app: &str, urgency: u8) -> u32 {
+    let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+    daemon
+        .proxy()
+        .await
+        .notify(app, 0, "", "summary", "", &[], hints, -1)
+        .await
+        .unwrap()
+}
+
+#[test]
+fn schedule_windows() {
+    // 2026-10-16 is a Friday
+    let workday = window(&["mon", "tue", "wed", "thu", "fri"], "09:00", "17:00");
+    assert!(workday.contains(at("2026-10-16", "09:00")));
+    assert!(!workday.contains(at("2026-10-16", "17:00")));
+    assert!(!workday.contains(at("2026-10-17", "12:00")));
+
+    // Overnight window belongs to the day it starts on
+    let friday_night = window(&["friday"], "22:00", "07:00");
+    assert!(friday_night.contains(at("2026-10-16", "23:30")));
+    assert!(friday_night.contains(at("2026-10-17", "06:59")));
+    assert!(!friday_night.contains(at("2026-10-16", "06:00")));
+
+    let all_sunday = window(&["sun"], "00:00", "00:00");
+    assert!(all_sunday.contains(at("2026-10-18", "15:00")));
+    assert!(!all_sunday.contains(at("2026-10-19", "15:00")));
+
+    let every_day = window(&[], "12:00", "13:00");
+    assert!(dnd::scheduled(&[workday.clone(), every_day], at("2026-10-17", "12:30")));
+
+    assert!(!window(&["someday"], "09:00", "17:00").contains(at("2026-10-16", "10:00")));
+    assert!(!window(&[], "9am", "17:00").contains(at("2026-10-16", "10:00")));
+}
+
+#[tokio::test]
+async fn allowlist_and_blocklist() {
+    let config = Config {
+        dnd_allow_apps: vec!["Pager".into()],
+        dnd_block_apps: vec!["Spammy".into()],
+        ..Config::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+    daemon.store.lock().unwrap().set_dnd(true);
+
+    let allowed = send(&daemon, "pager", 1).await;
+    let _quiet = send(&daemon, "mail", 1).await;
+    let blocked = send(&daemon, "spammy", 2).await;
+    let critical = send(&daemon, "alarm", 2).await;
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![critical, allowed]);
+    assert!(frame.center.contains(&blocked));
+}
+
+#[tokio::test]
+async fn transient_held_back_by_dnd_expires() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+    daemon.store.lock().unwrap().set_dnd(true);
+
+    let hints = HashMap::from([("transient", Value::Bool(true))]);
+    let id = daemon.notify_with("fleeting", 0, hints).await;
+
+    let signal = common::next_signal(&mut closed).await;
+    let args = signal.args().unwrap();
+    assert_eq!((args.id, args.reason), (id, CloseReason::Expired as u32));
+    assert!(daemon.pump().popups.is_empty());
+}
+
+#[tokio::test]
+async fn summary_when_dnd_ends() {
+    let daemon = TestDaemon::start().await;
+    daemon.store.lock().unwrap().set_dnd(true);
+
+    send(&daemon, "mail", 1).await;
+    send(&daemon, "chat", 0).await;
+    send(&daemon, "mail", 1).await;
+    assert!(daemon.pump().popups.is_empty());
+
+    {
+        let mut store = daemon.store.lock().unwrap();
+        store.set_dnd(false);
+        store.notify_change();
+    }
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups.len(), 1);
+    let store = daemon.store.lock().unwrap();
+    let summary = &store.notifications[&frame.popups[0]];
+    assert_eq!(summary.app_name, "xnotid");
+    assert_eq!(summary.summary, "3 notifications while Do Not Disturb was on");
+    assert_eq!(summary.body, "mail (2), chat (1)");
+    assert!(summary.transient);
+    // Shown as a popup only, the originals stay in the center
+    assert_eq!(frame.center.len(), 3);
+}
+
+#[tokio::test]
+async fn schedule_drives_dnd() {
+    let config = Config {
+        dnd_schedule: vec![window(&[], "22:00", "07:00")],
+        ..Config::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+
+    let changed = daemon.store.lock().unwrap().refresh_dnd_at(at("2026-10-16", "23:00"));
+    assert!(changed);
+    {
+        let store = daemon.store.lock().unwrap();
+        assert!(store.dnd_active());
+        assert!(store.dnd_automatic());
+        assert!(!store.dnd);
+    }
+    send(&daemon, "mail", 1).await;
+    assert!(daemon.pump().popups.is_empty());
+
+    let mut store = daemon.store.lock().unwrap();
+    assert!(store.refresh_dnd_at(at("2026-10-17", "07:00")));
+    assert!(!store.dnd_active());
+    assert!(!store.refresh_dnd_at(at("2026-10-17", "08:00")));
+}
+
+#[tokio::test]
+async fn fullscreen_and_disabled_dnd() {
+    let config = Config {
+        dnd_fullscreen: true,
+        ..Config::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+    {
+        let mut store = daemon.store.lock().unwrap();
+        store.set_fullscreen_dnd(true);
+        assert!(store.dnd_automatic());
+        store.set_fullscreen_dnd(false);
+        assert!(!store.dnd_active());
+    }
+
+    let config = Config {
+        dnd_enabled: false,
+        dnd_fullscreen: true,
+        ..Config::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+    {
+        let mut store = daemon.store.lock().unwrap();
+        store.set_dnd(true);
+        store.set_fullscreen_dnd(true);
+        assert!(!store.dnd_active());
+    }
+    let id = send(&daemon, "mail", 1).await;
+    assert_eq!(daemon.pump().popups, vec![id]);
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use common::TestDaemon;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use xnotid::history::{self, HistoryQuery};
+use xnotid::logfile::segment_path;
+use xnotid::notification::{LogEntry, Urgency};
+use zbus::zvariant::Value;
+
+fn entry(n: usize, event: &str, app: &str, urgency: Option<&str>, at: DateTime<Utc>) -> LogEntry {
+    LogEntry {
+        uuid: format!("uuid-{}", n),
+        timestamp: at.to_rfc3339(),
+        event: event.to_string(),
+        notification_id: Some(n as u32),
+        app_name: Some(app.to_string()),
+        app_icon: None,
+        summary: Some(format!("summary {}", n)),
+        body: (event == "received").then(|| format!("body {} {}", n, "x".repeat(2048))),
+        created_at: None,
+        urgency: urgency.map(str::to_string),
+        desktop_entry: None,
+        hints: None,
+        action_key: None,
+        group: None,
+    }
+}
+
+fn write_log(dir: &std::path::Path, entries: &[LogEntry]) -> std::path::PathBuf {
+    let path = dir.join("history-test.jsonl");
+    let mut f = fs::File::create(&path).unwrap();
+    for e in entries {
+        writeln!(f, "{}", serde_json::to_string(e).unwrap()).unwrap();
+    }
+    path
+}
+
+fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("xnotid-history-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn pages_newest_first_across_chunks() {
+    let dir = scratch_dir("paging");
+    let now = Utc::now();
+    let entries: Vec<LogEntry> = (0..120)
+        .map(|n| entry(n, "received", "app", Some("Normal"), now))
+        .collect();
+    let path = write_log(&dir, &entries);
+
+    let query = HistoryQuery::default();
+    let mut seen = Vec::new();
+    let mut before = None;
+    let mut pages = 0;
+    loop {
+        let page = history::read_page(&path, &query, before, 50).unwrap();
+        seen.extend(page.entries.into_iter().map(|e| e.uuid));
+        pages += 1;
+        match page.next {
+            Some(next) => before = Some(next),
+            None => break,
+        }
+    }
+
+    let expected: Vec<String> = (0..120).rev().map(|n| format!("uuid-{}", n)).collect();
+    assert_eq!(seen, expected);
+    assert_eq!(pages, 3);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn pages_on_into_rotated_segments() {
+    let dir = scratch_dir("segments");
+    let now = Utc::now();
+    let entries: Vec<LogEntry> = (0..6)
+        .map(|n| entry(n, "received", "app", Some("Normal"), now))
+        .collect();
+    // .2.gz holds 0-1, .1 holds 2-3 and the active log 4-5
+    let path = write_log(&dir, &entries[..2]);
+    let second = segment_path(&path, 2, false);
+    fs::rename(&path, &second).unwrap();
+    let status = std::process::Command::new("gzip").arg(&second).status().unwrap();
+    assert!(status.success());
+    fs::rename(write_log(&dir, &entries[2..4]), segment_path(&path, 1, false)).unwrap();
+    write_log(&dir, &entries[4..]);
+
+    let query = HistoryQuery::default();
+    let page = history::read_page(&path, &query, None, 3).unwrap();
+    let uuids: Vec<String> = page.entries.into_iter().map(|e| e.uuid).collect();
+    assert_eq!(uuids, vec!["uuid-5", "uuid-4", "uuid-3"]);
+    let next = page.next.unwrap();
+    assert_eq!(next.segment, 1);
+
+    let page = history::read_page(&path, &query, Some(next), 3).unwrap();
+    let uuids: Vec<String> = page.entries.into_iter().map(|e| e.uuid).collect();
+    assert_eq!(uuids, vec!["uuid-2", "uuid-1", "uuid-0"]);
+    assert!(page.next.is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn filters_combine() {
+    let dir = scratch_dir("filters");
+    let now = Utc::now();
+    let old = now - Duration::days(10);
+    let entries = vec![
+        entry(0, "received", "Mail", Some("Low"), old),
+        entry(1, "received", "Chat", Some("Critical"), now),
+        entry(2, "dismissed", "Chat", None, now),
+        entry(3, "received", "mailer", Some("Normal"), now),
+    ];
+    let path = write_log(&dir, &entries);
+    let uuids = |query: HistoryQuery| -> Vec<String> {
+        history::read_page(&path, &query, None, 50)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.uuid)
+            .collect()
+    };
+
+    let by_app = HistoryQuery { app_name: Some("MAIL".into()), ..Default::default() };
+    assert_eq!(uuids(by_app), vec!["uuid-3", "uuid-0"]);
+
+    let by_text = HistoryQuery { text: Some("BODY 1".into()), ..Default::default() };
+    assert_eq!(uuids(by_text), vec!["uuid-1"]);
+
+    let by_urgency = HistoryQuery { urgency: Some("Critical".into()), ..Default::default() };
+    assert_eq!(uuids(by_urgency), vec!["uuid-1"]);
+
+    let by_event = HistoryQuery { event: Some("dismissed".into()), ..Default::default() };
+    assert_eq!(uuids(by_event), vec!["uuid-2"]);
+
+    let recent = HistoryQuery { since: Some(now - Duration::days(1)), ..Default::default() };
+    assert_eq!(uuids(recent), vec!["uuid-3", "uuid-2", "uuid-1"]);
+
+    let older = HistoryQuery {
+        until: Some(now - Duration::days(1)),
+        app_name: Some("mail".into()),
+        ..Default::default()
+    };
+    assert_eq!(uuids(older), vec!["uuid-0"]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_log_and_garbage_lines() {
+    let dir = scratch_dir("garbage");
+    let missing = history::read_page(&dir.join("nope.jsonl"), &HistoryQuery::default(), None, 10).unwrap();
+    assert!(missing.entries.is_empty());
+    assert!(missing.next.is_none());
+
+    let path = write_log(&dir, &[entry(0, "received", "app", None, Utc::now())]);
+    let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    writeln!(f, "not json").unwrap();
+    write!(f, "{{\"truncated\":").unwrap();
+    let page = history::read_page(&path, &HistoryQuery::default(), None, 10).unwrap();
+    assert_eq!(page.entries.len(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn reshow_from_history() {
+    let daemon = TestDaemon::start().await;
+    let hints = HashMap::from([
+        ("urgency", Value::U8(2)),
+        ("x-group", Value::from("builds")),
+    ]);
+    let id = daemon.notify_with("build failed", 0, hints).await;
+    daemon.proxy().await.close_notification(id).await.unwrap();
+
+    let query = HistoryQuery { event: Some("received".into()), ..Default::default() };
+    let page = history::read_page(&daemon.log_path, &query, None, 10).unwrap();
+    assert_eq!(page.entries.len(), 1);
+
+    let reshown = {
+        let mut store = daemon.store.lock().unwrap();
+        let new_id = store.add(page.entries[0].to_notification(), 0);
+        store.notify_change();
+        new_id
+    };
+    assert_ne!(reshown, id);
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![reshown]);
+    let store = daemon.store.lock().unwrap();
+    let noti = &store.notifications[&reshown];
+    assert_eq!(noti.summary, "build failed");
+    assert_eq!(noti.urgency, Urgency::Critical);
+    assert_eq!(noti.group.as_deref(), Some("builds"));
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::TestDaemon;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use xnotid::config::Config;
+use xnotid::logfile::segment_path;
+use xnotid::notification::{LogEntry, Notification};
+use xnotid::store::Store;
+
+fn scratch_config(name: &str) -> (PathBuf, Config) {
+    let dir = std::env::temp_dir().join(format!("xnotid-logfile-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = Config {
+        log_path: dir.join("notifications.jsonl").to_string_lossy().into_owned(),
+        persist_history: false,
+        ..Config::default()
+    };
+    (dir, config)
+}
+
+fn add(store: &mut Store, app: &str, body: &str) -> u32 {
+    let noti = Notification::new(
+        0,
+        app.to_string(),
+        String::new(),
+        "summary".to_string(),
+        body.to_string(),
+        Vec::new(),
+        HashMap::new(),
+        -1,
+    );
+    let id = store.add(noti, 0);
+    store.notify_change();
+    id
+}
+
+fn records(path: &Path) -> Vec<LogEntry> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn rotates_by_size_and_keeps_limit() {
+    let (dir, mut config) = scratch_config("size");
+    config.log_rotate_size_kb = 1;
+    config.log_keep = 2;
+    let log = PathBuf::from(&config.log_path);
+    let mut store = Store::new(config);
+
+    for _ in 0..20 {
+        add(&mut store, "app", &"x".repeat(300));
+    }
+
+    assert!(fs::metadata(&log).unwrap().len() <= 1024);
+    assert!(segment_path(&log, 1, false).exists());
+    assert!(segment_path(&log, 2, false).exists());
+    assert!(!segment_path(&log, 3, false).exists());
+    for segment in [log.clone(), segment_path(&log, 1, false)] {
+        assert!(!records(&segment).is_empty());
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rotates_by_age() {
+    let (dir, mut config) = scratch_config("age");
+    config.log_rotate_days = 1;
+    let log = PathBuf::from(&config.log_path);
+    let old = LogEntry {
+        uuid: "old".into(),
+        timestamp: (Utc::now() - Duration::days(3)).to_rfc3339(),
+        event: "dismissed".into(),
+        notification_id: None,
+        app_name: None,
+        app_icon: None,
+        summary: None,
+        body: None,
+        created_at: None,
+        urgency: None,
+        desktop_entry: None,
+        hints: None,
+        action_key: None,
+        group: None,
+    };
+    fs::write(&log, format!("{}\n", serde_json::to_string(&old).unwrap())).unwrap();
+
+    let mut store = Store::new(config);
+    add(&mut store, "app", "fresh");
+
+    let rotated = records(&segment_path(&log, 1, false));
+    assert_eq!(rotated.len(), 1);
+    assert_eq!(rotated[0].uuid, "old");
+    let current = records(&log);
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].body.as_deref(), Some("fresh"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compresses_rotated_segments() {
+    let (dir, mut config) = scratch_config("gzip");
+    config.log_rotate_size_kb = 1;
+    config.log_compress = true;
+    let log = PathBuf::from(&config.log_path);
+    let mut store = Store::new(config);
+
+    for _ in 0..5 {
+        add(&mut store, "app", &"x".repeat(300));
+    }
+
+    // gzip runs in the background
+    let gzipped = segment_path(&log, 1, true);
+    for _ in 0..200 {
+        if gzipped.exists() && !segment_path(&log, 1, false).exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(gzipped.exists(), "rotated segment was not gzipped");
+    assert!(!segment_path(&log, 1, false).exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn purges_expired_records_and_segments() {
+    let (dir, mut config) = scratch_config("purge");
+    config.log_max_age_days = 7;
+    let log = PathBuf::from(&config.log_path);
+
+    let mut store = Store::new(config.clone());
+    add(&mut store, "app", "recent");
+    drop(store);
+
+    // Prepend a record from long ago and leave an old rotated segment around
+    let mut old: LogEntry = records(&log).remove(0);
+    old.uuid = "ancient".into();
+    old.timestamp = (Utc::now() - Duration::days(30)).to_rfc3339();
+    let contents = fs::read_to_string(&log).unwrap();
+    fs::write(&log, format!("{}\n{}", serde_json::to_string(&old).unwrap(), contents)).unwrap();
+    let segment = segment_path(&log, 1, false);
+    fs::write(&segment, "{}\n").unwrap();
+    let month_ago = SystemTime::now() - std::time::Duration::from_secs(30 * 24 * 60 * 60);
+    fs::File::options().write(true).open(&segment).unwrap().set_modified(month_ago).unwrap();
+
+    Store::new(config).purge_log();
+
+    let left = records(&log);
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].body.as_deref(), Some("recent"));
+    assert!(!segment.exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn redacts_body_for_configured_apps() {
+    let config = Config {
+        log_redact_apps: vec!["Secret".into()],
+        ..Config::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+    let proxy = daemon.proxy().await;
+    proxy
+        .notify("secret", 0, "", "otp", "123456", &[], HashMap::new(), -1)
+        .await
+        .unwrap();
+    proxy
+        .notify("mail", 0, "", "hi", "visible", &[], HashMap::new(), -1)
+        .await
+        .unwrap();
+
+    let bodies: Vec<Option<String>> = daemon.log_entries().into_iter().map(|e| e.body).collect();
+    assert_eq!(bodies, vec![Some("[redacted]".into()), Some("visible".into())]);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::{next_signal, TestDaemon};
+use xnotid::notification::CloseReason;
+
+async fn next_closed(stream: &mut common::NotificationClosedStream) -> (u32, u32) {
+    let signal = next_signal(stream).await;
+    let args = signal.args().expect("NotificationClosed args");
+    (args.id, args.reason)
+}
+
+#[tokio::test]
+async fn close_notification_emits_closed() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = daemon.notify("hello", 0).await;
+    proxy.close_notification(id).await.unwrap();
+
+    assert_eq!(next_closed(&mut closed).await, (id, CloseReason::Closed as u32));
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&id));
+}
+
+#[tokio::test]
+async fn timeout_expiry_emits_expired() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = daemon.notify("expiring", 0).await;
+    daemon.store.lock().unwrap().close(id, CloseReason::Expired);
+
+    assert_eq!(next_closed(&mut closed).await, (id, CloseReason::Expired as u32));
+}
+
+#[tokio::test]
+async fn click_dismissal_emits_dismissed() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = daemon.notify("clicked", 0).await;
+    daemon.store.lock().unwrap().close(id, CloseReason::Dismissed);
+
+    assert_eq!(next_closed(&mut closed).await, (id, CloseReason::Dismissed as u32));
+}
+
+#[tokio::test]
+async fn clear_all_emits_dismissed_for_each() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let first = daemon.notify("one", 0).await;
+    let second = daemon.notify("two", 0).await;
+    daemon.store.lock().unwrap().clear_all();
+
+    let mut seen = vec![next_closed(&mut closed).await, next_closed(&mut closed).await];
+    seen.sort();
+    let dismissed = CloseReason::Dismissed as u32;
+    assert_eq!(seen, vec![(first, dismissed), (second, dismissed)]);
+}
+
+#[tokio::test]
+async fn action_invocation_emits_action_then_dismissed() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut actions = proxy.receive_action_invoked().await.unwrap();
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = daemon.notify("actionable", 0).await;
+    daemon.store.lock().unwrap().invoke_action(id, "default");
+
+    let action = next_signal(&mut actions).await;
+    let args = action.args().unwrap();
+    assert_eq!((args.id, args.action_key), (id, "default"));
+    assert_eq!(next_closed(&mut closed).await, (id, CloseReason::Dismissed as u32));
+}
+
+#[tokio::test]
+async fn replacement_emits_undefined_and_keeps_id() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = daemon.notify("v1", 0).await;
+    assert_eq!(daemon.notify("v2", id).await, id);
+    assert_eq!(next_closed(&mut closed).await, (id, CloseReason::Undefined as u32));
+    assert_eq!(daemon.store.lock().unwrap().notifications[&id].summary, "v2");
+
+    // The replacement is still live and closes like any other notification.
+    proxy.close_notification(id).await.unwrap();
+    assert_eq!(next_closed(&mut closed).await, (id, CloseReason::Closed as u32));
+}
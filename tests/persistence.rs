@@ -0,0 +1,99 @@
+mod common;
+
+use common::TestDaemon;
+use std::collections::HashMap;
+use xnotid::notification::ImageData;
+use xnotid::store::Store;
+use zbus::zvariant::Value;
+
+#[tokio::test]
+async fn center_history_survives_restart() {
+    let daemon = TestDaemon::start().await;
+    let proxy = daemon.proxy().await;
+
+    let hints = HashMap::from([
+        ("image-path", Value::from("/tmp/avatar.png")),
+        ("x-group", Value::from("chat")),
+    ]);
+    let kept = proxy
+        .notify("chat", 0, "", "hello", "body", &["reply", "Reply"], hints, -1)
+        .await
+        .unwrap();
+    let dismissed = daemon.notify("dismissed", 0).await;
+    let transient = daemon
+        .notify_with("transient", 0, HashMap::from([("transient", Value::Bool(true))]))
+        .await;
+    let pixels = Value::from((1i32, 1i32, 4i32, true, 8i32, 4i32, vec![0u8, 0, 0, 255]));
+    let raw = daemon
+        .notify_with("raw image", 0, HashMap::from([("image-data", pixels)]))
+        .await;
+    let newest = daemon.notify("newest", 0).await;
+    proxy.close_notification(dismissed).await.unwrap();
+    {
+        let mut store = daemon.store.lock().unwrap();
+        store.dnd = true;
+        store.notify_change();
+        // The saved state is written in the background; wait for it
+        store.flush();
+    }
+
+    let config = daemon.store.lock().unwrap().config.clone();
+    let mut restored = Store::new(config);
+    restored.restore_state();
+
+    assert_eq!(restored.order, vec![newest, raw, kept]);
+    assert!(!restored.notifications.contains_key(&transient));
+    assert!(restored.dnd);
+    assert_eq!(restored.next_id, newest + 1);
+    assert_eq!(restored.groups["chat"], vec![kept]);
+    assert!(restored.popup_slots.is_empty());
+
+    let noti = &restored.notifications[&kept];
+    assert_eq!(noti.body, "body");
+    assert_eq!(noti.actions[0].key, "reply");
+    assert!(matches!(&noti.image, ImageData::Path(p) if p == "/tmp/avatar.png"));
+    // Raw pixels come back byte for byte
+    assert!(matches!(
+        &restored.notifications[&raw].image,
+        ImageData::Raw { width: 1, height: 1, channels: 4, data, .. } if data == &[0, 0, 0, 255]
+    ));
+}
+
+#[tokio::test]
+async fn restored_cards_lose_their_deadline() {
+    let daemon = TestDaemon::start().await;
+    let card = r#"{"xnotid_card":"v2","card":{"type":"confirm","question":"Ship?",
+        "deadline_seconds":5,"default_choice":true}}"#;
+    let id = daemon
+        .notify_with("Deploy", 0, HashMap::from([("x-xnotid-card", Value::from(card))]))
+        .await;
+    assert!(daemon.store.lock().unwrap().notifications[&id].card_deadline.is_some());
+    daemon.store.lock().unwrap().flush();
+
+    let config = daemon.store.lock().unwrap().config.clone();
+    let mut restored = Store::new(config);
+    restored.restore_state();
+
+    let noti = &restored.notifications[&id];
+    assert!(noti.card.is_some());
+    assert!(noti.card_deadline.is_none());
+    // Long past the old deadline, nothing answers or closes it
+    let later = noti.created_at + chrono::TimeDelta::hours(1);
+    assert!(!restored.expire_cards_at(later));
+    assert!(restored.notifications.contains_key(&id));
+}
+
+#[tokio::test]
+async fn persistence_can_be_disabled() {
+    let config = xnotid::config::Config {
+        persist_history: false,
+        ..Default::default()
+    };
+    let daemon = TestDaemon::start_with(config).await;
+
+    daemon.notify("not saved", 0).await;
+    daemon.store.lock().unwrap().flush();
+
+    let state_path = daemon.store.lock().unwrap().config.state_path.clone();
+    assert!(!std::path::Path::new(&state_path).exists());
+}
@@ -0,0 +1,45 @@
+use xnotid::config::PositionX::{self, Center, Left, Right};
+use xnotid::config::PositionY::{self, Bottom, Top};
+use xnotid::config::Config;
+use xnotid::placement::{window_origin, Area};
+
+fn config(position_x: PositionX, position_y: PositionY) -> Config {
+    Config {
+        position_x,
+        position_y,
+        margin_top: 10,
+        margin_right: 20,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn origin_honours_every_corner() {
+    let area = Area::new(1920, 30, 1920, 1050);
+    let (w, h) = (400, 200);
+
+    assert_eq!(window_origin(&config(Right, Top), &area, w, h), (3420, 40));
+    assert_eq!(window_origin(&config(Left, Top), &area, w, h), (1940, 40));
+    assert_eq!(window_origin(&config(Center, Top), &area, w, h), (2680, 40));
+    assert_eq!(window_origin(&config(Right, Bottom), &area, w, h), (3420, 870));
+    assert_eq!(window_origin(&config(Left, Bottom), &area, w, h), (1940, 870));
+}
+
+#[test]
+fn bottom_anchored_windows_grow_upward() {
+    let area = Area::new(0, 0, 1920, 1080);
+    let cfg = config(Right, Bottom);
+
+    let (_, short_y) = window_origin(&cfg, &area, 400, 100);
+    let (_, tall_y) = window_origin(&cfg, &area, 400, 300);
+    assert_eq!(short_y + 100, tall_y + 300);
+}
+
+#[test]
+fn workarea_is_clipped_to_monitor() {
+    let monitor = Area::new(1920, 0, 1920, 1080);
+    let workarea = Area::new(0, 24, 3840, 1056);
+
+    assert_eq!(workarea.intersect(&monitor), Some(Area::new(1920, 24, 1920, 1056)));
+    assert_eq!(Area::new(0, 0, 10, 10).intersect(&monitor), None);
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use common::TestDaemon;
+use std::collections::HashMap;
+use xnotid::config::Config;
+use xnotid::notification::{CloseReason, Urgency};
+use zbus::zvariant::Value;
+
+fn config_with_rules(yaml: &str) -> Config {
+    serde_yaml::from_str(yaml).expect("valid config")
+}
+
+async fn send(daemon: &TestDaemon, app: &str, summary: &str, body: &str, hints: HashMap<&str, Value<'_>>) -> u32 {
+    daemon
+        .proxy()
+        .await
+        .notify(app, 0, "", summary, body, &[], hints, -1)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn overrides_apply_to_matching_app() {
+    let daemon = TestDaemon::start_with(config_with_rules(
+        r#"
+rules:
+  - name: pager
+    match:
+      app_name: PagerDuty
+    set:
+      urgency: critical
+      timeout: 0
+      css_class: page
+      group: oncall
+      acknowledge: true
+"#,
+    ))
+    .await;
+
+    let paged = send(&daemon, "pagerduty", "db down", "", HashMap::new()).await;
+    let other = send(&daemon, "mail", "hello", "", HashMap::new()).await;
+
+    let store = daemon.store.lock().unwrap();
+    let noti = &store.notifications[&paged];
+    assert_eq!(noti.urgency, Urgency::Critical);
+    assert_eq!(noti.timeout, 0);
+    assert_eq!(noti.css_class.as_deref(), Some("page"));
+    assert_eq!(noti.group.as_deref(), Some("oncall"));
+    assert!(noti.acknowledge_to_dismiss);
+    assert_eq!(store.groups["oncall"], vec![paged]);
+
+    let untouched = &store.notifications[&other];
+    assert_eq!(untouched.urgency, Urgency::Normal);
+    assert!(untouched.css_class.is_none());
+}
+
+#[tokio::test]
+async fn regex_hint_and_stop() {
+    let daemon = TestDaemon::start_with(config_with_rules(
+        r#"
+rules:
+  - match:
+      summary: "(?i)^build (failed|broken)"
+      hints:
+        category: "^ci\\."
+    set:
+      urgency: critical
+    stop: true
+  - match:
+      body: "nightly"
+    set:
+      urgency: low
+      css_class: nightly
+"#,
+    ))
+    .await;
+
+    let ci = HashMap::from([("category", Value::from("ci.failure"))]);
+    let failed = send(&daemon, "ci", "Build FAILED", "nightly run", ci).await;
+    let chat = HashMap::from([("category", Value::from("im.received"))]);
+    let not_ci = send(&daemon, "ci", "build failed", "nightly run", chat).await;
+
+    let store = daemon.store.lock().unwrap();
+    // First rule stopped evaluation before the body rule
+    assert_eq!(store.notifications[&failed].urgency, Urgency::Critical);
+    assert!(store.notifications[&failed].css_class.is_none());
+    // Hint didn't match, so only the body rule applied
+    assert_eq!(store.notifications[&not_ci].urgency, Urgency::Low);
+    assert_eq!(store.notifications[&not_ci].css_class.as_deref(), Some("nightly"));
+}
+
+#[tokio::test]
+async fn category_matches_class_or_exact() {
+    let daemon = TestDaemon::start_with(config_with_rules(
+        r#"
+rules:
+  - match: { category: Email }
+    set: { css_class: mail }
+  - match: { category: im.received }
+    set: { urgency: low }
+"#,
+    ))
+    .await;
+
+    let category = |c: &'static str| HashMap::from([("category", Value::from(c))]);
+    let mail = send(&daemon, "mutt", "new mail", "", category("email.arrived")).await;
+    let im = send(&daemon, "chat", "hi", "", category("im.received")).await;
+    let im_error = send(&daemon, "chat", "offline", "", category("im.error")).await;
+    let none = send(&daemon, "chat", "plain", "", HashMap::new()).await;
+
+    let store = daemon.store.lock().unwrap();
+    assert_eq!(store.notifications[&mail].css_class.as_deref(), Some("mail"));
+    assert_eq!(store.notifications[&im].urgency, Urgency::Low);
+    assert_eq!(store.notifications[&im_error].urgency, Urgency::Normal);
+    assert!(store.notifications[&none].css_class.is_none());
+}
+
+#[tokio::test]
+async fn popup_history_and_log_suppression() {
+    let daemon = TestDaemon::start_with(config_with_rules(
+        r#"
+rules:
+  - match: { app_name: quiet }
+    set: { no_popup: true }
+  - match: { app_name: fleeting }
+    set: { no_history: true }
+  - match: { app_name: secret }
+    set: { no_log: true }
+  - match: { app_name: nowhere }
+    set: { no_popup: true, no_history: true }
+"#,
+    ))
+    .await;
+
+    let quiet = send(&daemon, "quiet", "q", "", HashMap::new()).await;
+    let fleeting = send(&daemon, "fleeting", "f", "", HashMap::new()).await;
+    let secret = send(&daemon, "secret", "s", "", HashMap::new()).await;
+    let nowhere = send(&daemon, "nowhere", "n", "", HashMap::new()).await;
+
+    let frame = daemon.pump();
+    assert_eq!(frame.popups, vec![secret, fleeting]);
+    assert_eq!(frame.center, vec![secret, quiet]);
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&nowhere));
+
+    let logged: Vec<String> = daemon
+        .log_entries()
+        .into_iter()
+        .filter(|e| e.event == "received")
+        .filter_map(|e| e.app_name)
+        .collect();
+    assert_eq!(logged, vec!["quiet", "fleeting", "nowhere"]);
+}
+
+#[tokio::test]
+async fn rules_apply_to_replacements() {
+    let daemon = TestDaemon::start_with(config_with_rules(
+        r#"
+rules:
+  - match: { summary: "^quiet" }
+    set: { no_popup: true }
+  - match: { summary: "^gone" }
+    set: { no_popup: true, no_history: true }
+"#,
+    ))
+    .await;
+    let proxy = daemon.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = send(&daemon, "app", "loud", "", HashMap::new()).await;
+    assert_eq!(daemon.pump().popups, vec![id]);
+    let replaced = proxy
+        .notify("app", id, "", "quiet now", "", &[], HashMap::new(), -1)
+        .await
+        .unwrap();
+    assert_eq!(replaced, id);
+    let frame = daemon.pump();
+    assert!(frame.popups.is_empty());
+    assert_eq!(frame.center, vec![id]);
+
+    // Replacing it with something that has nowhere to go expires it
+    proxy
+        .notify("app", id, "", "gone", "", &[], HashMap::new(), -1)
+        .await
+        .unwrap();
+    let reasons = [
+        common::next_signal(&mut closed).await.args().unwrap().reason,
+        common::next_signal(&mut closed).await.args().unwrap().reason,
+        common::next_signal(&mut closed).await.args().unwrap().reason,
+    ];
+    let undefined = CloseReason::Undefined as u32;
+    assert_eq!(reasons, [undefined, undefined, CloseReason::Expired as u32]);
+    assert!(!daemon.store.lock().unwrap().notifications.contains_key(&id));
+}
+
+#[tokio::test]
+async fn invalid_rule_is_skipped() {
+    let daemon = TestDaemon::start_with(config_with_rules(
+        r#"
+rules:
+  - match: { summary: "([unclosed" }
+    set: { urgency: low }
+  - match: { summary: "ok" }
+    set: { urgency: critical }
+"#,
+    ))
+    .await;
+
+    let id = send(&daemon, "app", "ok", "", HashMap::new()).await;
+    assert_eq!(daemon.store.lock().unwrap().notifications[&id].urgency, Urgency::Critical);
+}
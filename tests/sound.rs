@@ -0,0 +1,129 @@
+mod common;
+
+use common::TestDaemon;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use xnotid::config::Config;
+use xnotid::sound::{self, SoundPlayer};
+use zbus::zvariant::Value;
+
+/// Remembers what it was asked to play
+#[derive(Clone, Default)]
+struct RecordingPlayer(Arc<Mutex<Vec<PathBuf>>>);
+
+impl SoundPlayer for RecordingPlayer {
+    fn play(&mut self, _config: &Config, file: &Path) {
+        self.0.lock().unwrap().push(file.to_path_buf());
+    }
+}
+
+async fn start(config: Config) -> (TestDaemon, RecordingPlayer) {
+    let daemon = TestDaemon::start_with(config).await;
+    let player = RecordingPlayer::default();
+    daemon.store.lock().unwrap().set_sound_player(Box::new(player.clone()));
+    (daemon, player)
+}
+
+async fn send(daemon: &TestDaemon, app: &str, hints: HashMap<&str, Value<'_>>) -> u32 {
+    daemon
+        .proxy()
+        .await
+        .notify(app, 0, "", "summary", "", &[], hints, -1)
+        .await
+        .unwrap()
+}
+
+fn played(player: &RecordingPlayer) -> Vec<PathBuf> {
+    player.0.lock().unwrap().clone()
+}
+
+#[tokio::test]
+async fn hints_app_overrides_and_urgency_defaults() {
+    let config = Config {
+        sound_min_interval_ms: 0,
+        sound_normal: Some("/sounds/normal.oga".into()),
+        sound_apps: HashMap::from([
+            ("Chat".to_string(), "/sounds/chat.oga".to_string()),
+            ("Music".to_string(), String::new()),
+        ]),
+        ..Config::default()
+    };
+    let (daemon, player) = start(config).await;
+
+    send(&daemon, "mail", HashMap::from([("sound-file", Value::from("/tmp/ding.wav"))])).await;
+    send(&daemon, "mail", HashMap::new()).await;
+    send(&daemon, "chat", HashMap::from([("sound-file", Value::from("/tmp/ding.wav"))])).await;
+    send(&daemon, "music", HashMap::new()).await;
+    send(&daemon, "mail", HashMap::from([("suppress-sound", Value::Bool(true))])).await;
+    send(&daemon, "low", HashMap::from([("urgency", Value::U8(0))])).await;
+
+    assert_eq!(
+        played(&player),
+        ["/tmp/ding.wav", "/sounds/normal.oga", "/sounds/chat.oga"].map(PathBuf::from)
+    );
+}
+
+#[tokio::test]
+async fn dnd_and_rate_limit_silence() {
+    let config = Config {
+        sound_min_interval_ms: 60_000,
+        sound_normal: Some("/sounds/normal.oga".into()),
+        sound_critical: Some("/sounds/critical.oga".into()),
+        ..Config::default()
+    };
+    let (daemon, player) = start(config).await;
+
+    daemon.store.lock().unwrap().set_dnd(true);
+    send(&daemon, "mail", HashMap::new()).await;
+    assert!(played(&player).is_empty());
+    // Critical popups get through DND, and so does their sound
+    send(&daemon, "mail", HashMap::from([("urgency", Value::U8(2))])).await;
+    assert_eq!(played(&player), vec![PathBuf::from("/sounds/critical.oga")]);
+
+    daemon.store.lock().unwrap().set_dnd(false);
+    send(&daemon, "mail", HashMap::new()).await;
+    assert_eq!(played(&player), vec![PathBuf::from("/sounds/critical.oga")]);
+}
+
+#[tokio::test]
+async fn replacements_are_silent() {
+    let config = Config {
+        sound_min_interval_ms: 0,
+        sound_normal: Some("/sounds/normal.oga".into()),
+        ..Config::default()
+    };
+    let (daemon, player) = start(config).await;
+
+    let id = daemon.notify("downloading 10%", 0).await;
+    daemon.notify("downloading 20%", id).await;
+    assert_eq!(played(&player), vec![PathBuf::from("/sounds/normal.oga")]);
+}
+
+#[test]
+fn theme_lookup() {
+    let root = std::env::temp_dir().join(format!("xnotid-sounds-{}", uuid::Uuid::new_v4()));
+    let write = |path: &str, contents: &str| {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+    write("custom/index.theme", "[Sound Theme]\nInherits=base\n");
+    write("custom/stereo/bell.wav", "");
+    write("base/stereo/message-new-instant.oga", "");
+    write("freedesktop/stereo/message.oga", "");
+    write("freedesktop/stereo/bell.oga", "");
+    let dirs = [root.clone()];
+
+    let found = |name: &str| sound::lookup_in(&dirs, "custom", name);
+    assert_eq!(found("bell"), Some(root.join("custom/stereo/bell.wav")));
+    assert_eq!(
+        found("message-new-instant"),
+        Some(root.join("base/stereo/message-new-instant.oga"))
+    );
+    // Less specific names are tried once the full name isn't found anywhere
+    assert_eq!(found("message-new-email"), Some(root.join("freedesktop/stereo/message.oga")));
+    assert_eq!(found("dialog-warning"), None);
+
+    let _ = std::fs::remove_dir_all(&root);
+}